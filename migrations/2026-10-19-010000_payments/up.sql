@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE payments (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    event_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    amount BIGINT NOT NULL,
+    kind STRING NOT NULL DEFAULT 'payment',
+    method STRING NOT NULL,
+    reference STRING NOT NULL DEFAULT '',
+    status STRING NOT NULL DEFAULT 'pending',
+    paid_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    confirmed_at TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (event_id, user_id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "payment_webhook_events";
+ALTER TABLE payments DROP COLUMN IF EXISTS provider_ref;
+ALTER TABLE event_members DROP COLUMN IF EXISTS payment_status;
+ALTER TABLE events DROP COLUMN IF EXISTS cancelled;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE events ADD cancelled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE event_members ADD payment_status STRING NOT NULL DEFAULT 'unpaid';
+ALTER TABLE payments ADD provider_ref STRING UNIQUE;
+
+CREATE TABLE payment_webhook_events (
+    provider STRING NOT NULL,
+    event_ref STRING NOT NULL,
+    received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider, event_ref)
+);
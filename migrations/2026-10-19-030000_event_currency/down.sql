@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+-- Amounts go back to whole units, which only holds for currencies with two
+-- decimals; refuse rather than scale zero-decimal amounts down by 100.
+SELECT CASE
+    WHEN EXISTS (SELECT 1 FROM events WHERE currency NOT IN ('TWD', 'USD', 'EUR', 'GBP', 'HKD', 'CNY', 'SGD'))
+    THEN crdb_internal.force_error('22023', 'events in zero-decimal currencies (JPY, KRW) would lose their amounts; move or delete them first')
+    ELSE 0
+END;
+UPDATE payments SET amount = amount / 100;
+UPDATE event_members SET amount = amount / 100;
+UPDATE events SET min_amount = min_amount / 100, max_amount = max_amount / 100;
+ALTER TABLE events DROP COLUMN IF EXISTS currency;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Amounts were whole NT dollars; they are now minor units of the event currency.
+ALTER TABLE events ADD currency STRING NOT NULL DEFAULT 'TWD';
+UPDATE events SET min_amount = min_amount * 100, max_amount = max_amount * 100;
+UPDATE event_members SET amount = amount * 100;
+UPDATE payments SET amount = amount * 100;
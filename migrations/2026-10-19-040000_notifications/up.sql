@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE notifications (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    event_id UUID NOT NULL,
+    kind STRING NOT NULL,
+    message STRING NOT NULL,
+    read_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (user_id, created_at DESC),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE
+);
+
+-- event_id is the nil uuid for a user's default across all events.
+CREATE TABLE notification_preferences (
+    user_id UUID NOT NULL,
+    event_id UUID NOT NULL,
+    kind STRING NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    PRIMARY KEY (user_id, event_id, kind),
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE events DROP COLUMN IF EXISTS reminder_sent_at;
+ALTER TABLE notifications DROP COLUMN IF EXISTS emailed;
+DROP TABLE IF EXISTS "email_outbox";
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE email_outbox (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    kind STRING NOT NULL,
+    event_id UUID,
+    payload STRING NOT NULL DEFAULT '',
+    status STRING NOT NULL DEFAULT 'pending',
+    attempts INT4 NOT NULL DEFAULT 0,
+    last_error STRING,
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (status, run_at),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE
+);
+
+ALTER TABLE notifications ADD emailed BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE events ADD reminder_sent_at TIMESTAMP;
@@ -0,0 +1,36 @@
+-- Your SQL goes here
+ALTER TABLE users ADD is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- event_id is NULL for global endpoints, which only admins can register.
+CREATE TABLE webhook_endpoints (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    event_id UUID,
+    url STRING NOT NULL,
+    secret STRING NOT NULL,
+    event_types STRING[] NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (event_id),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE
+);
+
+CREATE TABLE webhook_deliveries (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    endpoint_id UUID NOT NULL,
+    event_type STRING NOT NULL,
+    payload STRING NOT NULL,
+    status STRING NOT NULL DEFAULT 'pending',
+    attempts INT4 NOT NULL DEFAULT 0,
+    response_status INT4,
+    last_error STRING,
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (status, run_at),
+    INDEX (endpoint_id, created_at DESC),
+    FOREIGN KEY (endpoint_id) REFERENCES webhook_endpoints (id) ON DELETE CASCADE
+);
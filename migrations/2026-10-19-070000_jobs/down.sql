@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE job_schedules;
+DROP TABLE jobs;
+ALTER TABLE events DROP COLUMN deleted_at;
+ALTER TABLE events DROP COLUMN expired;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+ALTER TABLE events ADD expired BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE events ADD deleted_at TIMESTAMP;
+
+CREATE TABLE jobs (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    kind STRING NOT NULL,
+    payload STRING NOT NULL DEFAULT '',
+    status STRING NOT NULL DEFAULT 'queued',
+    attempts INT4 NOT NULL DEFAULT 0,
+    max_attempts INT4 NOT NULL DEFAULT 5,
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_by STRING,
+    locked_at TIMESTAMP,
+    last_error STRING,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (status, run_at),
+    INDEX (kind, status)
+);
+
+-- One row per recurring job. Replicas race for due rows with SKIP LOCKED,
+-- so each tick enqueues a single job no matter how many are running.
+CREATE TABLE job_schedules (
+    name STRING NOT NULL,
+    cron STRING NOT NULL,
+    next_run_at TIMESTAMP NOT NULL,
+    last_run_at TIMESTAMP,
+    PRIMARY KEY (name)
+);
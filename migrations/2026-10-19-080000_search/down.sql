@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS event_comments_search_vector_idx;
+ALTER TABLE event_comments DROP COLUMN search_vector;
+DROP INDEX IF EXISTS events_search_vector_idx;
+ALTER TABLE events DROP COLUMN search_vector;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- The 'simple' configuration doesn't stem, so it behaves the same for the
+-- mix of English and Chinese text our events use, and exists on both
+-- CockroachDB and Postgres. USING GIN is accepted by both as well.
+ALTER TABLE events ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        to_tsvector('simple', name || ' ' || category || ' ' || description)
+    ) STORED;
+CREATE INDEX events_search_vector_idx ON events USING GIN (search_vector);
+
+ALTER TABLE event_comments ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('simple', content)) STORED;
+CREATE INDEX event_comments_search_vector_idx ON event_comments USING GIN (search_vector);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS events_latitude_longitude_idx;
+ALTER TABLE events DROP CONSTRAINT events_coordinates_pair;
+ALTER TABLE events DROP CONSTRAINT events_longitude_range;
+ALTER TABLE events DROP CONSTRAINT events_latitude_range;
+ALTER TABLE events DROP COLUMN longitude;
+ALTER TABLE events DROP COLUMN latitude;
+ALTER TABLE events DROP COLUMN venue;
+ALTER TABLE events DROP COLUMN address;
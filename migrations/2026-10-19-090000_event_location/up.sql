@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE events ADD address STRING;
+ALTER TABLE events ADD venue STRING;
+ALTER TABLE events ADD latitude FLOAT8;
+ALTER TABLE events ADD longitude FLOAT8;
+ALTER TABLE events ADD CONSTRAINT events_latitude_range CHECK (latitude BETWEEN -90 AND 90);
+ALTER TABLE events ADD CONSTRAINT events_longitude_range CHECK (longitude BETWEEN -180 AND 180);
+ALTER TABLE events ADD CONSTRAINT events_coordinates_pair CHECK ((latitude IS NULL) = (longitude IS NULL));
+-- Nearby search narrows to a bounding box first: a range scan on latitude,
+-- with longitude filtered from the same index entry.
+CREATE INDEX events_latitude_longitude_idx ON events (latitude, longitude) WHERE latitude IS NOT NULL;
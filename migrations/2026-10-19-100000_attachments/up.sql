@@ -0,0 +1,25 @@
+-- Your SQL goes here
+-- Event covers, gallery images and comment attachments carry event_id
+-- (comment attachments also comment_id); avatars only belong to user_id.
+CREATE TABLE attachments (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    kind STRING NOT NULL,
+    event_id UUID,
+    comment_id UUID,
+    content_type STRING NOT NULL,
+    size INT8 NOT NULL,
+    storage_key STRING NOT NULL,
+    thumbnail_key STRING,
+    width INT4,
+    height INT4,
+    original_name STRING NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (event_id, kind, created_at),
+    INDEX (comment_id),
+    INDEX (user_id, kind, created_at DESC),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (comment_id) REFERENCES event_comments (id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE events DROP CONSTRAINT IF EXISTS events_category_fkey;
+DROP INDEX IF EXISTS events_category_idx;
+DROP TABLE IF EXISTS "categories";
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE categories (
+    slug STRING NOT NULL,
+    name STRING NOT NULL,
+    icon STRING NOT NULL DEFAULT '',
+    sort_order INT4 NOT NULL DEFAULT 0,
+    parent STRING,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (slug),
+    INDEX (parent, sort_order),
+    FOREIGN KEY (parent) REFERENCES categories (slug)
+);
+
+-- Existing free-text values are kept as slugs so events keep pointing at
+-- them; admins can give them proper names and icons afterwards.
+INSERT INTO categories (slug, name) SELECT DISTINCT category, category FROM events;
+
+CREATE INDEX events_category_idx ON events (category);
+ALTER TABLE events ADD CONSTRAINT events_category_fkey FOREIGN KEY (category) REFERENCES categories (slug);
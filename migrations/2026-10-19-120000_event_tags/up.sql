@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE event_tags (
+    event_id UUID NOT NULL,
+    tag STRING NOT NULL,
+    PRIMARY KEY (event_id, tag),
+    INDEX (tag),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE
+);
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+-- Everything a NewEvent needs except the start time; end time is kept as
+-- a duration.
+CREATE TABLE event_templates (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    title STRING NOT NULL,
+    name STRING NOT NULL,
+    description STRING NOT NULL,
+    category STRING NOT NULL,
+    min_amount INT8 NOT NULL,
+    max_amount INT8 NOT NULL,
+    currency STRING NOT NULL,
+    duration_secs INT8 NOT NULL,
+    address STRING,
+    venue STRING,
+    latitude FLOAT8,
+    longitude FLOAT8,
+    tags STRING[] NOT NULL DEFAULT ARRAY[],
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    INDEX (user_id, created_at DESC),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (category) REFERENCES categories (slug)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS event_members_checkin_token_key CASCADE;
+ALTER TABLE event_members DROP COLUMN checked_in_by;
+ALTER TABLE event_members DROP COLUMN checked_in_at;
+ALTER TABLE event_members DROP COLUMN checkin_token;
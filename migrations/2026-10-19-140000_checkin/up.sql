@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- checkin_token is handed out lazily, the first time a member asks for
+-- their QR code.
+ALTER TABLE event_members ADD checkin_token STRING;
+ALTER TABLE event_members ADD checked_in_at TIMESTAMP;
+ALTER TABLE event_members ADD checked_in_by UUID;
+CREATE UNIQUE INDEX event_members_checkin_token_key ON event_members (checkin_token);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE event_members DROP COLUMN joined_at;
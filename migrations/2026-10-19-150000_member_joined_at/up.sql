@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Existing memberships have no known join time and stay NULL.
+ALTER TABLE event_members ADD joined_at TIMESTAMP;
+ALTER TABLE event_members ALTER COLUMN joined_at SET DEFAULT CURRENT_TIMESTAMP;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE event_members ALTER COLUMN joined_at SET DEFAULT CURRENT_TIMESTAMP;
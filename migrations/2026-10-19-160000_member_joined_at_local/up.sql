@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- joined_at is set by the app on the local clock, like checked_in_at.
+ALTER TABLE event_members ALTER COLUMN joined_at DROP DEFAULT;
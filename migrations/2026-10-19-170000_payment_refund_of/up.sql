@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- At most one refund per payment.
+ALTER TABLE payments ADD refund_of UUID UNIQUE REFERENCES payments (id) ON DELETE CASCADE;
@@ -0,0 +1,450 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{JobList, JobQuery, NewCategory, UpdateCategory, JOB_STATUSES};
+use crate::db;
+use crate::categories;
+
+const DEFAULT_JOB_LIMIT: i64 = 50;
+const MAX_JOB_LIMIT: i64 = 500;
+
+#[utoipa::path(
+    tag = "admin",
+    params(JobQuery),
+    responses(
+        (status = 200, description = "Job counts and matching jobs", body = JobList),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/jobs")]
+pub async fn get_jobs(
+    query: web::Query<JobQuery>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let query = query.into_inner();
+    if let Some(status) = &query.status {
+        if !JOB_STATUSES.contains(&status.as_str()) {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: format!("Unknown job status {}", status),
+                error_code: "400".to_string(),
+            });
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !db::is_admin(&mut conn, user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_JOB_LIMIT).clamp(1, MAX_JOB_LIMIT);
+    let counts = db::count_jobs_by_status(&mut conn);
+    let jobs = db::get_jobs(&mut conn, query.status, query.kind, limit);
+
+    match (counts, jobs) {
+        (Ok(counts), Ok(jobs)) => HttpResponse::Ok().json(JobList { counts, jobs }),
+        _ => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get jobs".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    params(("job_id" = Uuid, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job queued again", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/admin/jobs/{job_id}/retry")]
+pub async fn retry_job(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !db::is_admin(&mut conn, user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    match db::retry_dead_job(&mut conn, path.0) {
+        Ok(0) => HttpResponse::NotFound().json(DefaultError {
+            message: "Dead job not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to retry job".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    request_body = NewCategory,
+    responses(
+        (status = 201, description = "Created category", body = Category),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 409, description = "Conflict", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/admin/categories")]
+pub async fn create_category(
+    mut form: web::Json<NewCategory>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !db::is_admin(&mut conn, user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    form.name = form.name.trim().to_string();
+    if let Err(e) = categories::slug_check(&form.slug).and(categories::name_check(&form.name)) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: e,
+            error_code: "400".to_string(),
+        });
+    }
+    if let Some(parent) = &form.parent {
+        let checked = categories::parent_check(&form.slug, parent, |candidate| {
+            db::get_category(&mut conn, candidate).ok().flatten().map(|c| c.parent)
+        });
+        if let Err(e) = checked {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e,
+                error_code: "400".to_string(),
+            });
+        }
+    }
+    match db::get_category(&mut conn, &form.slug) {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(DefaultError {
+                message: format!("Category {} already exists", form.slug),
+                error_code: "409".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get category".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+
+    match db::create_category(&mut conn, form.into_inner()) {
+        Ok(category) => HttpResponse::Created().json(category),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to create category".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    params(("slug" = String, Path, description = "Category slug")),
+    request_body = UpdateCategory,
+    responses(
+        (status = 200, description = "Updated category", body = Category),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[patch("/admin/categories/{slug}")]
+pub async fn patch_category(
+    path: web::Path<(String,)>,
+    mut form: web::Json<UpdateCategory>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !db::is_admin(&mut conn, user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let slug = path.into_inner().0;
+    match db::get_category(&mut conn, &slug) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Category not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get category".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+
+    if let Some(name) = &form.name {
+        let name = name.trim().to_string();
+        if let Err(e) = categories::name_check(&name) {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e,
+                error_code: "400".to_string(),
+            });
+        }
+        form.name = Some(name);
+    }
+    if let Some(Some(parent)) = &form.parent {
+        let checked = categories::parent_check(&slug, parent, |candidate| {
+            db::get_category(&mut conn, candidate).ok().flatten().map(|c| c.parent)
+        });
+        if let Err(e) = checked {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e,
+                error_code: "400".to_string(),
+            });
+        }
+    }
+
+    let have_changes = form.name.is_some()
+        || form.icon.is_some()
+        || form.sort_order.is_some()
+        || form.parent.is_some();
+    let result = if have_changes {
+        db::update_category(&mut conn, &slug, form.into_inner())
+    } else {
+        db::get_category(&mut conn, &slug).map(|c| c.unwrap())
+    };
+
+    match result {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to update category".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    params(("slug" = String, Path, description = "Category slug")),
+    responses(
+        (status = 200, description = "Category deleted", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 409, description = "Conflict", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[delete("/admin/categories/{slug}")]
+pub async fn delete_category(
+    path: web::Path<(String,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !db::is_admin(&mut conn, user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let slug = path.into_inner().0;
+    match db::category_in_use(&mut conn, &slug) {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Conflict().json(DefaultError {
+                message: "Category still has events or subcategories".to_string(),
+                error_code: "409".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to delete category".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+
+    match db::delete_category(&mut conn, &slug) {
+        Ok(0) => HttpResponse::NotFound().json(DefaultError {
+            message: "Category not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to delete category".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
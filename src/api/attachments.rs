@@ -0,0 +1,867 @@
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::{delete, get, post, HttpResponse, Responder, web};
+use chrono::{Duration, Utc};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{
+    Attachment, FileQuery, NewAttachment, UpdateUser, ATTACHMENT_AVATAR, ATTACHMENT_COMMENT,
+    ATTACHMENT_EVENT_COVER, ATTACHMENT_EVENT_GALLERY,
+};
+use crate::db;
+use crate::storage::StorageError;
+use crate::uploads::{self, UploadError, DOWNLOAD_URL_TTL_SECS, VARIANT_ORIGINAL, VARIANT_THUMBNAIL};
+
+const MAX_FILE_NAME_CHARS: usize = 255;
+const AVATAR_CACHE_SECS: u32 = 300;
+
+pub struct Upload {
+    pub data: Vec<u8>,
+    pub content_type: Option<String>,
+    pub file_name: String,
+}
+
+struct Target {
+    kind: &'static str,
+    event_id: Option<Uuid>,
+    comment_id: Option<Uuid>,
+}
+
+/// Reads the `file` part of a multipart body, giving up as soon as it
+/// grows past `max_bytes` instead of buffering the whole request.
+pub async fn read_upload(mut payload: Multipart, max_bytes: usize) -> Result<Upload, HttpResponse> {
+    if let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| {
+            HttpResponse::BadRequest().json(DefaultError {
+                message: e.to_string(),
+                error_code: "400".to_string(),
+            })
+        })?;
+        if field.name() != "file" {
+            return Err(HttpResponse::BadRequest().json(DefaultError {
+                message: format!("Unexpected field {}", field.name()),
+                error_code: "400".to_string(),
+            }));
+        }
+        let content_type = field.content_type().map(|m| m.essence_str().to_string());
+        let file_name: String = field
+            .content_disposition()
+            .get_filename()
+            .unwrap_or("")
+            .chars()
+            .take(MAX_FILE_NAME_CHARS)
+            .collect();
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| {
+                HttpResponse::BadRequest().json(DefaultError {
+                    message: e.to_string(),
+                    error_code: "400".to_string(),
+                })
+            })?;
+            if data.len() + chunk.len() > max_bytes {
+                return Err(HttpResponse::PayloadTooLarge().json(DefaultError {
+                    message: UploadError::TooLarge(max_bytes).to_string(),
+                    error_code: "413".to_string(),
+                }));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        return Ok(Upload {
+            data,
+            content_type,
+            file_name,
+        });
+    }
+
+    Err(HttpResponse::BadRequest().json(DefaultError {
+        message: "Missing file field".to_string(),
+        error_code: "400".to_string(),
+    }))
+}
+
+async fn delete_blobs(data: &web::Data<MyData>, storage_key: &str, thumbnail_key: Option<&str>) {
+    for key in std::iter::once(storage_key).chain(thumbnail_key) {
+        if let Err(e) = data.storage.delete(key).await {
+            log::warn!("failed to delete {} from {} storage: {}", key, data.storage.name(), e);
+        }
+    }
+}
+
+/// Validates the upload, writes the original and its thumbnail to storage
+/// and records it. Blobs of a replaced cover or avatar are removed after
+/// the new one is committed.
+async fn store_upload(
+    data: &web::Data<MyData>,
+    user_id: Uuid,
+    target: Target,
+    upload: Upload,
+) -> Result<Attachment, HttpResponse> {
+    let kind = target.kind;
+    let declared = upload.content_type.clone();
+    let bytes = upload.data;
+    // Decoding and resizing is CPU bound, keep it off the worker thread.
+    let (processed, bytes) = web::block(move || {
+        let processed = uploads::process(kind, declared.as_deref(), &bytes);
+        (processed, bytes)
+    })
+    .await
+    .map_err(|_| {
+        HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to process upload".to_string(),
+            error_code: "500".to_string(),
+        })
+    })?;
+    let processed = processed.map_err(|e| match e {
+        UploadError::TooLarge(_) => HttpResponse::PayloadTooLarge().json(DefaultError {
+            message: e.to_string(),
+            error_code: "413".to_string(),
+        }),
+        UploadError::UnsupportedType(_) | UploadError::TypeMismatch(_, _) => {
+            HttpResponse::UnsupportedMediaType().json(DefaultError {
+                message: e.to_string(),
+                error_code: "415".to_string(),
+            })
+        }
+        UploadError::Empty | UploadError::InvalidImage(_) => HttpResponse::BadRequest().json(DefaultError {
+            message: e.to_string(),
+            error_code: "400".to_string(),
+        }),
+    })?;
+
+    let storage_failed = |e: StorageError| {
+        log::error!("failed to store upload: {}", e);
+        HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to store file".to_string(),
+            error_code: "500".to_string(),
+        })
+    };
+    let id = Uuid::new_v4();
+    let size = bytes.len() as i64;
+    let storage_key = uploads::storage_key(id, VARIANT_ORIGINAL);
+    data.storage
+        .put(&storage_key, bytes, processed.content_type)
+        .await
+        .map_err(storage_failed)?;
+    let thumbnail_key = match processed.thumbnail {
+        Some(thumbnail) => {
+            let key = uploads::storage_key(id, VARIANT_THUMBNAIL);
+            data.storage
+                .put(&key, thumbnail, "image/jpeg")
+                .await
+                .map_err(storage_failed)?;
+            Some(key)
+        }
+        None => None,
+    };
+
+    let new_attachment = NewAttachment {
+        id,
+        user_id,
+        kind: kind.to_string(),
+        event_id: target.event_id,
+        comment_id: target.comment_id,
+        content_type: processed.content_type.to_string(),
+        size,
+        storage_key: storage_key.clone(),
+        thumbnail_key: thumbnail_key.clone(),
+        width: processed.width,
+        height: processed.height,
+        original_name: upload.file_name,
+    };
+    let result = {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+        db::create_attachment(&mut conn, new_attachment)
+    };
+    match result {
+        Ok((attachment, replaced)) => {
+            for old in replaced.iter() {
+                delete_blobs(data, &old.storage_key, old.thumbnail_key.as_deref()).await;
+            }
+            Ok(attachment)
+        }
+        Err(_) => {
+            delete_blobs(data, &storage_key, thumbnail_key.as_deref()).await;
+            Err(HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to save attachment".to_string(),
+                error_code: "500".to_string(),
+            }))
+        }
+    }
+}
+
+fn download_expiry() -> chrono::NaiveDateTime {
+    Utc::now().naive_utc() + Duration::seconds(DOWNLOAD_URL_TTL_SECS)
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Stored cover, replacing the previous one", body = AttachmentView),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 413, description = "File too large", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/cover")]
+pub async fn upload_event_cover(
+    path: web::Path<(Uuid,)>,
+    payload: Multipart,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+        match db::get_event_by_id(&mut conn, event_id, Uuid::nil()) {
+            Some(event) => {
+                if event.user_id != user_id {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Only the event owner can change the cover".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+            None => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "Event not found".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+        }
+    }
+
+    let upload = match read_upload(payload, uploads::max_bytes(ATTACHMENT_EVENT_COVER)).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let target = Target {
+        kind: ATTACHMENT_EVENT_COVER,
+        event_id: Some(event_id),
+        comment_id: None,
+    };
+    match store_upload(&data, user_id, target, upload).await {
+        Ok(attachment) => HttpResponse::Created().json(uploads::view(&data.uploads, attachment, download_expiry())),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Stored image", body = AttachmentView),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 413, description = "File too large", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/gallery")]
+pub async fn upload_event_gallery(
+    path: web::Path<(Uuid,)>,
+    payload: Multipart,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+        let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+        if event.is_none() {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Event not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+        let event = event.unwrap();
+        if event.user_id != user_id {
+            let event_member = db::get_event_members(&mut conn, event_id);
+            if !event_member.contains(&user_id) {
+                return HttpResponse::Forbidden().json(DefaultError {
+                    message: "You are not in this event".to_string(),
+                    error_code: "403".to_string(),
+                });
+            }
+        }
+    }
+
+    let upload = match read_upload(payload, uploads::max_bytes(ATTACHMENT_EVENT_GALLERY)).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let target = Target {
+        kind: ATTACHMENT_EVENT_GALLERY,
+        event_id: Some(event_id),
+        comment_id: None,
+    };
+    match store_upload(&data, user_id, target, upload).await {
+        Ok(attachment) => HttpResponse::Created().json(uploads::view(&data.uploads, attachment, download_expiry())),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("event_id" = Uuid, Path, description = "Event id"),
+        ("comment_id" = Uuid, Path, description = "Comment id"),
+    ),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Stored attachment", body = AttachmentView),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 413, description = "File too large", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/msgs/{comment_id}/attachments")]
+pub async fn upload_comment_attachment(
+    path: web::Path<(Uuid, Uuid)>,
+    payload: Multipart,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let (event_id, comment_id) = path.into_inner();
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+        if db::get_event_by_id(&mut conn, event_id, Uuid::nil()).is_none() {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Event not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+        match db::get_comment_author(&mut conn, event_id, comment_id) {
+            Ok(Some(author)) => {
+                if author != user_id {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Only the author can attach files to a comment".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+            Ok(None) => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "Comment not found".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to get comment".to_string(),
+                    error_code: "500".to_string(),
+                });
+            }
+        }
+    }
+
+    let upload = match read_upload(payload, uploads::max_bytes(ATTACHMENT_COMMENT)).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let target = Target {
+        kind: ATTACHMENT_COMMENT,
+        event_id: Some(event_id),
+        comment_id: Some(comment_id),
+    };
+    match store_upload(&data, user_id, target, upload).await {
+        Ok(attachment) => HttpResponse::Created().json(uploads::view(&data.uploads, attachment, download_expiry())),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "User with the new avatar", body = User),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 413, description = "File too large", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/users/{user_id}/avatar")]
+pub async fn upload_avatar(
+    path: web::Path<(Uuid,)>,
+    payload: Multipart,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let upload = match read_upload(payload, uploads::max_bytes(ATTACHMENT_AVATAR)).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let target = Target {
+        kind: ATTACHMENT_AVATAR,
+        event_id: None,
+        comment_id: None,
+    };
+    let attachment = match store_upload(&data, user_id, target, upload).await {
+        Ok(attachment) => attachment,
+        Err(response) => return response,
+    };
+
+    // users.avatar is a plain URL everywhere else, so point it at the
+    // redirecting endpoint; the version parameter busts client caches.
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+    let user = db::update_user(
+        &mut conn,
+        user_id,
+        UpdateUser {
+            avatar: Some(format!(
+                "{}/api/v1/users/{}/avatar?v={}",
+                data.uploads.public_url, user_id, attachment.id
+            )),
+            name: None,
+            email: None,
+            phone: None,
+        },
+    );
+
+    HttpResponse::Ok().json(user)
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("user_id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 302, description = "Redirects to a signed download link"),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[get("/users/{user_id}/avatar")]
+pub async fn get_avatar(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_avatar(&mut conn, path.0) {
+        Ok(Some(avatar)) => {
+            let variant = if avatar.thumbnail_key.is_some() {
+                VARIANT_THUMBNAIL
+            } else {
+                VARIANT_ORIGINAL
+            };
+            let url = uploads::signed_url(&data.uploads, avatar.id, variant, download_expiry().and_utc().timestamp());
+            HttpResponse::Found()
+                .insert_header(("Location", url))
+                .insert_header(CacheControl(vec![
+                    CacheDirective::Private,
+                    CacheDirective::MaxAge(AVATAR_CACHE_SECS),
+                ]))
+                .finish()
+        }
+        Ok(None) => HttpResponse::NotFound().json(DefaultError {
+            message: "Avatar not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get avatar".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Cover and gallery with signed links", body = [AttachmentView]),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[get("/events/{event_id}/attachments")]
+pub async fn get_event_attachments(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    let event_id = path.0;
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if db::get_event_by_id(&mut conn, event_id, Uuid::nil()).is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+
+    match db::get_event_attachments(&mut conn, event_id) {
+        Ok(attachments) => {
+            let expires = download_expiry();
+            let views: Vec<_> = attachments
+                .into_iter()
+                .map(|a| uploads::view(&data.uploads, a, expires))
+                .collect();
+            HttpResponse::Ok().json(views)
+        }
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get attachments".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(("attachment_id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 200, description = "Attachment deleted", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[delete("/attachments/{attachment_id}")]
+pub async fn delete_attachment(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let attachment = {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+
+        let attachment = match db::get_attachment(&mut conn, path.0) {
+            Ok(Some(a)) => a,
+            Ok(None) => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "Attachment not found".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to get attachment".to_string(),
+                    error_code: "500".to_string(),
+                });
+            }
+        };
+
+        // Event owners can moderate anything attached to their event.
+        let allowed = attachment.user_id == user_id
+            || attachment
+                .event_id
+                .and_then(|event_id| db::get_event_by_id(&mut conn, event_id, Uuid::nil()))
+                .map(|event| event.user_id == user_id)
+                .unwrap_or(false);
+        if !allowed {
+            return HttpResponse::Forbidden().json(DefaultError {
+                message: "Forbidden".to_string(),
+                error_code: "403".to_string(),
+            });
+        }
+
+        if db::delete_attachment(&mut conn, attachment.id).is_err() {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to delete attachment".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+        if attachment.kind == ATTACHMENT_AVATAR {
+            db::update_user(
+                &mut conn,
+                attachment.user_id,
+                UpdateUser {
+                    avatar: Some(String::new()),
+                    name: None,
+                    email: None,
+                    phone: None,
+                },
+            );
+        }
+        attachment
+    };
+    delete_blobs(&data, &attachment.storage_key, attachment.thumbnail_key.as_deref()).await;
+
+    HttpResponse::Ok().json(DefaultMsg {
+        message: "Attachment deleted".to_string(),
+        message_code: "200".to_string(),
+    })
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("attachment_id" = Uuid, Path, description = "Attachment id"),
+        FileQuery,
+    ),
+    responses(
+        (status = 200, description = "File contents", content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[get("/files/{attachment_id}")]
+pub async fn get_file(
+    path: web::Path<(Uuid,)>,
+    query: web::Query<FileQuery>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    let attachment_id = path.0;
+    let now = Utc::now().timestamp();
+
+    if query.variant != VARIANT_ORIGINAL && query.variant != VARIANT_THUMBNAIL {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: format!("Unknown variant {}", query.variant),
+            error_code: "400".to_string(),
+        });
+    }
+    if !uploads::verify_download(&data.uploads, attachment_id, &query.variant, query.expires, &query.signature, now) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Download link is invalid or has expired".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let attachment = {
+        let mut conn = data
+            .pool
+            .get()
+            .expect("couldn't get db connection from pool");
+        match db::get_attachment(&mut conn, attachment_id) {
+            Ok(Some(a)) => a,
+            Ok(None) => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "File not found".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to get attachment".to_string(),
+                    error_code: "500".to_string(),
+                });
+            }
+        }
+    };
+
+    let (key, content_type) = if query.variant == VARIANT_THUMBNAIL {
+        match attachment.thumbnail_key.clone() {
+            Some(key) => (key, "image/jpeg".to_string()),
+            None => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "File has no thumbnail".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+        }
+    } else {
+        (attachment.storage_key.clone(), attachment.content_type.clone())
+    };
+
+    let bytes = match data.storage.get(&key).await {
+        Ok(bytes) => bytes,
+        Err(StorageError::NotFound) => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "File not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+        Err(e) => {
+            log::error!("failed to read {} from {} storage: {}", key, data.storage.name(), e);
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to read file".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    };
+
+    // Only images are shown inline; anything else is a download so it
+    // can't be rendered in our origin.
+    let disposition = ContentDisposition {
+        disposition: if content_type.starts_with("image/") {
+            DispositionType::Inline
+        } else {
+            DispositionType::Attachment
+        },
+        parameters: if attachment.original_name.is_empty() {
+            vec![]
+        } else {
+            vec![DispositionParam::Filename(attachment.original_name.clone())]
+        },
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(disposition)
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(CacheControl(vec![
+            CacheDirective::Private,
+            CacheDirective::MaxAge((query.expires - now).max(0) as u32),
+        ]))
+        .body(bytes)
+}
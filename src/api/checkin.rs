@@ -0,0 +1,206 @@
+use actix_session::Session;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{get, post, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::DefaultError;
+use crate::MyData;
+use crate::models::{CheckinForm, QrQuery};
+use crate::checkin::{self, QR_FORMAT_PNG, QR_FORMAT_SVG};
+use crate::db;
+
+#[utoipa::path(
+    tag = "checkin",
+    params(
+        ("event_id" = Uuid, Path, description = "Event id"),
+        QrQuery,
+    ),
+    responses(
+        (status = 200, description = "QR code holding the member's check-in token", content_type = "image/png"),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/events/{event_id}/checkin-code")]
+pub async fn get_checkin_code(
+    path: web::Path<(Uuid,)>,
+    query: web::Query<QrQuery>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let event_id = path.0;
+    let format = query.format.clone().unwrap_or(QR_FORMAT_PNG.to_string());
+    if format != QR_FORMAT_PNG && format != QR_FORMAT_SVG {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "format should be png or svg".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = match db::get_event_by_id(&mut conn, event_id, Uuid::nil()) {
+        Some(event) => event,
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Event not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+    if !event.established || event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Check-in opens once the event is established".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let token = match db::get_or_create_checkin_token(&mut conn, event_id, user_id, &checkin::generate_token()) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            return HttpResponse::Forbidden().json(DefaultError {
+                message: "You are not in this event".to_string(),
+                error_code: "403".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get check-in token".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    };
+
+    let rendered = if format == QR_FORMAT_SVG {
+        checkin::qr_svg(&token).map(|svg| ("image/svg+xml", svg.into_bytes()))
+    } else {
+        checkin::qr_png(&token).map(|png| ("image/png", png))
+    };
+    match rendered {
+        Ok((content_type, body)) => HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(CacheControl(vec![CacheDirective::Private, CacheDirective::NoStore]))
+            .body(body),
+        Err(e) => {
+            log::error!("failed to render check-in code for event {}: {}", event_id, e);
+            HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to render check-in code".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "checkin",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = CheckinForm,
+    responses(
+        (status = 200, description = "Member checked in", body = CheckinResult),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/checkin")]
+pub async fn check_in(
+    path: web::Path<(Uuid,)>,
+    form: web::Json<CheckinForm>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let event_id = path.0;
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = match db::get_event_by_id(&mut conn, event_id, Uuid::nil()) {
+        Some(event) => event,
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Event not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+    if event.user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Only the organizer can check members in".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    if !event.established || event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Check-in opens once the event is established".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    match db::check_in_member(&mut conn, event_id, checkin::clean_token(&form.token), user_id) {
+        Ok(Some(result)) => HttpResponse::Ok().json(result),
+        Ok(None) => HttpResponse::NotFound().json(DefaultError {
+            message: "Unknown check-in code for this event".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to check in".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
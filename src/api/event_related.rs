@@ -4,9 +4,32 @@ use uuid::Uuid;
 
 use crate::api::types::{DefaultError, DefaultMsg};
 use crate::MyData;
-use crate::models::{NewEventMember, NewEventMsg};
+use crate::models::{
+    NewEventMember, NewEventMsg, TagCount, TagsQuery, NOTIFY_COMMENT_CREATED, NOTIFY_MEMBER_JOINED,
+    NOTIFY_MEMBER_LEFT, WEBHOOK_COMMENT_CREATED, WEBHOOK_MEMBER_JOINED, WEBHOOK_MEMBER_LEFT,
+};
 use crate::db;
+use crate::money::Money;
+use crate::notify;
+use crate::tags::normalize_tag;
+use crate::webhooks;
 
+const DEFAULT_TAG_LIMIT: i64 = 20;
+const MAX_TAG_LIMIT: i64 = 100;
+
+#[utoipa::path(
+    tag = "members",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = NewEventMember,
+    responses(
+        (status = 200, description = "Joined or pledge updated", body = DefaultMsg),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[put("/events/{event_id}/join")]
 pub async fn join_event(
     path: web::Path<(Uuid,)>,
@@ -53,6 +76,12 @@ pub async fn join_event(
         });
     }
     let event = event.unwrap();
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
     if event.established == true {
         return HttpResponse::BadRequest().json(DefaultError {
             message: "Event has already established".to_string(),
@@ -68,6 +97,7 @@ pub async fn join_event(
 
     form.event_id = event_id;
     form.user_id = user_id;
+    let amount = form.amount;
     let result = db::create_event_member(&mut conn, form.into_inner());
 
     match result {
@@ -86,12 +116,46 @@ pub async fn join_event(
         }
     }
 
+    if event.user_id != user_id {
+        let user = db::get_user_by_id(&mut conn, user_id);
+        notify::notify(
+            &mut conn,
+            vec![event.user_id],
+            event_id,
+            NOTIFY_MEMBER_JOINED,
+            format!("{} joined {}", user.name, event.name),
+        );
+    }
+    let user = db::get_user_by_id(&mut conn, user_id);
+    webhooks::dispatch(
+        &mut conn,
+        WEBHOOK_MEMBER_JOINED,
+        event_id,
+        serde_json::json!({
+            "event": webhooks::event_data(&event),
+            "user": { "id": user.id, "name": user.name },
+            "amount": Money::from_db(amount, &event.currency),
+        }),
+    );
+
     HttpResponse::Ok().json(DefaultMsg {
         message: "Success".to_string(),
         message_code: "200".to_string(),
     })
 }
 
+#[utoipa::path(
+    tag = "members",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Left the event", body = DefaultMsg),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[post("/events/{event_id}/leave")]
 pub async fn leave_event(
     path: web::Path<(Uuid,)>,
@@ -165,12 +229,42 @@ pub async fn leave_event(
         });
     }
 
+    let user = db::get_user_by_id(&mut conn, user_id);
+    notify::notify(
+        &mut conn,
+        vec![event.user_id],
+        event_id,
+        NOTIFY_MEMBER_LEFT,
+        format!("{} left {}", user.name, event.name),
+    );
+    webhooks::dispatch(
+        &mut conn,
+        WEBHOOK_MEMBER_LEFT,
+        event_id,
+        serde_json::json!({
+            "event": webhooks::event_data(&event),
+            "user": { "id": user.id, "name": user.name },
+        }),
+    );
+
     HttpResponse::Ok().json(DefaultMsg {
         message: "Success".to_string(),
         message_code: "200".to_string(),
     })
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = NewEventMsg,
+    responses(
+        (status = 200, description = "Comments of the event", body = [EventMsg]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[post("/events/{event_id}/msgs")]
 pub async fn add_event_msg(
     path: web::Path<(Uuid,)>,
@@ -228,10 +322,30 @@ pub async fn add_event_msg(
 
     form.event_id = event_id;
     form.user_id = user_id;
+    let content = form.content.clone();
     let result = db::create_event_msg(&mut conn, form.into_inner());
 
     match result {
         Ok(r) => {
+            let user = db::get_user_by_id(&mut conn, user_id);
+            let audience = notify::event_audience(&mut conn, event_id, event.user_id, user_id);
+            notify::notify(
+                &mut conn,
+                audience,
+                event_id,
+                NOTIFY_COMMENT_CREATED,
+                format!("{} commented on {}", user.name, event.name),
+            );
+            webhooks::dispatch(
+                &mut conn,
+                WEBHOOK_COMMENT_CREATED,
+                event_id,
+                serde_json::json!({
+                    "event": webhooks::event_data(&event),
+                    "user": { "id": user.id, "name": user.name },
+                    "content": content,
+                }),
+            );
             HttpResponse::Ok().json(r)
         }
         Err(_) => {
@@ -245,6 +359,15 @@ pub async fn add_event_msg(
    
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Comments of the event", body = [EventMsg]),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
 #[get("/events/{event_id}/msgs")]
 pub async fn get_event_msgs(
     path: web::Path<(Uuid,)>,
@@ -279,6 +402,13 @@ pub async fn get_event_msgs(
     }
 }
 
+#[utoipa::path(
+    tag = "categories",
+    responses(
+        (status = 200, description = "Categories with their open event counts", body = [CategoryWithCount]),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
 #[get("/categories")]
 pub async fn get_categories(
     data: web::Data<MyData>,
@@ -288,7 +418,49 @@ pub async fn get_categories(
         .get()
         .expect("couldn't get db connection from pool");
 
-    let categories = db::get_categories(&mut conn);
+    match db::get_categories(&mut conn) {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get categories".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
 
-    HttpResponse::Ok().json(categories)
-}
\ No newline at end of file
+#[utoipa::path(
+    tag = "tags",
+    params(TagsQuery),
+    responses(
+        (status = 200, description = "Tags by usage", body = [TagCount]),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[get("/tags")]
+pub async fn get_tags(
+    query: web::Query<TagsQuery>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    // Normalize the prefix like a tag so "#Week" still finds "weekend"; a
+    // prefix no tag could start with simply has no matches.
+    let prefix = match query.prefix.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(prefix) => match normalize_tag(prefix) {
+            Ok(prefix) => prefix,
+            Err(_) => return HttpResponse::Ok().json(Vec::<TagCount>::new()),
+        },
+        None => String::new(),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_TAG_LIMIT).clamp(1, MAX_TAG_LIMIT);
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_tag_counts(&mut conn, &prefix, limit) {
+        Ok(tags) => HttpResponse::Ok().json(tags),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get tags".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
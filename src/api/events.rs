@@ -2,12 +2,24 @@ use actix_session::Session;
 use actix_web::{get, post, patch, delete, HttpResponse, Responder, web};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
 
 use crate::api::types::{DefaultError, DefaultMsg};
 use crate::MyData;
-use crate::models::{NewEvent, UpdateEvent, EventWithMembers};
+use crate::models::{
+    CloneEventForm, EventsQuery, NewEvent, NewEventForm, NewJob, UpdateEvent, UpdateEventForm, EventWithMembers,
+    JOB_REFUND_PAYMENT, NOTIFY_EVENT_CANCELLED, NOTIFY_EVENT_ESTABLISHED, NOTIFY_EVENT_UPDATED,
+    WEBHOOK_EVENT_CREATED, WEBHOOK_EVENT_ESTABLISHED,
+};
 use crate::PgPooledConnection;
 use crate::db;
+use crate::geo::{coordinates_check, parse_near, MAX_RADIUS_KM};
+use crate::money::{amount_check, find_currency, Money};
+use crate::notify;
+use crate::payment::{self, RefundError};
+use crate::tags::{normalize_tags, TagFilter};
+use crate::templates;
+use crate::webhooks;
 
 fn time_check(start_time: NaiveDateTime, end_time: NaiveDateTime) -> bool {
     if start_time > end_time {
@@ -16,19 +28,116 @@ fn time_check(start_time: NaiveDateTime, end_time: NaiveDateTime) -> bool {
     return true;
 }
 
-fn amount_check(min_amount: i64, max_amount: i64) -> bool {
-    if min_amount > max_amount {
-        return false;
+/// Checks every way of creating an event goes through: `POST /events`,
+/// clones, templates and imports. Normalizes the currency code and tags in
+/// place. The inner error is a message for the client, the outer one a
+/// failed category lookup.
+pub fn check_new_event(
+    conn: &mut PgConnection,
+    form: &mut NewEvent,
+    tags: &mut Vec<String>,
+) -> Result<Result<(), String>, diesel::result::Error> {
+    if time_check(form.start_time, form.end_time) == false {
+        return Ok(Err("Start time should be earlier than end time".to_string()));
     }
-    return true;
+    match find_currency(&form.currency) {
+        Some(currency) => {
+            form.currency = currency.code.to_string();
+        }
+        None => {
+            return Ok(Err(format!("Unknown currency {}", form.currency)));
+        }
+    }
+    let amounts = Money::new(form.min_amount, &form.currency)
+        .and_then(|min| Ok((min, Money::new(form.max_amount, &form.currency)?)))
+        .and_then(|(min, max)| amount_check(min, max));
+    if let Err(e) = amounts {
+        return Ok(Err(e.to_string()));
+    }
+    if let Err(e) = coordinates_check(form.location.latitude, form.location.longitude) {
+        return Ok(Err(e));
+    }
+    match normalize_tags(tags) {
+        Ok(normalized) => {
+            *tags = normalized;
+        }
+        Err(e) => {
+            return Ok(Err(e));
+        }
+    }
+    match db::get_category(conn, &form.category)? {
+        Some(_) => Ok(Ok(())),
+        None => Ok(Err(format!("Unknown category {}", form.category))),
+    }
+}
+
+/// `check_new_event` as a response for the single event endpoints.
+pub fn validate_new_event(
+    conn: &mut PgConnection,
+    form: &mut NewEvent,
+    tags: &mut Vec<String>,
+) -> Result<(), Box<HttpResponse>> {
+    match check_new_event(conn, form, tags) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(message)) => Err(Box::new(HttpResponse::BadRequest().json(DefaultError {
+            message,
+            error_code: "400".to_string(),
+        }))),
+        Err(_) => Err(Box::new(HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get category".to_string(),
+            error_code: "500".to_string(),
+        }))),
+    }
+}
+
+/// Stores an event that passed `validate_new_event` along with its tags
+/// and lets webhook subscribers know.
+pub fn insert_event(
+    conn: &mut PgConnection,
+    form: NewEvent,
+    tags: &[String],
+) -> Result<EventWithMembers, Box<HttpResponse>> {
+    let mut event = db::create_event(conn, form);
+    if !tags.is_empty() {
+        match db::set_event_tags(conn, event.id, tags) {
+            Ok(tags) => {
+                event.tags = tags;
+            }
+            Err(_) => {
+                return Err(Box::new(HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to save tags".to_string(),
+                    error_code: "500".to_string(),
+                })));
+            }
+        }
+    }
+    webhooks::dispatch(
+        conn,
+        WEBHOOK_EVENT_CREATED,
+        event.id,
+        serde_json::json!({ "event": webhooks::event_data(&event) }),
+    );
+    Ok(event)
 }
 
+#[utoipa::path(
+    tag = "events",
+    request_body = NewEventForm,
+    responses(
+        (status = 200, description = "Created event", body = EventWithMembers),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[post("/events")]
 pub async fn create_event(
-    mut form: web::Json<NewEvent>,
+    body: web::Json<NewEventForm>,
     data: web::Data<MyData>,
     session: Session,
 ) -> impl Responder {
+    let NewEventForm { event: mut form, min_amount, max_amount, mut tags } = body.into_inner();
     let user_id: Uuid;
     match session.get::<Uuid>("user_id") {
         Ok(id) => {
@@ -53,42 +162,102 @@ pub async fn create_event(
     }
 
     form.user_id = user_id;
-    if time_check(form.start_time, form.end_time) == false {
-        return HttpResponse::BadRequest().json(DefaultError {
-            message: "Start time should be earlier than end time".to_string(),
-            error_code: "400".to_string(),
-        });
-    }
-    if amount_check(form.min_amount, form.max_amount) == false {
-        return HttpResponse::BadRequest().json(DefaultError {
-            message: "Min amount should be smaller than max amount".to_string(),
-            error_code: "400".to_string(),
-        });
+    let amounts = Money::parse(&min_amount, &form.currency)
+        .and_then(|min| Ok((min, Money::parse(&max_amount, &form.currency)?)));
+    match amounts {
+        Ok((min, max)) => {
+            form.min_amount = min.minor;
+            form.max_amount = max.minor;
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e.to_string(),
+                error_code: "400".to_string(),
+            });
+        }
     }
     let mut conn: PgPooledConnection = data
         .pool
         .get()
         .expect("couldn't get db connection from pool");
 
-    let event = db::create_event(&mut conn, form.into_inner());
-
-    HttpResponse::Ok().json(event)
+    if let Err(response) = validate_new_event(&mut conn, &mut form, &mut tags) {
+        return *response;
+    }
+    match insert_event(&mut conn, form, &tags) {
+        Ok(event) => HttpResponse::Ok().json(event),
+        Err(response) => *response,
+    }
 }
 
+#[utoipa::path(
+    tag = "events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "Open events", body = [EventWithMembers]),
+        (status = 400, description = "Invalid request", body = DefaultError),
+    ),
+)]
 #[get("/events")]
 pub async fn get_events(
+    query: web::Query<EventsQuery>,
     data: web::Data<MyData>,
 ) -> impl Responder {
+    let near = match &query.near {
+        Some(near) => match parse_near(near) {
+            Ok(point) => Some(point),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(DefaultError {
+                    message: e,
+                    error_code: "400".to_string(),
+                });
+            }
+        },
+        None => None,
+    };
+    let tag_filter = match TagFilter::parse(query.tags.as_deref().unwrap_or(""), query.tags_mode.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e,
+                error_code: "400".to_string(),
+            });
+        }
+    };
+    let radius_km = query.radius_km.unwrap_or(10.0);
+    if !(radius_km > 0.0 && radius_km <= MAX_RADIUS_KM) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: format!("radius_km should be between 0 and {}", MAX_RADIUS_KM),
+            error_code: "400".to_string(),
+        });
+    }
+
     let mut conn: PgPooledConnection = data
         .pool
         .get()
         .expect("couldn't get db connection from pool");
 
-    let events = db::get_events(&mut conn);
+    let mut events = match near {
+        Some((lat, lng)) => db::get_events_near(&mut conn, lat, lng, radius_km),
+        None => db::get_events(&mut conn),
+    };
+    if let Some(filter) = &tag_filter {
+        events.retain(|e| filter.matches(&e.tags));
+    }
 
     HttpResponse::Ok().json(events)
 }
 
+#[utoipa::path(
+    tag = "events",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "The event; `members`, `payment_summary` and `attendance` only for its owner", body = EventWithMembers),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security((), ("session" = [])),
+)]
 #[get("/events/{event_id}")]
 pub async fn get_event(
     path: web::Path<(Uuid,)>,
@@ -135,13 +304,28 @@ pub async fn get_event(
     }
 }
 
+#[utoipa::path(
+    tag = "events",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = UpdateEventForm,
+    responses(
+        (status = 200, description = "Updated event", body = EventWithMembers),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[patch("/events/{event_id}")]
 pub async fn patch_event(
     path: web::Path<(Uuid,)>,
-    mut form: web::Json<UpdateEvent>,
+    body: web::Json<UpdateEventForm>,
     data: web::Data<MyData>,
     session: Session,
 ) -> impl Responder {
+    let UpdateEventForm { event: update, mut min_amount, mut max_amount, mut tags } = body.into_inner();
+    let mut form = web::Json(update);
     let user_id: Uuid;
     let null_uuid = Uuid::nil();
 
@@ -192,6 +376,12 @@ pub async fn patch_event(
             error_code: "403".to_string(),
         });
     }
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
     match form.established {
         Some(established) => {
             if established == false {
@@ -214,8 +404,15 @@ pub async fn patch_event(
             category: None,
             name: None,
             description: None,
+            address: None,
+            venue: None,
+            latitude: None,
+            longitude: None,
         };
         form = web::Json(new_form);
+        min_amount = None;
+        max_amount = None;
+        tags = None;
     }
 
     if form.start_time.is_some() {
@@ -224,11 +421,40 @@ pub async fn patch_event(
     if form.end_time.is_some() {
         event.end_time = form.end_time.unwrap();
     }
-    if form.max_amount.is_some() {
-        event.max_amount = form.max_amount.unwrap();
+    if let Some(amount) = &max_amount {
+        match Money::parse(amount, &event.currency) {
+            Ok(money) => {
+                event.max_amount = money;
+                form.max_amount = Some(money.minor);
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(DefaultError {
+                    message: e.to_string(),
+                    error_code: "400".to_string(),
+                });
+            }
+        }
+    }
+    if let Some(amount) = &min_amount {
+        match Money::parse(amount, &event.currency) {
+            Ok(money) => {
+                event.min_amount = money;
+                form.min_amount = Some(money.minor);
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(DefaultError {
+                    message: e.to_string(),
+                    error_code: "400".to_string(),
+                });
+            }
+        }
+    }
+
+    if form.latitude.is_some() {
+        event.location.latitude = form.latitude;
     }
-    if form.min_amount.is_some() {
-        event.min_amount = form.min_amount.unwrap();
+    if form.longitude.is_some() {
+        event.location.longitude = form.longitude;
     }
 
     if time_check(event.start_time, event.end_time) == false {
@@ -237,12 +463,48 @@ pub async fn patch_event(
             error_code: "400".to_string(),
         });
     }
-    if amount_check(event.min_amount, event.max_amount) == false {
+    if let Err(e) = amount_check(event.min_amount, event.max_amount) {
         return HttpResponse::BadRequest().json(DefaultError {
-            message: "Min amount should be smaller than max amount".to_string(),
+            message: e.to_string(),
             error_code: "400".to_string(),
         });
     }
+    if let Err(e) = coordinates_check(event.location.latitude, event.location.longitude) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: e,
+            error_code: "400".to_string(),
+        });
+    }
+    if let Some(raw) = &tags {
+        match normalize_tags(raw) {
+            Ok(normalized) => {
+                tags = Some(normalized);
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(DefaultError {
+                    message: e,
+                    error_code: "400".to_string(),
+                });
+            }
+        }
+    }
+    if let Some(category) = &form.category {
+        match db::get_category(&mut conn, category) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return HttpResponse::BadRequest().json(DefaultError {
+                    message: format!("Unknown category {}", category),
+                    error_code: "400".to_string(),
+                });
+            }
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to get category".to_string(),
+                    error_code: "500".to_string(),
+                });
+            }
+        }
+    }
     
     let have_changes = form.established.is_some()
         || form.start_time.is_some()
@@ -251,11 +513,69 @@ pub async fn patch_event(
         || form.min_amount.is_some()
         || form.category.is_some()
         || form.name.is_some()
-        || form.description.is_some();
+        || form.description.is_some()
+        || form.address.is_some()
+        || form.venue.is_some()
+        || form.latitude.is_some()
+        || form.longitude.is_some();
+    let tags_changed = tags.is_some();
     
+    let establishing = form.established == Some(true) && !event.established;
+    let editing = form.start_time.is_some()
+        || form.end_time.is_some()
+        || form.max_amount.is_some()
+        || form.min_amount.is_some()
+        || form.category.is_some()
+        || form.name.is_some()
+        || form.description.is_some()
+        || form.address.is_some()
+        || form.venue.is_some()
+        || form.latitude.is_some()
+        || form.longitude.is_some()
+        || tags_changed;
+
+    if let Some(tags) = &tags {
+        if db::set_event_tags(&mut conn, path.0, tags).is_err() {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to save tags".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+
     let event: EventWithMembers;
-    if have_changes {
-        event = db::update_event(&mut conn, path.0, form.into_inner());
+    if have_changes || tags_changed {
+        if have_changes {
+            event = db::update_event(&mut conn, path.0, form.into_inner());
+        } else {
+            event = db::get_event_by_id(&mut conn, path.0, user_id).unwrap();
+        }
+
+        let audience = notify::event_audience(&mut conn, event.id, event.user_id, user_id);
+        if establishing {
+            notify::notify(
+                &mut conn,
+                audience.clone(),
+                event.id,
+                NOTIFY_EVENT_ESTABLISHED,
+                format!("{} has been established", event.name),
+            );
+            webhooks::dispatch(
+                &mut conn,
+                WEBHOOK_EVENT_ESTABLISHED,
+                event.id,
+                serde_json::json!({ "event": webhooks::event_data(&event) }),
+            );
+        }
+        if editing {
+            notify::notify(
+                &mut conn,
+                audience,
+                event.id,
+                NOTIFY_EVENT_UPDATED,
+                format!("{} has been updated", event.name),
+            );
+        }
     } else {
         event = db::get_event_by_id(&mut conn, path.0, user_id).unwrap();
     }
@@ -263,6 +583,17 @@ pub async fn patch_event(
     HttpResponse::Ok().json(event)
 }
 
+#[utoipa::path(
+    tag = "events",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Event deleted", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[delete("/events/{event_id}")]
 pub async fn delete_event(
     path: web::Path<(Uuid,)>,
@@ -334,6 +665,16 @@ pub async fn delete_event(
     })
 }
 
+#[utoipa::path(
+    tag = "events",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    responses(
+        (status = 200, description = "Events the user owns or joined", body = [EventWithMembers]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[get("/users/{user_id}/events")]
 pub async fn get_user_events(
     path: web::Path<(Uuid,)>,
@@ -379,4 +720,220 @@ pub async fn get_user_events(
     let events = db::get_events_by_user_id(&mut conn, user_id);
 
     HttpResponse::Ok().json(events)
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    tag = "events",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Event cancelled, members refunded", body = DefaultMsg),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/cancel")]
+pub async fn cancel_event(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let null_uuid = Uuid::nil();
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn: PgPooledConnection = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, path.0, null_uuid);
+    let event: EventWithMembers = match event {
+        Some(e) => e,
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Not Found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+
+    if event.user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has already been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    match db::cancel_event(&mut conn, event.id) {
+        Ok(true) => {}
+        // Someone else cancelled it in the meantime and is refunding.
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: "Event has already been cancelled".to_string(),
+                error_code: "400".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to cancel event".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+
+    let audience = notify::event_audience(&mut conn, event.id, event.user_id, user_id);
+    notify::notify(
+        &mut conn,
+        audience,
+        event.id,
+        NOTIFY_EVENT_CANCELLED,
+        format!("{} has been cancelled", event.name),
+    );
+
+    // Online payments are refunded through the provider that captured them;
+    // anything paid outside the system is left for the owner to settle.
+    // Refunds the provider turned down are retried by the job worker.
+    let provider = data.payment_provider.clone();
+    let mut refunded = 0;
+    let mut retrying = 0;
+    let mut failed = 0;
+    for payment in db::get_refundable_payments(&mut conn, event.id, provider.name()) {
+        match payment::refund_payment(&mut conn, provider.as_ref(), &payment).await {
+            Ok(_) => refunded += 1,
+            Err(RefundError::Provider(e)) => {
+                let job = NewJob {
+                    kind: JOB_REFUND_PAYMENT.to_string(),
+                    payload: payment.id.to_string(),
+                    run_at: chrono::Utc::now().naive_utc(),
+                };
+                match db::enqueue_job(&mut conn, job) {
+                    Ok(_) => {
+                        log::warn!("refund for payment {} failed, will retry: {}", payment.id, e);
+                        retrying += 1;
+                    }
+                    Err(_) => {
+                        log::error!("refund for payment {} failed and couldn't be queued: {}", payment.id, e);
+                        failed += 1;
+                    }
+                }
+            }
+            Err(e @ RefundError::NotRecorded(_)) => {
+                log::error!("refund for payment {}: {}", payment.id, e);
+                failed += 1;
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(DefaultMsg {
+        message: format!(
+            "Event cancelled, {} refunds issued, {} to be retried, {} failed",
+            refunded, retrying, failed
+        ),
+        message_code: "200".to_string(),
+    })
+}
+
+#[utoipa::path(
+    tag = "events",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = CloneEventForm,
+    responses(
+        (status = 200, description = "New event", body = EventWithMembers),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/clone")]
+pub async fn clone_event(
+    path: web::Path<(Uuid,)>,
+    form: web::Json<CloneEventForm>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn: PgPooledConnection = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = match db::get_event_by_id(&mut conn, path.0, Uuid::nil()) {
+        Some(event) => event,
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Not Found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+    if event.user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut new_event = templates::clone_event(&event, form.start_time);
+    let mut tags = Vec::new();
+    if let Err(response) = validate_new_event(&mut conn, &mut new_event, &mut tags) {
+        return *response;
+    }
+    match insert_event(&mut conn, new_event, &tags) {
+        Ok(event) => HttpResponse::Ok().json(event),
+        Err(response) => *response,
+    }
+}
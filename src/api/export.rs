@@ -0,0 +1,122 @@
+use actix_session::Session;
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::{get, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::DefaultError;
+use crate::MyData;
+use crate::models::ExportQuery;
+use crate::export::{self, FORMAT_CSV, FORMAT_XLSX};
+use crate::db;
+
+/// Member list for the organizer, as a spreadsheet. Only the owner can
+/// export; events have no co-organizers yet.
+#[utoipa::path(
+    tag = "members",
+    params(
+        ("event_id" = Uuid, Path, description = "Event id"),
+        ExportQuery,
+    ),
+    responses(
+        (status = 200, description = "Member list as CSV or XLSX", content_type = "text/csv"),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/events/{event_id}/members/export")]
+pub async fn export_members(
+    path: web::Path<(Uuid,)>,
+    query: web::Query<ExportQuery>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let event_id = path.0;
+    let format = query.format.clone().unwrap_or(FORMAT_CSV.to_string());
+    if format != FORMAT_CSV && format != FORMAT_XLSX {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "format should be csv or xlsx".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    if event.unwrap().user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    // Owner view, with members and the payment summary.
+    let event = match db::get_event_by_id(&mut conn, event_id, user_id) {
+        Some(event) => event,
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Event not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+
+    let rows = export::rows(&event);
+    let rendered = if format == FORMAT_XLSX {
+        export::to_xlsx(&rows).map(|xlsx| (export::XLSX_CONTENT_TYPE, xlsx))
+    } else {
+        Ok((export::CSV_CONTENT_TYPE, export::to_csv(&rows).into_bytes()))
+    };
+    match rendered {
+        Ok((content_type, body)) => HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(export::file_name(&event.name, &format))],
+            })
+            .insert_header(CacheControl(vec![CacheDirective::Private, CacheDirective::NoStore]))
+            .body(body),
+        Err(e) => {
+            log::error!("failed to export members of event {}: {}", event_id, e);
+            HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to export members".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+}
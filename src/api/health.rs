@@ -0,0 +1,58 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use std::collections::BTreeMap;
+
+use crate::health::{self, STATUS_OK};
+use crate::MyData;
+
+/// The process is up and serving requests. Doesn't touch any dependency,
+/// so an outage elsewhere doesn't get the instance restarted.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Serving", body = HealthReport),
+    ),
+)]
+#[get("/health/live")]
+pub async fn live() -> impl Responder {
+    HttpResponse::Ok().json(health::report(BTreeMap::new()))
+}
+
+/// Whether this instance can take traffic: the database answers, its
+/// schema is up to date, and the session store is reachable.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Every component is ok", body = HealthReport),
+        (status = 503, description = "At least one component failed", body = HealthReport),
+    ),
+)]
+#[get("/health/ready")]
+pub async fn ready(data: web::Data<MyData>) -> impl Responder {
+    let pool = data.pool.clone();
+    let database = web::block(move || health::check_database(&pool));
+    let (database, redis) = futures_util::join!(database, health::check_redis(&data.redis));
+
+    let mut components = BTreeMap::new();
+    match database {
+        Ok((database, migrations)) => {
+            components.insert("database".to_string(), database);
+            components.insert("migrations".to_string(), migrations);
+        }
+        Err(e) => {
+            log::error!("database health check panicked: {}", e);
+            components.insert("database".to_string(), health::failed("check panicked"));
+            components.insert("migrations".to_string(), health::failed("check panicked"));
+        }
+    }
+    components.insert("redis".to_string(), redis);
+
+    let report = health::report(components);
+    if report.status == STATUS_OK {
+        HttpResponse::Ok().json(report)
+    } else {
+        for (name, component) in report.components.iter().filter(|(_, c)| c.status != STATUS_OK) {
+            log::warn!("{} is unhealthy: {}", name, component.error.clone().unwrap_or_default());
+        }
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
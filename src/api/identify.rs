@@ -1,14 +1,17 @@
 use actix_session::Session;
+use actix_web::http::header::ORIGIN;
 use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
 use jsonwebtoken_google::Parser;
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use utoipa::ToSchema;
 
 use crate::api::types::{DefaultError, DefaultMsg};
 use crate::db::get_or_create_user;
 use crate::MyData;
 use crate::PgPooledConnection;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct LoginFormData {
     credential: String,
     g_csrf_token: String,
@@ -22,6 +25,16 @@ pub struct TokenClaims {
     picture: String,
 }
 
+#[utoipa::path(
+    tag = "auth",
+    request_body(content = LoginFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Logged in, redirects to the app with `user_id`"),
+        (status = 401, description = "Unauthorized", body = DefaultError),
+        (status = 409, description = "Conflict", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
 #[post("/login")]
 pub async fn user_login(
     req: HttpRequest,
@@ -57,7 +70,15 @@ pub async fn user_login(
         }
     }
 
-    if data.cors_enabled == false && g_csrf_token != form.g_csrf_token {
+    // Google's double-submit cookie lives on the page's domain, so with CORS
+    // it only reaches us when the page is served from here. Otherwise the
+    // request has to come from an allowed origin.
+    let double_submit = !g_csrf_token.is_empty() && g_csrf_token == form.g_csrf_token;
+    let allowed_origin = match (&data.cors, req.headers().get(ORIGIN)) {
+        (Some(cors), Some(origin)) => origin.to_str().map(|origin| cors.allows(origin)).unwrap_or(false),
+        _ => false,
+    };
+    if !double_submit && !allowed_origin {
         return HttpResponse::Unauthorized().json(DefaultError {
             message: "Invalid CSRF token".to_string(),
             error_code: "401".to_string(),
@@ -65,7 +86,11 @@ pub async fn user_login(
     }
 
     let parser = Parser::new(&data.google_client_id);
-    let claims = parser.parse::<TokenClaims>(&form.credential).await.unwrap();
+    let claims = parser
+        .parse::<TokenClaims>(&form.credential)
+        .instrument(tracing::info_span!("google.verify_token", otel.kind = "client"))
+        .await
+        .unwrap();
 
     let mut conn: PgPooledConnection = data
         .pool
@@ -95,6 +120,12 @@ pub async fn user_login(
         .finish()
 }
 
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Logged out", body = DefaultMsg),
+    ),
+)]
 #[post("/logout")]
 pub async fn user_logout(session: Session) -> impl Responder {
     session.purge();
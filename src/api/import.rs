@@ -0,0 +1,177 @@
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::{post, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::attachments::read_upload;
+use crate::api::events::check_new_event;
+use crate::api::types::DefaultError;
+use crate::MyData;
+use crate::models::{ImportQuery, ImportReport, ImportRowReport, WEBHOOK_EVENT_CREATED};
+use crate::db;
+use crate::logging;
+use crate::import::{self, ImportDefaults, FORMAT_ICS, MAX_IMPORT_BYTES};
+use crate::money::DEFAULT_CURRENCY;
+use crate::webhooks;
+
+/// Creates many events from an uploaded CSV or .ics `file`. Every row is
+/// validated like `POST /events`; without `commit=true` nothing is stored
+/// and the report only lists per-row errors. A commit with any invalid row
+/// stores nothing and answers 400 with the same report.
+#[utoipa::path(
+    tag = "events",
+    params(ImportQuery),
+    request_body(content = FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Validation report, with the created ids when committed", body = ImportReport),
+        (status = 400, description = "Unreadable file, or a commit with invalid rows (report)", body = ImportReport),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 413, description = "File too large", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/import")]
+pub async fn import_events(
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let upload = match read_upload(payload, MAX_IMPORT_BYTES).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let text = match String::from_utf8(upload.data) {
+        Ok(text) => text,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: "File should be UTF-8 text".to_string(),
+                error_code: "400".to_string(),
+            });
+        }
+    };
+    let defaults = ImportDefaults {
+        category: query.category.clone(),
+        currency: query.currency.clone().unwrap_or(DEFAULT_CURRENCY.to_string()),
+    };
+    let parsed = match import::detect_format(&upload.file_name, upload.content_type.as_deref(), &text) {
+        Some(FORMAT_ICS) => import::parse_ics(&text, &defaults),
+        Some(_) => import::parse_csv(&text, &defaults),
+        None => Err("Upload a .csv or .ics file".to_string()),
+    };
+    let rows = match parsed {
+        Ok(rows) if rows.is_empty() => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: "No events in file".to_string(),
+                error_code: "400".to_string(),
+            });
+        }
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e,
+                error_code: "400".to_string(),
+            });
+        }
+    };
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let mut report = ImportReport {
+        committed: false,
+        valid: 0,
+        invalid: 0,
+        rows: Vec::with_capacity(rows.len()),
+        created: Vec::new(),
+    };
+    let mut valid = Vec::with_capacity(rows.len());
+    for row in rows {
+        let error = match row.event {
+            Ok((mut form, mut tags)) => {
+                form.user_id = user_id;
+                match check_new_event(&mut conn, &mut form, &mut tags) {
+                    Ok(Ok(())) => {
+                        valid.push((form, tags));
+                        None
+                    }
+                    Ok(Err(e)) => Some(e),
+                    Err(_) => {
+                        return HttpResponse::InternalServerError().json(DefaultError {
+                            message: "Failed to get category".to_string(),
+                            error_code: "500".to_string(),
+                        });
+                    }
+                }
+            }
+            Err(e) => Some(e),
+        };
+        if error.is_some() {
+            report.invalid += 1;
+        } else {
+            report.valid += 1;
+        }
+        report.rows.push(ImportRowReport {
+            row: row.row,
+            name: row.name,
+            error,
+        });
+    }
+
+    if query.commit != Some(true) {
+        return HttpResponse::Ok().json(report);
+    }
+    if report.invalid > 0 {
+        return HttpResponse::BadRequest().json(report);
+    }
+    match db::create_events(&mut conn, valid) {
+        Ok(ids) => {
+            for id in &ids {
+                if let Some(event) = db::get_event_by_id(&mut conn, *id, Uuid::nil()) {
+                    webhooks::dispatch(
+                        &mut conn,
+                        WEBHOOK_EVENT_CREATED,
+                        event.id,
+                        serde_json::json!({ "event": webhooks::event_data(&event) }),
+                    );
+                }
+            }
+            report.committed = true;
+            report.created = ids;
+            HttpResponse::Ok().json(report)
+        }
+        Err(e) => {
+            log::error!("failed to import events for {}: {}", logging::redact_user(user_id), e);
+            HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to import events".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+}
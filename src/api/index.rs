@@ -3,14 +3,26 @@ use actix_files::NamedFile;
 use std::path::PathBuf;
 use crate::api::types::{DefaultMsg};
 
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "pong", body = DefaultMsg),
+    ),
+)]
 #[get("/ping")]
-async fn ping() -> impl Responder  {
+pub async fn ping() -> impl Responder  {
     HttpResponse::Ok().json(DefaultMsg {
         message: "pong".to_string(),
         message_code: "200".to_string()
     })
 }
 
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Demo page", content_type = "text/html"),
+    ),
+)]
 #[get("/")]
 pub async fn demo() -> Result<NamedFile> {
     let path = PathBuf::from("static/index.html");
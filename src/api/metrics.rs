@@ -0,0 +1,42 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::{Duration, Local};
+
+use crate::db;
+use crate::MyData;
+
+/// Prometheus scrape target. Pool and domain gauges are sampled here; a
+/// database outage leaves the domain gauges at their last value.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain"),
+        (status = 500, description = "Server error"),
+    ),
+)]
+#[get("/metrics")]
+pub async fn metrics(data: web::Data<MyData>) -> impl Responder {
+    let state = data.pool.state();
+    data.metrics.set_pool_state(state.connections, state.idle_connections);
+
+    match data.pool.get() {
+        Ok(mut conn) => {
+            let now = Local::now().naive_local();
+            let joined_since = now - Duration::minutes(1);
+            match db::get_event_gauges(&mut conn, now, joined_since) {
+                Ok(gauges) => data.metrics.set_event_gauges(&gauges),
+                Err(e) => log::warn!("failed to sample event gauges: {}", e),
+            }
+        }
+        Err(e) => log::warn!("failed to sample event gauges: {}", e),
+    }
+
+    match data.metrics.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(e) => {
+            log::error!("failed to render metrics: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -4,13 +4,27 @@ use actix_web::{post, HttpResponse, Responder};
 
 mod index;
 mod identify;
-mod types;
+pub mod types;
 mod user_info;
 mod events;
 mod event_related;
+mod payments;
+mod notifications;
+mod webhooks;
+mod admin;
+mod search;
+mod attachments;
+mod templates;
+mod checkin;
+mod export;
+mod metrics;
+mod health;
+mod import;
+mod openapi;
 
 mod identify_test;
 mod index_test;
+mod openapi_test;
 
 use crate::api::index::{demo, ping};
 use crate::api::types::DefaultError;
@@ -21,6 +35,15 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(demo);
     cfg.service(ping);
     cfg.service(login_mock);
+    cfg.service(metrics::metrics);
+    cfg.service(health::live);
+    cfg.service(health::ready);
+    // Ahead of the /api/v1 scope, which would otherwise answer 404 for it.
+    #[cfg(feature = "swagger-ui")]
+    cfg.service(
+        utoipa_swagger_ui::SwaggerUi::new("/api/v1/docs/{_:.*}")
+            .url("/api/v1/docs/openapi.json", openapi::spec()),
+    );
     cfg.service(web::scope("/api/v1")
         .service(user_login)
         .service(user_logout)
@@ -37,9 +60,60 @@ pub fn init(cfg: &mut web::ServiceConfig) {
         .service(event_related::add_event_msg)
         .service(event_related::get_event_msgs)
         .service(event_related::get_categories)
+        .service(event_related::get_tags)
+        .service(payments::get_event_payments)
+        .service(payments::record_payment)
+        .service(payments::mark_paid)
+        .service(payments::confirm_payment)
+        .service(payments::reject_payment)
+        .service(payments::create_checkout)
+        .service(payments::payment_webhook)
+        .service(events::cancel_event)
+        .service(events::clone_event)
+        .service(notifications::get_notifications)
+        .service(notifications::mark_all_notifications_read)
+        .service(notifications::mark_notification_read)
+        .service(notifications::get_notification_preferences)
+        .service(notifications::put_notification_preference)
+        .service(notifications::unsubscribe_page)
+        .service(notifications::unsubscribe)
+        .service(webhooks::create_webhook)
+        .service(webhooks::get_webhooks)
+        .service(webhooks::delete_webhook)
+        .service(webhooks::get_webhook_deliveries)
+        .service(admin::get_jobs)
+        .service(admin::retry_job)
+        .service(admin::create_category)
+        .service(admin::patch_category)
+        .service(admin::delete_category)
+        .service(search::search)
+        .service(attachments::upload_event_cover)
+        .service(attachments::upload_event_gallery)
+        .service(attachments::upload_comment_attachment)
+        .service(attachments::get_event_attachments)
+        .service(attachments::upload_avatar)
+        .service(attachments::get_avatar)
+        .service(attachments::delete_attachment)
+        .service(attachments::get_file)
+        .service(templates::get_templates)
+        .service(templates::create_template)
+        .service(templates::delete_template)
+        .service(templates::create_event_from_template)
+        .service(checkin::get_checkin_code)
+        .service(checkin::check_in)
+        .service(export::export_members)
+        .service(import::import_events)
+        .service(openapi::get_openapi)
     );
 }
 
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Session set to a test user"),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
 #[post("/")]
 pub async fn login_mock(
     session: Session,
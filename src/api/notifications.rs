@@ -0,0 +1,438 @@
+use actix_session::Session;
+use actix_web::http::header::ContentType;
+use actix_web::{get, post, put, HttpRequest, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{
+    NotificationList, NotificationPreference, NotificationQuery, UnsubscribeQuery, NOTIFICATION_KINDS,
+};
+use crate::db;
+use crate::email::templates::unsubscribe_confirmation;
+use crate::email::verify_unsubscribe_token;
+
+const DEFAULT_NOTIFICATION_LIMIT: i64 = 50;
+const MAX_NOTIFICATION_LIMIT: i64 = 200;
+
+#[utoipa::path(
+    tag = "notifications",
+    params(
+        ("user_id" = Uuid, Path, description = "User id, must be the logged in user"),
+        NotificationQuery,
+    ),
+    responses(
+        (status = 200, description = "Notifications, newest first", body = NotificationList),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/users/{user_id}/notifications")]
+pub async fn get_notifications(
+    path: web::Path<(Uuid,)>,
+    query: web::Query<NotificationQuery>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_NOTIFICATION_LIMIT)
+        .clamp(1, MAX_NOTIFICATION_LIMIT);
+    let notifications = db::get_notifications(&mut conn, user_id, query.unread_only, limit);
+    let unread_count = db::count_unread_notifications(&mut conn, user_id);
+
+    match (notifications, unread_count) {
+        (Ok(notifications), Ok(unread_count)) => HttpResponse::Ok().json(NotificationList {
+            unread_count,
+            notifications,
+        }),
+        _ => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get notifications".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    responses(
+        (status = 200, description = "All marked read", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[put("/users/{user_id}/notifications/read")]
+pub async fn mark_all_notifications_read(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::mark_all_notifications_read(&mut conn, user_id) {
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to mark notifications as read".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(
+        ("user_id" = Uuid, Path, description = "User id, must be the logged in user"),
+        ("notification_id" = Uuid, Path, description = "Notification id"),
+    ),
+    responses(
+        (status = 200, description = "Marked read", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[put("/users/{user_id}/notifications/{notification_id}/read")]
+pub async fn mark_notification_read(
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::mark_notification_read(&mut conn, user_id, path.1) {
+        Ok(0) => HttpResponse::NotFound().json(DefaultError {
+            message: "Notification not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to mark notification as read".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    responses(
+        (status = 200, description = "Stored preferences", body = [NotificationPreference]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/users/{user_id}/notification-preferences")]
+pub async fn get_notification_preferences(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_notification_preferences(&mut conn, user_id) {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get notification preferences".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    request_body = NotificationPreference,
+    responses(
+        (status = 200, description = "Stored preference", body = NotificationPreference),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[put("/users/{user_id}/notification-preferences")]
+pub async fn put_notification_preference(
+    path: web::Path<(Uuid,)>,
+    mut form: web::Json<NotificationPreference>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    if !NOTIFICATION_KINDS.contains(&form.kind.as_str()) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: format!("Unknown notification kind {}", form.kind),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    if !form.event_id.is_nil() && db::get_event_by_id(&mut conn, form.event_id, Uuid::nil()).is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+
+    form.user_id = user_id;
+    match db::set_notification_preference(&mut conn, form.into_inner()) {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to save notification preference".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+fn valid_unsubscribe_link(data: &MyData, query: &UnsubscribeQuery) -> bool {
+    NOTIFICATION_KINDS.contains(&query.kind.as_str())
+        && verify_unsubscribe_token(&data.email.secret, query.user_id, &query.kind, &query.token)
+}
+
+/// Links in emails get opened by scanners and previews, so this only asks
+/// for confirmation; the form posts back to `unsubscribe`.
+#[utoipa::path(
+    tag = "notifications",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 200, description = "Confirmation page", content_type = "text/html"),
+        (status = 400, description = "Invalid request", body = DefaultError),
+    ),
+)]
+#[get("/unsubscribe")]
+pub async fn unsubscribe_page(
+    req: HttpRequest,
+    query: web::Query<UnsubscribeQuery>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    if !valid_unsubscribe_link(&data, &query) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Invalid unsubscribe link".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let action = format!("{}?{}", req.path(), req.query_string());
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(unsubscribe_confirmation(&query.kind, &action))
+}
+
+/// Also the RFC 8058 one-click target: mail clients post
+/// `List-Unsubscribe=One-Click` to the `List-Unsubscribe` URL, which
+/// carries the token, so the body is ignored.
+#[utoipa::path(
+    tag = "notifications",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 200, description = "Unsubscribed", body = DefaultMsg),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[post("/unsubscribe")]
+pub async fn unsubscribe(
+    query: web::Query<UnsubscribeQuery>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    if !valid_unsubscribe_link(&data, &query) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Invalid unsubscribe link".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let preference = NotificationPreference {
+        user_id: query.user_id,
+        event_id: Uuid::nil(),
+        kind: query.kind.clone(),
+        enabled: false,
+    };
+    match db::set_notification_preference(&mut conn, preference) {
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Unsubscribed".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to unsubscribe".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
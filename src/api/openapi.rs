@@ -0,0 +1,206 @@
+use actix_web::{get, HttpResponse, Responder};
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, Schema, SchemaFormat, SchemaType};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::RefOr;
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use crate::api::{
+    admin, attachments, checkin, event_related, events, export, health, identify, import, index,
+    metrics, notifications, payments, search, templates, user_info, webhooks,
+};
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::models;
+use crate::money::Money;
+
+/// `multipart/form-data` body of the upload endpoints: a single `file`
+/// part, read by the handlers as a stream.
+pub struct FileUpload;
+
+impl<'s> ToSchema<'s> for FileUpload {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "FileUpload",
+            ObjectBuilder::new()
+                .property(
+                    "file",
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary))),
+                )
+                .required("file")
+                .into(),
+        )
+    }
+}
+
+struct SessionCookie;
+
+impl Modify for SessionCookie {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                "session",
+                "Set by POST /api/v1/login. Requests other than GET also need the X-CSRF-Token header from the latest response.",
+            ))),
+        );
+    }
+}
+
+/// Routes outside of `/api/v1`.
+#[derive(OpenApi)]
+#[openapi(paths(index::ping, index::demo, crate::api::login_mock, metrics::metrics, health::live, health::ready))]
+struct RootDoc;
+
+/// Everything in the `/api/v1` scope; paths are relative to it. Timestamps
+/// are unix seconds unless noted otherwise.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "O2Gather API"),
+    paths(
+        get_openapi,
+        identify::user_login,
+        identify::user_logout,
+        user_info::get_user,
+        user_info::patch_user,
+        events::create_event,
+        events::get_events,
+        events::get_event,
+        events::get_user_events,
+        events::patch_event,
+        events::delete_event,
+        event_related::join_event,
+        event_related::leave_event,
+        event_related::add_event_msg,
+        event_related::get_event_msgs,
+        event_related::get_categories,
+        event_related::get_tags,
+        payments::get_event_payments,
+        payments::record_payment,
+        payments::mark_paid,
+        payments::confirm_payment,
+        payments::reject_payment,
+        payments::create_checkout,
+        payments::payment_webhook,
+        events::cancel_event,
+        events::clone_event,
+        notifications::get_notifications,
+        notifications::mark_all_notifications_read,
+        notifications::mark_notification_read,
+        notifications::get_notification_preferences,
+        notifications::put_notification_preference,
+        notifications::unsubscribe_page,
+        notifications::unsubscribe,
+        webhooks::create_webhook,
+        webhooks::get_webhooks,
+        webhooks::delete_webhook,
+        webhooks::get_webhook_deliveries,
+        admin::get_jobs,
+        admin::retry_job,
+        admin::create_category,
+        admin::patch_category,
+        admin::delete_category,
+        search::search,
+        attachments::upload_event_cover,
+        attachments::upload_event_gallery,
+        attachments::upload_comment_attachment,
+        attachments::get_event_attachments,
+        attachments::upload_avatar,
+        attachments::get_avatar,
+        attachments::delete_attachment,
+        attachments::get_file,
+        templates::get_templates,
+        templates::create_template,
+        templates::delete_template,
+        templates::create_event_from_template,
+        checkin::get_checkin_code,
+        checkin::check_in,
+        export::export_members,
+        import::import_events,
+    ),
+    components(schemas(
+        DefaultError,
+        DefaultMsg,
+        FileUpload,
+        Money,
+        identify::LoginFormData,
+        models::User,
+        models::UpdateUser,
+        models::UserProfile,
+        models::AttendanceCounts,
+        models::NewEvent,
+        models::NewEventForm,
+        models::UpdateEvent,
+        models::UpdateEventForm,
+        models::CloneEventForm,
+        models::EventLocation,
+        models::Event,
+        models::EventWithMembers,
+        models::EventOwner,
+        models::EventMember,
+        models::PaymentSummary,
+        models::AttendanceSummary,
+        models::ImportReport,
+        models::ImportRowReport,
+        models::NewEventMember,
+        models::NewEventMsg,
+        models::EventMsg,
+        models::EventMsgUser,
+        models::Category,
+        models::CategoryWithCount,
+        models::NewCategory,
+        models::UpdateCategory,
+        models::TagCount,
+        models::Payment,
+        models::PaymentResponse,
+        models::NewPayment,
+        models::CheckoutResponse,
+        models::Notification,
+        models::NotificationList,
+        models::NotificationPreference,
+        models::WebhookEndpoint,
+        models::NewWebhookEndpoint,
+        models::CreatedWebhookEndpoint,
+        models::WebhookDelivery,
+        models::Job,
+        models::JobCount,
+        models::JobList,
+        models::SearchResults,
+        models::SearchHighlights,
+        models::EventSearchHit,
+        models::CommentSearchHit,
+        models::AttachmentView,
+        models::EventTemplate,
+        models::NewEventTemplate,
+        models::CheckinForm,
+        models::CheckinResult,
+        models::HealthReport,
+        models::ComponentHealth,
+    )),
+    modifiers(&SessionCookie),
+)]
+struct ApiDoc;
+
+/// The whole API with full paths, as served at `/api/v1/openapi.json`.
+pub fn spec() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    let paths = std::mem::take(&mut doc.paths.paths);
+    doc.paths.paths = paths
+        .into_iter()
+        .map(|(path, item)| (format!("/api/v1{}", path), item))
+        .collect();
+    doc.merge(RootDoc::openapi());
+    doc
+}
+
+#[utoipa::path(
+    tag = "docs",
+    responses(
+        (status = 200, description = "This document", content_type = "application/json"),
+    ),
+)]
+#[get("/openapi.json")]
+pub async fn get_openapi() -> impl Responder {
+    HttpResponse::Ok().json(spec())
+}
@@ -0,0 +1,123 @@
+/// An OpenAPI path with every `{param}` filled in. The nil uuid parses as
+/// any of the ids and as a category slug.
+#[cfg(test)]
+fn example_uri(path: &str) -> String {
+    let mut uri = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').unwrap() + start;
+        uri.push_str(&rest[..start]);
+        uri.push_str(&uuid::Uuid::nil().to_string());
+        rest = &rest[end + 1..];
+    }
+    uri.push_str(rest);
+    uri
+}
+
+#[cfg(test)]
+fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", serde_json::Value::String(r)) => refs.push(r.clone()),
+                    _ => collect_refs(value, refs),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter().for_each(|v| collect_refs(v, refs)),
+        _ => {}
+    }
+}
+
+#[actix_web::test]
+async fn test_documented_routes_registered() {
+    use crate::api::init;
+    use crate::api::openapi::spec;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::{test, web, App, HttpResponse};
+    use utoipa::openapi::PathItemType;
+
+    // Requests no route takes fall through to this; handlers can answer 404
+    // themselves, e.g. the demo page without its static file.
+    let app = test::init_service(
+        App::new()
+            .configure(init)
+            .default_service(web::to(HttpResponse::ImATeapot)),
+    )
+    .await;
+    let doc = spec();
+    let mut operation_ids: Vec<String> = Vec::new();
+    for (path, item) in doc.paths.paths.iter() {
+        for (method, operation) in item.operations.iter() {
+            let method = match method {
+                PathItemType::Get => Method::GET,
+                PathItemType::Post => Method::POST,
+                PathItemType::Put => Method::PUT,
+                PathItemType::Patch => Method::PATCH,
+                PathItemType::Delete => Method::DELETE,
+                _ => panic!("{} uses a method the API has no routes for", path),
+            };
+            // Without app data most handlers fail their extractors, which
+            // is fine; only reaching the default service is not.
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri(&example_uri(path))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_ne!(
+                resp.status(),
+                StatusCode::IM_A_TEAPOT,
+                "{} {} is documented but not registered in api::init",
+                method,
+                path
+            );
+            operation_ids.push(operation.operation_id.clone().unwrap());
+        }
+    }
+
+    assert!(operation_ids.len() > 50);
+    let mut unique = operation_ids.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), operation_ids.len());
+    assert!(doc.paths.paths.contains_key("/api/v1/events/{event_id}"));
+    assert!(doc.paths.paths.contains_key("/ping"));
+}
+
+#[test]
+fn test_schema_refs_resolve() {
+    use crate::api::openapi::spec;
+
+    let json = serde_json::to_value(spec()).unwrap();
+    let schemas = json["components"]["schemas"].as_object().unwrap();
+    let mut refs = Vec::new();
+    collect_refs(&json, &mut refs);
+    assert!(!refs.is_empty());
+    for r in refs {
+        let name = r.strip_prefix("#/components/schemas/").unwrap();
+        assert!(schemas.contains_key(name), "{} is not in components", name);
+    }
+    // ts_seconds fields are documented as the integers they serialize to.
+    assert_eq!(
+        json["components"]["schemas"]["Payment"]["properties"]["paid_at"]["type"],
+        "integer"
+    );
+    let upload = &json["components"]["schemas"]["FileUpload"];
+    assert_eq!(upload["properties"]["file"]["format"], "binary");
+    assert_eq!(upload["required"][0], "file");
+}
+
+#[actix_web::test]
+async fn test_openapi_json() {
+    use crate::api::init;
+    use actix_web::{test, App};
+
+    let app = test::init_service(App::new().configure(init)).await;
+
+    let req = test::TestRequest::get().uri("/api/v1/openapi.json").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["openapi"].as_str().unwrap().starts_with("3."));
+}
@@ -0,0 +1,649 @@
+use actix_session::Session;
+use actix_web::{get, post, put, HttpRequest, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{
+    CheckoutResponse, NewPayment, PaymentResponse, PAYMENT_KIND_PAYMENT, PAYMENT_KIND_REFUND, PAYMENT_STATUS_CONFIRMED,
+    PAYMENT_STATUS_PENDING, PAYMENT_STATUS_REJECTED,
+};
+use crate::db;
+use crate::payment::CheckoutRequest;
+
+fn payment_check(amount: i64, kind: &str) -> bool {
+    if amount <= 0 {
+        return false;
+    }
+    kind == PAYMENT_KIND_PAYMENT || kind == PAYMENT_KIND_REFUND
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "All payments for the owner, own payments for members", body = [PaymentResponse]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/events/{event_id}/payments")]
+pub async fn get_event_payments(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    let event = event.unwrap();
+
+    let result = if event.user_id == user_id {
+        db::get_payments_by_event_id(&mut conn, event_id)
+    } else {
+        let event_member = db::get_event_members(&mut conn, event_id);
+        if !event_member.contains(&user_id) {
+            return HttpResponse::Forbidden().json(DefaultError {
+                message: "You are not in this event".to_string(),
+                error_code: "403".to_string(),
+            });
+        }
+        db::get_payments_by_event_and_user(&mut conn, event_id, user_id)
+    };
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(
+            r.into_iter()
+                .map(|p| PaymentResponse::new(p, &event.currency))
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get payments".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = NewPayment,
+    responses(
+        (status = 200, description = "Recorded payment", body = PaymentResponse),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/payments")]
+pub async fn record_payment(
+    path: web::Path<(Uuid,)>,
+    mut form: web::Json<NewPayment>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    let event = event.unwrap();
+    if event.user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    if !event.established {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has not established yet".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if !payment_check(form.amount, &form.kind) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Amount should be positive and kind should be payment or refund".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    let event_member = db::get_event_members(&mut conn, event_id);
+    if !event_member.iter().any(|x| *x == form.user_id) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "User is not in this event".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    form.event_id = event_id;
+    form.status = PAYMENT_STATUS_CONFIRMED.to_string();
+    form.confirmed_at = Some(chrono::Utc::now().naive_utc());
+    form.provider_ref = None;
+    form.refund_of = None;
+    let result = db::create_payment(&mut conn, form.into_inner())
+        .and_then(|r| db::refresh_pledge_status(&mut conn, r.event_id, r.user_id).map(|_| r));
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(PaymentResponse::new(r, &event.currency)),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to record payment".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    request_body = NewPayment,
+    responses(
+        (status = 200, description = "Payment waiting for the owner to confirm", body = PaymentResponse),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/paid")]
+pub async fn mark_paid(
+    path: web::Path<(Uuid,)>,
+    mut form: web::Json<NewPayment>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    let event = event.unwrap();
+    if !event.established {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has not established yet".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    let event_member = db::get_event_members(&mut conn, event_id);
+    if !event_member.contains(&user_id) {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "You are not in this event".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+    if !payment_check(form.amount, PAYMENT_KIND_PAYMENT) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Amount should be positive".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    form.event_id = event_id;
+    form.user_id = user_id;
+    form.kind = PAYMENT_KIND_PAYMENT.to_string();
+    form.status = PAYMENT_STATUS_PENDING.to_string();
+    form.confirmed_at = None;
+    form.provider_ref = None;
+    form.refund_of = None;
+    let result = db::create_payment(&mut conn, form.into_inner())
+        .and_then(|r| db::refresh_pledge_status(&mut conn, r.event_id, r.user_id).map(|_| r));
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(PaymentResponse::new(r, &event.currency)),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to mark as paid".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+async fn review_payment(
+    event_id: Uuid,
+    payment_id: Uuid,
+    new_status: &str,
+    data: web::Data<MyData>,
+    session: Session,
+) -> HttpResponse {
+    let user_id: Uuid;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    let event = event.unwrap();
+    if event.user_id != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let payment = db::get_payment_by_id(&mut conn, payment_id);
+    let payment = match payment {
+        Some(p) if p.event_id == event_id => p,
+        _ => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Payment not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    };
+    if payment.status != PAYMENT_STATUS_PENDING {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Payment has already been reviewed".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let result = db::update_payment_status(&mut conn, payment_id, new_status)
+        .and_then(|r| db::refresh_pledge_status(&mut conn, r.event_id, r.user_id).map(|_| r));
+    match result {
+        Ok(r) => HttpResponse::Ok().json(PaymentResponse::new(r, &event.currency)),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to update payment".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(
+        ("event_id" = Uuid, Path, description = "Event id"),
+        ("payment_id" = Uuid, Path, description = "Payment id"),
+    ),
+    responses(
+        (status = 200, description = "Confirmed payment", body = PaymentResponse),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[put("/events/{event_id}/payments/{payment_id}/confirm")]
+pub async fn confirm_payment(
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    review_payment(path.0, path.1, PAYMENT_STATUS_CONFIRMED, data, session).await
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(
+        ("event_id" = Uuid, Path, description = "Event id"),
+        ("payment_id" = Uuid, Path, description = "Payment id"),
+    ),
+    responses(
+        (status = 200, description = "Rejected payment", body = PaymentResponse),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[put("/events/{event_id}/payments/{payment_id}/reject")]
+pub async fn reject_payment(
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    review_payment(path.0, path.1, PAYMENT_STATUS_REJECTED, data, session).await
+}
+
+#[utoipa::path(
+    tag = "payments",
+    params(("event_id" = Uuid, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Provider checkout to redirect to", body = CheckoutResponse),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+        (status = 502, description = "Payment provider error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/events/{event_id}/checkout")]
+pub async fn create_checkout(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    let event_id = path.0;
+
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let event = db::get_event_by_id(&mut conn, event_id, Uuid::nil());
+    if event.is_none() {
+        return HttpResponse::NotFound().json(DefaultError {
+            message: "Event not found".to_string(),
+            error_code: "404".to_string(),
+        });
+    }
+    let event = event.unwrap();
+    if !event.established {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has not established yet".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if event.cancelled {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Event has been cancelled".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let outstanding = match db::get_member_outstanding(&mut conn, event_id, user_id) {
+        Ok(o) => o,
+        Err(diesel::result::Error::NotFound) => {
+            return HttpResponse::Forbidden().json(DefaultError {
+                message: "You are not in this event".to_string(),
+                error_code: "403".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get balance".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    };
+    if outstanding == 0 {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Nothing left to pay".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+
+    let provider = data.payment_provider.clone();
+    let payment = db::create_payment(&mut conn, NewPayment {
+        event_id,
+        user_id,
+        amount: outstanding,
+        kind: PAYMENT_KIND_PAYMENT.to_string(),
+        method: provider.name().to_string(),
+        reference: "".to_string(),
+        status: PAYMENT_STATUS_PENDING.to_string(),
+        paid_at: None,
+        confirmed_at: None,
+        provider_ref: None,
+        refund_of: None,
+    });
+    let payment = match payment {
+        Ok(p) => p,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to create payment".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    };
+
+    let checkout_request = CheckoutRequest {
+        payment_id: payment.id,
+        event_id,
+        user_id,
+        amount: outstanding,
+        currency: event.currency.clone(),
+        description: event.name.clone(),
+        success_url: format!("{}?event_id={}&payment=success", data.redirect_url, event_id),
+        cancel_url: format!("{}?event_id={}&payment=cancel", data.redirect_url, event_id),
+    };
+    let checkout = match provider.create_checkout(&checkout_request).await {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("checkout for payment {} failed: {}", payment.id, e);
+            db::update_payment_status(&mut conn, payment.id, PAYMENT_STATUS_REJECTED).ok();
+            return HttpResponse::BadGateway().json(DefaultError {
+                message: "Payment provider is unavailable".to_string(),
+                error_code: "502".to_string(),
+            });
+        }
+    };
+
+    match db::set_payment_provider_ref(&mut conn, payment.id, &checkout.provider_ref) {
+        Ok(_) => HttpResponse::Ok().json(CheckoutResponse {
+            payment_id: payment.id,
+            url: checkout.url,
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to save checkout".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "payments",
+    request_body(content = String, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Event processed", body = DefaultMsg),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 401, description = "Unauthorized", body = DefaultError),
+        (status = 404, description = "Unknown checkout; the provider should retry", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[post("/payments/webhook")]
+pub async fn payment_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    let provider = data.payment_provider.clone();
+    let signature = req
+        .headers()
+        .get(provider.signature_header())
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if !provider.verify_webhook(&body, signature) {
+        return HttpResponse::Unauthorized().json(DefaultError {
+            message: "Invalid signature".to_string(),
+            error_code: "401".to_string(),
+        });
+    }
+
+    let event = match provider.parse_event(&body) {
+        Ok(e) => e,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: e.to_string(),
+                error_code: "400".to_string(),
+            });
+        }
+    };
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::reconcile_provider_event(&mut conn, provider.name(), &event) {
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().json(DefaultError {
+            message: "Checkout not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to reconcile payment".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
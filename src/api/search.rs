@@ -0,0 +1,82 @@
+use actix_web::{get, HttpResponse, Responder, web};
+
+use crate::api::types::DefaultError;
+use crate::MyData;
+use crate::models::{SearchQuery, SearchResults};
+use crate::db;
+use crate::search::{comment_hit, event_hit, terms, SearchFilter};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+const MAX_QUERY_CHARS: usize = 200;
+
+#[utoipa::path(
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Ranked matches", body = SearchResults),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+)]
+#[get("/search")]
+pub async fn search(
+    query: web::Query<SearchQuery>,
+    data: web::Data<MyData>,
+) -> impl Responder {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "Search query is required".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if q.chars().count() > MAX_QUERY_CHARS {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: format!("Search query should be at most {} characters", MAX_QUERY_CHARS),
+            error_code: "400".to_string(),
+        });
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message: "from should be earlier than to".to_string(),
+                error_code: "400".to_string(),
+            });
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let filter = SearchFilter::new(&query, limit);
+    let terms = terms(q);
+
+    let events = match db::search_events(&mut conn, &filter) {
+        Ok(rows) => rows.into_iter().map(|r| event_hit(r, &terms)).collect(),
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to search events".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    };
+    let comments = if query.comments {
+        match db::search_comments(&mut conn, &filter) {
+            Ok(rows) => Some(rows.into_iter().map(|r| comment_hit(r, &terms)).collect()),
+            Err(_) => {
+                return HttpResponse::InternalServerError().json(DefaultError {
+                    message: "Failed to search comments".to_string(),
+                    error_code: "500".to_string(),
+                })
+            }
+        }
+    } else {
+        None
+    };
+
+    HttpResponse::Ok().json(SearchResults { events, comments })
+}
@@ -0,0 +1,305 @@
+use actix_session::Session;
+use actix_web::{delete, get, post, HttpResponse, Responder, web};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::api::events::{insert_event, validate_new_event};
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{CloneEventForm, NewEventTemplate};
+use crate::db;
+use crate::templates::{duration_check, event_from_new_template, event_from_template, title_check};
+
+#[utoipa::path(
+    tag = "templates",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    responses(
+        (status = 200, description = "Saved templates", body = [EventTemplate]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/users/{user_id}/templates")]
+pub async fn get_templates(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_event_templates(&mut conn, user_id) {
+        Ok(templates) => HttpResponse::Ok().json(templates),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get templates".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "templates",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    request_body = NewEventTemplate,
+    responses(
+        (status = 200, description = "Saved template", body = EventTemplate),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/users/{user_id}/templates")]
+pub async fn create_template(
+    path: web::Path<(Uuid,)>,
+    mut form: web::Json<NewEventTemplate>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    form.user_id = user_id;
+    form.title = form.title.trim().to_string();
+    if let Err(e) = title_check(&form.title).and(duration_check(form.duration_secs)) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: e,
+            error_code: "400".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    // Run the template through the same checks as a real event; the start
+    // time doesn't matter for any of them.
+    let mut event = event_from_new_template(&form, Utc::now().naive_utc());
+    let mut tags = form.tags.clone();
+    if let Err(response) = validate_new_event(&mut conn, &mut event, &mut tags) {
+        return *response;
+    }
+    form.currency = event.currency;
+    form.tags = tags;
+
+    match db::create_event_template(&mut conn, form.into_inner()) {
+        Ok(template) => HttpResponse::Ok().json(template),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to create template".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "templates",
+    params(
+        ("user_id" = Uuid, Path, description = "User id, must be the logged in user"),
+        ("template_id" = Uuid, Path, description = "Template id"),
+    ),
+    responses(
+        (status = 200, description = "Template deleted", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[delete("/users/{user_id}/templates/{template_id}")]
+pub async fn delete_template(
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::delete_event_template(&mut conn, user_id, path.1) {
+        Ok(0) => HttpResponse::NotFound().json(DefaultError {
+            message: "Template not found".to_string(),
+            error_code: "404".to_string(),
+        }),
+        Ok(_) => HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to delete template".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "templates",
+    params(
+        ("user_id" = Uuid, Path, description = "User id, must be the logged in user"),
+        ("template_id" = Uuid, Path, description = "Template id"),
+    ),
+    request_body = CloneEventForm,
+    responses(
+        (status = 200, description = "New event", body = EventWithMembers),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/users/{user_id}/templates/{template_id}/events")]
+pub async fn create_event_from_template(
+    path: web::Path<(Uuid, Uuid)>,
+    form: web::Json<CloneEventForm>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+    if path.0 != user_id {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let template = match db::get_event_template(&mut conn, user_id, path.1) {
+        Ok(Some(template)) => template,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Template not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get template".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    };
+
+    let mut event = event_from_template(&template, form.start_time);
+    let mut tags = template.tags.clone();
+    if let Err(response) = validate_new_event(&mut conn, &mut event, &mut tags) {
+        return *response;
+    }
+    match insert_event(&mut conn, event, &tags) {
+        Ok(event) => HttpResponse::Ok().json(event),
+        Err(response) => *response,
+    }
+}
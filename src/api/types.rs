@@ -1,12 +1,15 @@
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
+/// Sent with every 4xx and 5xx. The response also carries a `request_id`
+/// field, matching the `X-Request-Id` header, to quote in bug reports.
+#[derive(Serialize, ToSchema)]
 pub struct DefaultError {
     pub message: String,
     pub error_code: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct DefaultMsg {
     pub message: String,
     pub message_code: String,
@@ -5,10 +5,20 @@ use uuid::Uuid;
 use crate::api::types::{DefaultError};
 use crate::MyData;
 use crate::PgPooledConnection;
-use crate::db::{get_user_by_id, update_user};
-use crate::models::User;
+use crate::db::{get_attendance_counts, get_user_by_id, update_user};
+use crate::models::{User, UserProfile};
 use crate::models::UpdateUser;
 
+#[utoipa::path(
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    responses(
+        (status = 200, description = "Profile with attendance record", body = UserProfile),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[get("/users/{user_id}")]
 pub async fn get_user(
     path: web::Path<(Uuid,)>,
@@ -50,10 +60,26 @@ pub async fn get_user(
         .expect("couldn't get db connection from pool");
 
     let user = get_user_by_id(&mut conn, user_id);
-
-    HttpResponse::Ok().json(user)
+    match get_attendance_counts(&mut conn, user_id, chrono::Local::now().naive_local()) {
+        Ok(attendance) => HttpResponse::Ok().json(UserProfile { user, attendance }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get attendance".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
 }
 
+#[utoipa::path(
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User id, must be the logged in user")),
+    request_body = UpdateUser,
+    responses(
+        (status = 200, description = "Updated user", body = User),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
 #[patch("/users/{user_id}")]
 pub async fn patch_user(
     path: web::Path<(Uuid,)>,
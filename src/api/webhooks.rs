@@ -0,0 +1,318 @@
+use actix_session::Session;
+use actix_web::{delete, get, post, HttpResponse, Responder, web};
+use uuid::Uuid;
+
+use crate::api::types::{DefaultError, DefaultMsg};
+use crate::MyData;
+use crate::models::{CreatedWebhookEndpoint, NewWebhookEndpoint, WEBHOOK_EVENT_TYPES};
+use crate::db;
+use crate::webhooks::{check_endpoint_url, generate_secret};
+
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+#[utoipa::path(
+    tag = "webhooks",
+    request_body = NewWebhookEndpoint,
+    responses(
+        (status = 201, description = "Registered endpoint with its signing secret", body = CreatedWebhookEndpoint),
+        (status = 400, description = "Invalid request", body = DefaultError),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[post("/webhooks")]
+pub async fn create_webhook(
+    mut form: web::Json<NewWebhookEndpoint>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let url = form.url.clone();
+    match web::block(move || check_endpoint_url(&url)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(message)) => {
+            return HttpResponse::BadRequest().json(DefaultError {
+                message,
+                error_code: "400".to_string(),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to check webhook url".to_string(),
+                error_code: "500".to_string(),
+            });
+        }
+    }
+    if form.event_types.is_empty() {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: "At least one event type is required".to_string(),
+            error_code: "400".to_string(),
+        });
+    }
+    if let Some(t) = form.event_types.iter().find(|t| !WEBHOOK_EVENT_TYPES.contains(&t.as_str())) {
+        return HttpResponse::BadRequest().json(DefaultError {
+            message: format!("Unknown event type {}", t),
+            error_code: "400".to_string(),
+        });
+    }
+    form.event_types.sort();
+    form.event_types.dedup();
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    // Per-event endpoints belong to the event owner, global ones to admins.
+    let allowed = match form.event_id {
+        Some(event_id) => match db::get_event_by_id(&mut conn, event_id, Uuid::nil()) {
+            Some(event) => event.user_id == user_id || db::is_admin(&mut conn, user_id),
+            None => {
+                return HttpResponse::NotFound().json(DefaultError {
+                    message: "Event not found".to_string(),
+                    error_code: "404".to_string(),
+                });
+            }
+        },
+        None => db::is_admin(&mut conn, user_id),
+    };
+    if !allowed {
+        return HttpResponse::Forbidden().json(DefaultError {
+            message: "Forbidden".to_string(),
+            error_code: "403".to_string(),
+        });
+    }
+
+    form.user_id = user_id;
+    form.secret = generate_secret();
+    let secret = form.secret.clone();
+    match db::create_webhook_endpoint(&mut conn, form.into_inner()) {
+        Ok(endpoint) => HttpResponse::Created().json(CreatedWebhookEndpoint { endpoint, secret }),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to create webhook".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Endpoints of the user, or all for admins", body = [WebhookEndpoint]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/webhooks")]
+pub async fn get_webhooks(
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    let all = db::is_admin(&mut conn, user_id);
+    match db::get_webhook_endpoints(&mut conn, user_id, all) {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get webhooks".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook endpoint id")),
+    responses(
+        (status = 200, description = "Endpoint removed", body = DefaultMsg),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[delete("/webhooks/{webhook_id}")]
+pub async fn delete_webhook(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_webhook_endpoint(&mut conn, path.0) {
+        Some(endpoint) => {
+            if endpoint.user_id != user_id && !db::is_admin(&mut conn, user_id) {
+                return HttpResponse::Forbidden().json(DefaultError {
+                    message: "Forbidden".to_string(),
+                    error_code: "403".to_string(),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Webhook not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    }
+
+    if db::delete_webhook_endpoint(&mut conn, path.0) {
+        HttpResponse::Ok().json(DefaultMsg {
+            message: "Success".to_string(),
+            message_code: "200".to_string(),
+        })
+    } else {
+        HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to delete webhook".to_string(),
+            error_code: "500".to_string(),
+        })
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook endpoint id")),
+    responses(
+        (status = 200, description = "Recent deliveries", body = [WebhookDelivery]),
+        (status = 403, description = "Not logged in or not allowed", body = DefaultError),
+        (status = 404, description = "Not found", body = DefaultError),
+        (status = 500, description = "Server error", body = DefaultError),
+    ),
+    security(("session" = [])),
+)]
+#[get("/webhooks/{webhook_id}/deliveries")]
+pub async fn get_webhook_deliveries(
+    path: web::Path<(Uuid,)>,
+    data: web::Data<MyData>,
+    session: Session,
+) -> impl Responder {
+    let user_id: Uuid;
+    match session.get::<Uuid>("user_id") {
+        Ok(id) => {
+            match id {
+                Some(id) => {
+                    user_id = id;
+                }
+                None => {
+                    return HttpResponse::Forbidden().json(DefaultError {
+                        message: "Forbidden".to_string(),
+                        error_code: "403".to_string(),
+                    });
+                }
+            }
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(DefaultError {
+                message: "Failed to get session".to_string(),
+                error_code: "500".to_string(),
+            })
+        }
+    }
+
+    let mut conn = data
+        .pool
+        .get()
+        .expect("couldn't get db connection from pool");
+
+    match db::get_webhook_endpoint(&mut conn, path.0) {
+        Some(endpoint) => {
+            if endpoint.user_id != user_id && !db::is_admin(&mut conn, user_id) {
+                return HttpResponse::Forbidden().json(DefaultError {
+                    message: "Forbidden".to_string(),
+                    error_code: "403".to_string(),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::NotFound().json(DefaultError {
+                message: "Webhook not found".to_string(),
+                error_code: "404".to_string(),
+            });
+        }
+    }
+
+    match db::get_webhook_deliveries(&mut conn, path.0, DELIVERY_LOG_LIMIT) {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(_) => HttpResponse::InternalServerError().json(DefaultError {
+            message: "Failed to get webhook deliveries".to_string(),
+            error_code: "500".to_string(),
+        }),
+    }
+}
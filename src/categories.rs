@@ -0,0 +1,52 @@
+const MAX_SLUG_CHARS: usize = 64;
+const MAX_NAME_CHARS: usize = 64;
+/// Deepest chain of parents we follow before assuming something is off.
+const MAX_DEPTH: usize = 8;
+
+/// Slugs of new categories are lowercase ASCII words joined by `-`.
+/// Categories carried over from free-text event categories keep their
+/// original values and aren't checked.
+pub fn slug_check(slug: &str) -> Result<(), String> {
+    if slug.is_empty() || slug.len() > MAX_SLUG_CHARS {
+        return Err(format!("Category slug should be 1 to {} characters", MAX_SLUG_CHARS));
+    }
+    let valid = slug
+        .split('-')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    if !valid {
+        return Err(format!(
+            "Category slug {} should only use lowercase letters, digits and single dashes",
+            slug
+        ));
+    }
+    Ok(())
+}
+
+pub fn name_check(name: &str) -> Result<(), String> {
+    let length = name.trim().chars().count();
+    if length == 0 || length > MAX_NAME_CHARS {
+        return Err(format!("Category name should be 1 to {} characters", MAX_NAME_CHARS));
+    }
+    Ok(())
+}
+
+/// Checks that `parent` can be the parent of `slug`: it has to exist and
+/// must not be `slug` itself or one of its descendants. `parent_of`
+/// returns `None` for an unknown category and `Some(parent)` otherwise.
+pub fn parent_check<F>(slug: &str, parent: &str, mut parent_of: F) -> Result<(), String>
+where
+    F: FnMut(&str) -> Option<Option<String>>,
+{
+    let mut current = parent.to_string();
+    for _ in 0..MAX_DEPTH {
+        if current == slug {
+            return Err(format!("Category {} can't be nested under itself", slug));
+        }
+        match parent_of(&current) {
+            None if current == parent => return Err(format!("Unknown category {}", parent)),
+            None | Some(None) => return Ok(()),
+            Some(Some(next)) => current = next,
+        }
+    }
+    Err(format!("Categories can be nested at most {} levels deep", MAX_DEPTH))
+}
@@ -0,0 +1,45 @@
+#[test]
+fn test_slug_and_name_check() {
+    use crate::categories::{name_check, slug_check};
+
+    assert!(slug_check("outdoor").is_ok());
+    assert!(slug_check("board-games-2").is_ok());
+    assert!(slug_check("").is_err());
+    assert!(slug_check("Outdoor").is_err());
+    assert!(slug_check("board--games").is_err());
+    assert!(slug_check("-games").is_err());
+    assert!(slug_check("戶外").is_err());
+    assert!(slug_check(&"a".repeat(65)).is_err());
+
+    assert!(name_check("戶外活動").is_ok());
+    assert!(name_check("  ").is_err());
+    assert!(name_check(&"名".repeat(65)).is_err());
+}
+
+#[test]
+fn test_parent_check() {
+    use crate::categories::parent_check;
+    use std::collections::HashMap;
+
+    // sports <- ball <- basketball
+    let tree: HashMap<&str, Option<&str>> = [
+        ("sports", None),
+        ("ball", Some("sports")),
+        ("basketball", Some("ball")),
+        ("food", None),
+    ]
+    .into_iter()
+    .collect();
+    let parent_of = |slug: &str| tree.get(slug).map(|p| p.map(|p| p.to_string()));
+
+    assert!(parent_check("basketball", "ball", parent_of).is_ok());
+    assert!(parent_check("new", "basketball", parent_of).is_ok());
+    assert!(parent_check("sports", "food", parent_of).is_ok());
+    assert_eq!(
+        parent_check("new", "missing", parent_of),
+        Err("Unknown category missing".to_string())
+    );
+    assert!(parent_check("sports", "sports", parent_of).is_err());
+    // Moving sports under its own grandchild would make a loop.
+    assert!(parent_check("sports", "basketball", parent_of).is_err());
+}
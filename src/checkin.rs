@@ -0,0 +1,57 @@
+use image::{GrayImage, ImageOutputFormat, Luma};
+use qrcode::render::svg;
+use qrcode::{Color, QrCode};
+use std::io::Cursor;
+use uuid::Uuid;
+
+pub const QR_FORMAT_PNG: &str = "png";
+pub const QR_FORMAT_SVG: &str = "svg";
+/// Light modules around the code, as the QR spec asks for.
+const QUIET_ZONE: u32 = 4;
+const MODULE_PX: u32 = 8;
+
+/// Random, unguessable token a member shows at the event. Whoever holds it
+/// can be checked in, so it's never derived from ids.
+pub fn generate_token() -> String {
+    format!("ci_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Scanners sometimes add whitespace or a trailing newline.
+pub fn clean_token(scanned: &str) -> &str {
+    scanned.trim()
+}
+
+pub fn qr_svg(data: &str) -> Result<String, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(code
+        .render::<svg::Color>()
+        .quiet_zone(true)
+        .module_dimensions(MODULE_PX, MODULE_PX)
+        .build())
+}
+
+pub fn qr_png(data: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| e.to_string())?;
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (width + 2 * QUIET_ZONE) * MODULE_PX;
+
+    let image = GrayImage::from_fn(size, size, |x, y| {
+        let (mx, my) = (x / MODULE_PX, y / MODULE_PX);
+        let inside = (QUIET_ZONE..QUIET_ZONE + width).contains(&mx)
+            && (QUIET_ZONE..QUIET_ZONE + width).contains(&my);
+        let dark = inside
+            && colors[((my - QUIET_ZONE) * width + (mx - QUIET_ZONE)) as usize] == Color::Dark;
+        if dark {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    });
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
@@ -0,0 +1,35 @@
+#[test]
+fn test_generate_token() {
+    use crate::checkin::{clean_token, generate_token};
+
+    let token = generate_token();
+    assert!(token.starts_with("ci_"));
+    assert_eq!(token.len(), 3 + 64);
+    assert_ne!(token, generate_token());
+    assert_eq!(clean_token(&format!(" {}\n", token)), token);
+}
+
+#[test]
+fn test_qr_png() {
+    use crate::checkin::qr_png;
+
+    let data = "ci_0123456789abcdef";
+    let modules = qrcode::QrCode::new(data.as_bytes()).unwrap().width() as u32;
+    let png = qr_png(data).unwrap();
+    let image = image::load_from_memory(&png).unwrap().to_luma8();
+    assert_eq!(image.width(), image.height());
+    // Code plus a 4 module quiet zone on each side, 8px per module.
+    assert_eq!(image.width(), (modules + 8) * 8);
+    // Quiet zone is light, the finder pattern's corner is dark.
+    assert_eq!(image.get_pixel(0, 0).0, [255]);
+    assert_eq!(image.get_pixel(4 * 8, 4 * 8).0, [0]);
+}
+
+#[test]
+fn test_qr_svg() {
+    use crate::checkin::qr_svg;
+
+    let svg = qr_svg("ci_0123456789abcdef").unwrap();
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("</svg>"));
+}
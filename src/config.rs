@@ -0,0 +1,375 @@
+use actix_web::http::header::HeaderName;
+use actix_web::http::Method;
+use lettre::message::Mailbox;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::cors::{self, CorsSettings, OriginPattern};
+use crate::logging::LogFormat;
+use crate::ratelimit::RateLimits;
+use crate::telemetry::DEFAULT_SAMPLE_RATIO;
+
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+/// `actix_web::cookie::Key` needs at least this much.
+pub const MIN_SECRET_KEY_LEN: usize = 64;
+
+/// Server settings, read from a TOML file and then overridden by env vars
+/// with the upper-cased key, e.g. `pool_size` by `POOL_SIZE`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database_url: String,
+    pub redis_url: String,
+    pub google_client_id: String,
+    pub secret_key: String,
+    /// `dev` binds to localhost only.
+    pub stage: String,
+    pub redirect_url: String,
+    pub public_url: String,
+    pub email_from: String,
+    /// Defaults to 127.0.0.1 in dev and 0.0.0.0 otherwise.
+    pub host: Option<String>,
+    pub port: u16,
+    pub pool_size: u32,
+    /// How long an idle session stays in Redis.
+    pub session_ttl_secs: i64,
+    pub cors: bool,
+    /// Exact origins or `https://*.example.com`; required with `cors`.
+    pub cors_origins: Vec<String>,
+    pub cors_methods: Vec<String>,
+    pub cors_headers: Vec<String>,
+    pub cors_max_age_secs: usize,
+    pub log_format: LogFormat,
+    pub log_user_ids: bool,
+    /// Requests per window and user (or IP, when logged out); 0 turns a
+    /// group off.
+    pub rate_limit_window_secs: u64,
+    pub rate_limit_comments: u64,
+    pub rate_limit_events: u64,
+    pub rate_limit_auth: u64,
+    /// Behind a proxy that sets `X-Forwarded-For`.
+    pub rate_limit_trust_proxy: bool,
+    /// Traces are only exported when set.
+    pub otel_exporter_otlp_endpoint: Option<String>,
+    pub otel_traces_sampler_arg: f64,
+    /// `stripe` or `fake`.
+    pub payment_provider: String,
+    /// Required with `stripe`.
+    pub payment_webhook_secret: String,
+    pub stripe_secret_key: String,
+    /// `smtp`, `file` or `capture`.
+    pub email_transport: String,
+    pub smtp_url: String,
+    pub email_dir: String,
+    /// `local` or `s3`.
+    pub storage_backend: String,
+    pub storage_dir: String,
+    pub s3_endpoint: String,
+    pub s3_bucket: String,
+    pub s3_region: String,
+    pub s3_access_key: String,
+    pub s3_secret_key: String,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            database_url: String::new(),
+            redis_url: String::new(),
+            google_client_id: String::new(),
+            secret_key: String::new(),
+            stage: "dev".to_string(),
+            redirect_url: String::new(),
+            public_url: String::new(),
+            email_from: "O2Gather <noreply@o2gather.app>".to_string(),
+            host: None,
+            port: 8080,
+            pool_size: 10,
+            session_ttl_secs: 24 * 60 * 60,
+            cors: false,
+            cors_origins: Vec::new(),
+            cors_methods: cors::DEFAULT_METHODS.iter().map(|m| m.to_string()).collect(),
+            cors_headers: cors::DEFAULT_HEADERS.iter().map(|h| h.to_string()).collect(),
+            cors_max_age_secs: cors::DEFAULT_MAX_AGE_SECS,
+            log_format: LogFormat::Text,
+            log_user_ids: false,
+            rate_limit_window_secs: 60,
+            rate_limit_comments: 10,
+            rate_limit_events: 10,
+            rate_limit_auth: 10,
+            rate_limit_trust_proxy: false,
+            otel_exporter_otlp_endpoint: None,
+            otel_traces_sampler_arg: DEFAULT_SAMPLE_RATIO,
+            payment_provider: "fake".to_string(),
+            payment_webhook_secret: String::new(),
+            stripe_secret_key: String::new(),
+            email_transport: "file".to_string(),
+            smtp_url: String::new(),
+            email_dir: "mail".to_string(),
+            storage_backend: "local".to_string(),
+            storage_dir: "uploads".to_string(),
+            s3_endpoint: String::new(),
+            s3_bucket: String::new(),
+            s3_region: "us-east-1".to_string(),
+            s3_access_key: String::new(),
+            s3_secret_key: String::new(),
+        }
+    }
+}
+
+fn set<T: FromStr>(env: &HashMap<String, String>, key: &str, target: &mut T, errors: &mut Vec<String>)
+where
+    T::Err: Display,
+{
+    if let Some(value) = env.get(key) {
+        match value.parse() {
+            Ok(value) => *target = value,
+            Err(e) => errors.push(format!("{}: {}", key, e)),
+        }
+    }
+}
+
+fn set_optional(env: &HashMap<String, String>, key: &str, target: &mut Option<String>) {
+    if let Some(value) = env.get(key) {
+        *target = Some(value.clone()).filter(|value| !value.is_empty());
+    }
+}
+
+/// Comma-separated lists, e.g. `CORS_ORIGINS=https://a.example,https://b.example`.
+/// An empty value keeps the default.
+fn set_list(env: &HashMap<String, String>, key: &str, target: &mut Vec<String>) {
+    if let Some(value) = env.get(key).filter(|value| !value.trim().is_empty()) {
+        *target = value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+}
+
+fn check_required(errors: &mut Vec<String>, key: &str, value: &str) {
+    if value.is_empty() {
+        errors.push(format!("{} is required", key));
+    }
+}
+
+fn check_url(errors: &mut Vec<String>, key: &str, value: &str, schemes: &[&str]) {
+    if value.is_empty() {
+        errors.push(format!("{} is required", key));
+        return;
+    }
+    match reqwest::Url::parse(value) {
+        Ok(url) if schemes.contains(&url.scheme()) => {}
+        Ok(url) => errors.push(format!(
+            "{}: scheme should be {}, not {}",
+            key,
+            schemes.join(" or "),
+            url.scheme()
+        )),
+        Err(e) => errors.push(format!("{}: {}", key, e)),
+    }
+}
+
+impl Config {
+    /// `file` is the TOML text, if there is a config file. All problems are
+    /// returned at once.
+    pub fn from_sources(file: Option<&str>, env: &HashMap<String, String>) -> Result<Config, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut config = match file.map(toml::from_str::<Config>) {
+            Some(Ok(config)) => config,
+            Some(Err(e)) => return Err(vec![format!("config file: {}", e)]),
+            None => Config::default(),
+        };
+
+        set(env, "DATABASE_URL", &mut config.database_url, &mut errors);
+        set(env, "REDIS_URL", &mut config.redis_url, &mut errors);
+        set(env, "GOOGLE_CLIENT_ID", &mut config.google_client_id, &mut errors);
+        set(env, "SECRET_KEY", &mut config.secret_key, &mut errors);
+        set(env, "STAGE", &mut config.stage, &mut errors);
+        set(env, "REDIRECT_URL", &mut config.redirect_url, &mut errors);
+        set(env, "PUBLIC_URL", &mut config.public_url, &mut errors);
+        set(env, "EMAIL_FROM", &mut config.email_from, &mut errors);
+        set_optional(env, "HOST", &mut config.host);
+        set(env, "PORT", &mut config.port, &mut errors);
+        set(env, "POOL_SIZE", &mut config.pool_size, &mut errors);
+        set(env, "SESSION_TTL_SECS", &mut config.session_ttl_secs, &mut errors);
+        set(env, "CORS", &mut config.cors, &mut errors);
+        set_list(env, "CORS_ORIGINS", &mut config.cors_origins);
+        set_list(env, "CORS_METHODS", &mut config.cors_methods);
+        set_list(env, "CORS_HEADERS", &mut config.cors_headers);
+        set(env, "CORS_MAX_AGE_SECS", &mut config.cors_max_age_secs, &mut errors);
+        if let Some(format) = env.get("LOG_FORMAT") {
+            match LogFormat::parse(format) {
+                Some(format) => config.log_format = format,
+                None => errors.push("LOG_FORMAT: should be text or json".to_string()),
+            }
+        }
+        set(env, "LOG_USER_IDS", &mut config.log_user_ids, &mut errors);
+        set(env, "RATE_LIMIT_WINDOW_SECS", &mut config.rate_limit_window_secs, &mut errors);
+        set(env, "RATE_LIMIT_COMMENTS", &mut config.rate_limit_comments, &mut errors);
+        set(env, "RATE_LIMIT_EVENTS", &mut config.rate_limit_events, &mut errors);
+        set(env, "RATE_LIMIT_AUTH", &mut config.rate_limit_auth, &mut errors);
+        set(env, "RATE_LIMIT_TRUST_PROXY", &mut config.rate_limit_trust_proxy, &mut errors);
+        set_optional(env, "OTEL_EXPORTER_OTLP_ENDPOINT", &mut config.otel_exporter_otlp_endpoint);
+        set(env, "OTEL_TRACES_SAMPLER_ARG", &mut config.otel_traces_sampler_arg, &mut errors);
+        set(env, "PAYMENT_PROVIDER", &mut config.payment_provider, &mut errors);
+        set(env, "PAYMENT_WEBHOOK_SECRET", &mut config.payment_webhook_secret, &mut errors);
+        set(env, "STRIPE_SECRET_KEY", &mut config.stripe_secret_key, &mut errors);
+        set(env, "EMAIL_TRANSPORT", &mut config.email_transport, &mut errors);
+        set(env, "SMTP_URL", &mut config.smtp_url, &mut errors);
+        set(env, "EMAIL_DIR", &mut config.email_dir, &mut errors);
+        set(env, "STORAGE_BACKEND", &mut config.storage_backend, &mut errors);
+        set(env, "STORAGE_DIR", &mut config.storage_dir, &mut errors);
+        set(env, "S3_ENDPOINT", &mut config.s3_endpoint, &mut errors);
+        set(env, "S3_BUCKET", &mut config.s3_bucket, &mut errors);
+        set(env, "S3_REGION", &mut config.s3_region, &mut errors);
+        set(env, "S3_ACCESS_KEY", &mut config.s3_access_key, &mut errors);
+        set(env, "S3_SECRET_KEY", &mut config.s3_secret_key, &mut errors);
+
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Reads `CONFIG_FILE`, or `config.toml` if present, then the process
+    /// env.
+    pub fn load() -> Result<Config, Vec<String>> {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let file = match env.get("CONFIG_FILE").filter(|path| !path.is_empty()) {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Some(text),
+                Err(e) => return Err(vec![format!("CONFIG_FILE {}: {}", path, e)]),
+            },
+            None => std::fs::read_to_string(DEFAULT_CONFIG_FILE).ok(),
+        };
+        Config::from_sources(file.as_deref(), &env)
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        check_url(&mut errors, "DATABASE_URL", &self.database_url, &["postgres", "postgresql"]);
+        check_url(&mut errors, "REDIS_URL", &self.redis_url, &["redis", "rediss"]);
+        check_url(&mut errors, "REDIRECT_URL", &self.redirect_url, &["http", "https"]);
+        check_url(&mut errors, "PUBLIC_URL", &self.public_url, &["http", "https"]);
+        if let Some(endpoint) = &self.otel_exporter_otlp_endpoint {
+            check_url(&mut errors, "OTEL_EXPORTER_OTLP_ENDPOINT", endpoint, &["http", "https"]);
+        }
+        for origin in &self.cors_origins {
+            if let Err(e) = OriginPattern::parse(origin) {
+                errors.push(format!("CORS_ORIGINS: {}", e));
+            }
+        }
+        if self.cors && self.cors_origins.is_empty() {
+            errors.push("CORS_ORIGINS is required when CORS is on".to_string());
+        }
+        for method in &self.cors_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                errors.push(format!("CORS_METHODS: {} is not a method", method));
+            }
+        }
+        for header in &self.cors_headers {
+            if HeaderName::from_bytes(header.as_bytes()).is_err() {
+                errors.push(format!("CORS_HEADERS: {} is not a header name", header));
+            }
+        }
+        check_required(&mut errors, "GOOGLE_CLIENT_ID", &self.google_client_id);
+        if self.secret_key.len() < MIN_SECRET_KEY_LEN {
+            errors.push(format!(
+                "SECRET_KEY: should be at least {} bytes, got {}",
+                MIN_SECRET_KEY_LEN,
+                self.secret_key.len()
+            ));
+        }
+        if self.port == 0 {
+            errors.push("PORT: should not be 0".to_string());
+        }
+        if self.pool_size == 0 {
+            errors.push("POOL_SIZE: should be at least 1".to_string());
+        }
+        if self.session_ttl_secs <= 0 {
+            errors.push("SESSION_TTL_SECS: should be positive".to_string());
+        }
+        if self.rate_limit_window_secs == 0 {
+            errors.push("RATE_LIMIT_WINDOW_SECS: should be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.otel_traces_sampler_arg) {
+            errors.push("OTEL_TRACES_SAMPLER_ARG: should be between 0 and 1".to_string());
+        }
+        if let Err(e) = self.email_from.parse::<Mailbox>() {
+            errors.push(format!("EMAIL_FROM: {}", e));
+        }
+        match self.payment_provider.as_str() {
+            "stripe" => {
+                check_required(&mut errors, "PAYMENT_WEBHOOK_SECRET", &self.payment_webhook_secret);
+                check_required(&mut errors, "STRIPE_SECRET_KEY", &self.stripe_secret_key);
+            }
+            "fake" => {}
+            other => errors.push(format!("PAYMENT_PROVIDER: should be stripe or fake, not {}", other)),
+        }
+        match self.email_transport.as_str() {
+            "smtp" => check_url(&mut errors, "SMTP_URL", &self.smtp_url, &["smtp", "smtps"]),
+            "file" => check_required(&mut errors, "EMAIL_DIR", &self.email_dir),
+            "capture" => {}
+            other => errors.push(format!("EMAIL_TRANSPORT: should be smtp, file or capture, not {}", other)),
+        }
+        match self.storage_backend.as_str() {
+            "local" => check_required(&mut errors, "STORAGE_DIR", &self.storage_dir),
+            "s3" => {
+                check_url(&mut errors, "S3_ENDPOINT", &self.s3_endpoint, &["http", "https"]);
+                check_required(&mut errors, "S3_BUCKET", &self.s3_bucket);
+                check_required(&mut errors, "S3_REGION", &self.s3_region);
+                check_required(&mut errors, "S3_ACCESS_KEY", &self.s3_access_key);
+                check_required(&mut errors, "S3_SECRET_KEY", &self.s3_secret_key);
+            }
+            other => errors.push(format!("STORAGE_BACKEND: should be local or s3, not {}", other)),
+        }
+        errors
+    }
+
+    /// None when CORS is off. Only call on a validated config.
+    pub fn cors_settings(&self) -> Option<CorsSettings> {
+        if !self.cors {
+            return None;
+        }
+        Some(CorsSettings {
+            origins: self
+                .cors_origins
+                .iter()
+                .filter_map(|origin| OriginPattern::parse(origin).ok())
+                .collect(),
+            methods: self
+                .cors_methods
+                .iter()
+                .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+                .collect(),
+            headers: self
+                .cors_headers
+                .iter()
+                .filter_map(|header| HeaderName::from_bytes(header.as_bytes()).ok())
+                .collect(),
+            max_age_secs: self.cors_max_age_secs,
+        })
+    }
+
+    pub fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            window_secs: self.rate_limit_window_secs,
+            comments: self.rate_limit_comments,
+            events: self.rate_limit_events,
+            auth: self.rate_limit_auth,
+        }
+    }
+
+    pub fn bind_host(&self) -> &str {
+        match &self.host {
+            Some(host) => host,
+            None if self.stage == "dev" => "127.0.0.1",
+            None => "0.0.0.0",
+        }
+    }
+}
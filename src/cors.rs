@@ -0,0 +1,99 @@
+use actix_cors::Cors;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::Method;
+
+use crate::csrf::CSRF_HEADER;
+use crate::logging::REQUEST_ID_HEADER;
+
+pub const DEFAULT_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+pub const DEFAULT_HEADERS: [&str; 4] = ["content-type", "traceparent", REQUEST_ID_HEADER, CSRF_HEADER];
+pub const DEFAULT_MAX_AGE_SECS: usize = 3600;
+
+/// An allowed `Origin`: either exact, like `https://o2gather.app`, or any
+/// subdomain, like `https://*.o2gather.app`. The wildcard doesn't match the
+/// bare domain itself.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OriginPattern {
+    Exact(String),
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    pub fn parse(pattern: &str) -> Result<OriginPattern, String> {
+        let (scheme, rest) = pattern
+            .split_once("://")
+            .ok_or(format!("{} has no scheme", pattern))?;
+        if scheme != "http" && scheme != "https" {
+            return Err(format!("{} should be http or https", pattern));
+        }
+        if rest.is_empty() || rest.contains('/') {
+            return Err(format!("{} should be an origin, without a path", pattern));
+        }
+        match rest.strip_prefix("*.") {
+            Some(domain) => {
+                if domain.is_empty() || domain.contains('*') || !domain.contains('.') {
+                    return Err(format!("{} is too broad", pattern));
+                }
+                Ok(OriginPattern::Subdomain {
+                    scheme: scheme.to_string(),
+                    suffix: format!(".{}", domain.to_ascii_lowercase()),
+                })
+            }
+            None if rest.contains('*') => Err(format!("{} can only start with *.", pattern)),
+            None => reqwest::Url::parse(pattern)
+                .map(|url| OriginPattern::Exact(url.origin().ascii_serialization()))
+                .map_err(|e| format!("{}: {}", pattern, e)),
+        }
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            OriginPattern::Subdomain { scheme, suffix } => match origin.split_once("://") {
+                Some((origin_scheme, host)) => {
+                    let host = host.to_ascii_lowercase();
+                    let label = host.strip_suffix(suffix.as_str()).unwrap_or("");
+                    origin_scheme == scheme
+                        && !label.is_empty()
+                        && label
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CorsSettings {
+    pub origins: Vec<OriginPattern>,
+    pub methods: Vec<Method>,
+    pub headers: Vec<HeaderName>,
+    /// How long browsers may cache a preflight answer.
+    pub max_age_secs: usize,
+}
+
+impl CorsSettings {
+    pub fn allows(&self, origin: &str) -> bool {
+        self.origins.iter().any(|pattern| pattern.matches(origin))
+    }
+
+    /// Credentialed CORS for the allowed origins only. Preflights for other
+    /// methods or headers are refused.
+    pub fn cors(&self) -> Cors {
+        let settings = self.clone();
+        Cors::default()
+            .supports_credentials()
+            .allowed_origin_fn(move |origin: &HeaderValue, _req_head: &actix_web::dev::RequestHead| {
+                origin.to_str().map(|origin| settings.allows(origin)).unwrap_or(false)
+            })
+            .allowed_methods(self.methods.clone())
+            .allowed_headers(self.headers.clone())
+            .expose_headers(vec![
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(CSRF_HEADER),
+            ])
+            .max_age(self.max_age_secs)
+    }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+use crate::cors::CorsSettings;
+
+#[cfg(test)]
+fn settings() -> CorsSettings {
+    use crate::cors::{OriginPattern, DEFAULT_HEADERS, DEFAULT_MAX_AGE_SECS, DEFAULT_METHODS};
+    use actix_web::http::header::HeaderName;
+    use actix_web::http::Method;
+
+    CorsSettings {
+        origins: vec![
+            OriginPattern::parse("https://o2gather.app").unwrap(),
+            OriginPattern::parse("https://*.preview.o2gather.app").unwrap(),
+        ],
+        methods: DEFAULT_METHODS
+            .iter()
+            .map(|m| Method::from_bytes(m.as_bytes()).unwrap())
+            .collect(),
+        headers: DEFAULT_HEADERS.iter().copied().map(HeaderName::from_static).collect(),
+        max_age_secs: DEFAULT_MAX_AGE_SECS,
+    }
+}
+
+#[test]
+fn test_parse_origin_pattern() {
+    use crate::cors::OriginPattern;
+
+    assert_eq!(
+        OriginPattern::parse("https://O2Gather.app:443").unwrap(),
+        OriginPattern::Exact("https://o2gather.app".to_string())
+    );
+    assert_eq!(
+        OriginPattern::parse("http://localhost:3000").unwrap(),
+        OriginPattern::Exact("http://localhost:3000".to_string())
+    );
+    assert!(OriginPattern::parse("o2gather.app").is_err());
+    assert!(OriginPattern::parse("https://o2gather.app/app").is_err());
+    assert!(OriginPattern::parse("ftp://o2gather.app").is_err());
+    assert!(OriginPattern::parse("https://*").is_err());
+    assert!(OriginPattern::parse("https://*.app").is_err());
+    assert!(OriginPattern::parse("https://app.*.o2gather.app").is_err());
+}
+
+#[test]
+fn test_allows() {
+    let settings = settings();
+    assert!(settings.allows("https://o2gather.app"));
+    assert!(settings.allows("https://pr-12.preview.o2gather.app"));
+    assert!(settings.allows("https://a.b.preview.o2gather.app"));
+
+    assert!(!settings.allows("http://o2gather.app"));
+    assert!(!settings.allows("https://o2gather.app.evil.com"));
+    assert!(!settings.allows("https://evilo2gather.app"));
+    assert!(!settings.allows("https://preview.o2gather.app"));
+    assert!(!settings.allows("https://evilpreview.o2gather.app"));
+    assert!(!settings.allows("http://pr-12.preview.o2gather.app"));
+    assert!(!settings.allows("https://pr-12.preview.o2gather.app.evil.com"));
+    assert!(!settings.allows("null"));
+}
+
+#[actix_web::test]
+async fn test_preflight() {
+    use actix_web::http::header;
+    use actix_web::{test, web, App, HttpResponse};
+
+    let app = test::init_service(
+        App::new()
+            .wrap(settings().cors())
+            .route("/events", web::post().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/events")
+        .insert_header((header::ORIGIN, "https://pr-1.preview.o2gather.app"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://pr-1.preview.o2gather.app"
+    );
+    assert_eq!(res.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+    assert_eq!(res.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+
+    // Unknown origin.
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/events")
+        .insert_header((header::ORIGIN, "https://evil.example"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    // Method outside the allowlist.
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/events")
+        .insert_header((header::ORIGIN, "https://o2gather.app"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "TRACE"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    // Simple request from an allowed origin.
+    let req = test::TestRequest::post()
+        .uri("/events")
+        .insert_header((header::ORIGIN, "https://o2gather.app"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(
+        res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://o2gather.app"
+    );
+}
@@ -0,0 +1,141 @@
+use actix_session::{Session, SessionExt};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use actix_web::http::Method;
+use actix_web::{Error, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::api::types::DefaultError;
+
+pub const CSRF_HEADER: &str = "x-csrf-token";
+const SESSION_KEY: &str = "csrf_token";
+
+/// Methods that must not change state, and so need no token.
+pub fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Routes carrying their own signed token in the URL, which a forged
+/// request couldn't know. Opened from emails, with or without a session.
+pub const EXEMPT_ROUTES: &[&str] = &["/api/v1/unsubscribe"];
+
+pub fn is_exempt(pattern: Option<&str>) -> bool {
+    pattern.is_some_and(|pattern| EXEMPT_ROUTES.contains(&pattern))
+}
+
+/// Browsers can't attach an `Authorization` header cross-site without a
+/// preflight, which CORS refuses, so such requests can't be forged.
+pub fn is_bearer(req: &ServiceRequest) -> bool {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.len() > 7 && value[..7].eq_ignore_ascii_case("bearer "))
+        .unwrap_or(false)
+}
+
+pub fn new_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Compares in constant time, so the token can't be guessed byte by byte.
+pub fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn logged_in(session: &Session) -> bool {
+    session.entries().contains_key("user_id")
+}
+
+/// Middleware for the double-submit token: once a session has a user, it
+/// gets a token, sent back in `X-CSRF-Token` on every response. Requests
+/// other than GET, HEAD and OPTIONS from that session must echo it in the
+/// same header. Needs to run inside `SessionMiddleware`.
+pub struct Csrf;
+
+impl<S, B> Transform<S, ServiceRequest> for Csrf
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let session = req.get_session();
+        if !is_safe(req.method())
+            && !is_bearer(&req)
+            && !is_exempt(req.match_pattern().as_deref())
+            && logged_in(&session)
+        {
+            let expected = session.get::<String>(SESSION_KEY).ok().flatten();
+            let given = req
+                .headers()
+                .get(CSRF_HEADER)
+                .and_then(|value| value.to_str().ok());
+            let valid = match (expected, given) {
+                (Some(expected), Some(given)) => tokens_match(&expected, given),
+                _ => false,
+            };
+            if !valid {
+                let res = HttpResponse::Forbidden().json(DefaultError {
+                    message: "Invalid CSRF token".to_string(),
+                    error_code: "403".to_string(),
+                });
+                return Box::pin(async move { Ok(req.into_response(res).map_into_right_body()) });
+            }
+        }
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let mut res = service.call(req).await?;
+            // Covers logins, and sessions from before tokens existed.
+            if logged_in(&session) {
+                let token = match session.get::<String>(SESSION_KEY).ok().flatten() {
+                    Some(token) => Some(token),
+                    None => {
+                        let token = new_token();
+                        session.insert(SESSION_KEY, &token).ok().map(|_| token)
+                    }
+                };
+                if let Some(value) = token.and_then(|token| HeaderValue::from_str(&token).ok()) {
+                    res.headers_mut().insert(HeaderName::from_static(CSRF_HEADER), value);
+                }
+            }
+            Ok(res.map_into_left_body())
+        })
+    }
+}
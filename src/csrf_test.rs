@@ -0,0 +1,176 @@
+#[cfg(test)]
+use actix_web::cookie::Cookie;
+#[cfg(test)]
+use actix_web::dev::ServiceResponse;
+
+#[cfg(test)]
+fn session_cookie<B>(res: &ServiceResponse<B>) -> Cookie<'static> {
+    let header = res.headers().get("set-cookie").unwrap().to_str().unwrap();
+    Cookie::parse_encoded(header.to_string()).unwrap()
+}
+
+#[test]
+fn test_tokens_match() {
+    use crate::csrf::{new_token, tokens_match};
+
+    let token = new_token();
+    assert_eq!(token.len(), 64);
+    assert_ne!(token, new_token());
+    assert!(tokens_match(&token, &token));
+    assert!(!tokens_match(&token, &token[..63]));
+    assert!(!tokens_match(&token, &new_token()));
+}
+
+#[test]
+fn test_exempt_routes() {
+    use crate::csrf::is_exempt;
+
+    assert!(is_exempt(Some("/api/v1/unsubscribe")));
+    assert!(!is_exempt(Some("/api/v1/events")));
+    assert!(!is_exempt(None));
+}
+
+#[actix_web::test]
+async fn test_token_required_after_login() {
+    use crate::csrf::{Csrf, CSRF_HEADER};
+    use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+    use actix_web::cookie::Key;
+    use actix_web::{test, web, App, HttpResponse};
+
+    let app = test::init_service(
+        App::new()
+            .wrap(Csrf)
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                    .cookie_name("session".to_string())
+                    .build(),
+            )
+            .route(
+                "/login",
+                web::post().to(|session: Session| async move {
+                    session.insert("user_id", uuid::Uuid::new_v4()).unwrap();
+                    HttpResponse::Ok().finish()
+                }),
+            )
+            .route("/events", web::get().to(|| async { HttpResponse::Ok().finish() }))
+            .route("/events", web::patch().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+
+    // Anonymous requests carry no ambient credentials.
+    let res = test::call_service(&app, test::TestRequest::patch().uri("/events").to_request()).await;
+    assert!(res.status().is_success());
+
+    let res = test::call_service(&app, test::TestRequest::post().uri("/login").to_request()).await;
+    assert!(res.status().is_success());
+    let token = res.headers().get(CSRF_HEADER).unwrap().to_str().unwrap().to_string();
+    let cookie = session_cookie(&res);
+
+    // Reads hand out the same token.
+    let req = test::TestRequest::get().uri("/events").cookie(cookie.clone()).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get(CSRF_HEADER).unwrap(), token.as_str());
+
+    let req = test::TestRequest::patch().uri("/events").cookie(cookie.clone()).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, "0".repeat(64)))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, token))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    // Bearer-authenticated clients are exempt.
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie)
+        .insert_header(("authorization", "Bearer abc"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+}
+
+#[actix_web::test]
+async fn test_cross_origin() {
+    use crate::cors::{CorsSettings, OriginPattern};
+    use crate::csrf::{Csrf, CSRF_HEADER};
+    use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+    use actix_web::cookie::Key;
+    use actix_web::http::header::{HeaderName, ORIGIN};
+    use actix_web::http::Method;
+    use actix_web::{test, web, App, HttpResponse};
+
+    let cors = CorsSettings {
+        origins: vec![OriginPattern::parse("https://o2gather.app").unwrap()],
+        methods: vec![Method::GET, Method::POST, Method::PATCH],
+        headers: vec![HeaderName::from_static(CSRF_HEADER)],
+        max_age_secs: 60,
+    };
+    let app = test::init_service(
+        App::new()
+            .wrap(Csrf)
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                    .cookie_name("session".to_string())
+                    .build(),
+            )
+            .wrap(cors.cors())
+            .route(
+                "/login",
+                web::post().to(|session: Session| async move {
+                    session.insert("user_id", uuid::Uuid::new_v4()).unwrap();
+                    HttpResponse::Ok().finish()
+                }),
+            )
+            .route("/events", web::get().to(|| async { HttpResponse::Ok().finish() }))
+            .route("/events", web::patch().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .insert_header((ORIGIN, "https://o2gather.app"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    let token = res.headers().get(CSRF_HEADER).unwrap().to_str().unwrap().to_string();
+    let cookie = session_cookie(&res);
+
+    // A forged request from another site rides on the session cookie but
+    // can't know the token.
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie.clone())
+        .insert_header((ORIGIN, "https://evil.example"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(!res.status().is_success());
+
+    // Even from an allowed origin, the cookie alone is not enough.
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie.clone())
+        .insert_header((ORIGIN, "https://o2gather.app"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let req = test::TestRequest::patch()
+        .uri("/events")
+        .cookie(cookie)
+        .insert_header((ORIGIN, "https://o2gather.app"))
+        .insert_header((CSRF_HEADER, token))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+}
@@ -1,12 +1,29 @@
 use crate::models::{
     Event, EventMember, EventMsg, EventWithMembers, NewEvent, NewEventMember, NewEventMsg, NewUser,
-    UpdateEvent, UpdateUser, User, EventOwner
+    UpdateEvent, UpdateUser, User, EventOwner, NewPayment, Payment, PaymentSummary,
+    PAYMENT_KIND_PAYMENT, PAYMENT_KIND_REFUND, PAYMENT_STATUS_CONFIRMED, PAYMENT_STATUS_PENDING,
+    PAYMENT_STATUS_REJECTED, PLEDGE_PAID, PLEDGE_PARTIAL, PLEDGE_PENDING, PLEDGE_REFUNDED,
+    PLEDGE_UNPAID, NewNotification, Notification, NotificationPreference, NewOutboxEmail,
+    OutboxEmail, EMAIL_STATUS_FAILED, EMAIL_STATUS_PENDING, EMAIL_STATUS_SENT,
+    NOTIFY_COMMENT_CREATED, NewWebhookDelivery, NewWebhookEndpoint, WebhookDelivery,
+    WebhookEndpoint, DELIVERY_STATUS_DELIVERED, DELIVERY_STATUS_FAILED, DELIVERY_STATUS_PENDING,
+    Job, JobCount, JobSchedule, NewJob, JOB_STATUS_DEAD, JOB_STATUS_QUEUED, JOB_STATUS_RUNNING,
+    JOB_STATUS_SUCCEEDED, CommentSearchRow, EventSearchRow, Attachment, NewAttachment,
+    ATTACHMENT_AVATAR, ATTACHMENT_EVENT_COVER, Category, CategoryWithCount, NewCategory,
+    UpdateCategory, TagCount, EventTemplate, NewEventTemplate, AttendanceCounts,
+    AttendanceSummary, CheckinResult, EventGauges,
 };
+use crate::money::Money;
+use crate::payment::{ProviderEvent, ProviderEventKind};
+use crate::geo::{haversine_km, BoundingBox};
+use crate::search::SearchFilter;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::result::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
+#[instrument(skip_all)]
 pub fn get_or_create_user(
     conn: &mut PgConnection,
     sub: String,
@@ -36,6 +53,7 @@ pub fn get_or_create_user(
         })
 }
 
+#[instrument(skip_all)]
 pub fn get_user_by_id(conn: &mut PgConnection, user_id: Uuid) -> User {
     use crate::schema::users::dsl::*;
 
@@ -46,6 +64,7 @@ pub fn get_user_by_id(conn: &mut PgConnection, user_id: Uuid) -> User {
         .unwrap_or_else(|_| panic!("Error getting user by id"))
 }
 
+#[instrument(skip_all)]
 pub fn update_user(conn: &mut PgConnection, user_id: Uuid, user_data: UpdateUser) -> User {
     use crate::schema::users::dsl::*;
 
@@ -56,6 +75,7 @@ pub fn update_user(conn: &mut PgConnection, user_id: Uuid, user_data: UpdateUser
         .expect("Error updating user")
 }
 
+#[instrument(skip_all)]
 pub fn create_event(conn: &mut PgConnection, event_data: NewEvent) -> EventWithMembers {
     use crate::schema::event_members;
     use crate::schema::events;
@@ -70,11 +90,15 @@ pub fn create_event(conn: &mut PgConnection, event_data: NewEvent) -> EventWithM
     let members = event_members::table
         .filter(event_members::event_id.eq(event.id))
         .inner_join(users::table)
+        .inner_join(events::table)
         .select((
             users::name,
             users::email,
             users::phone,
-            event_members::amount,
+            (event_members::amount, events::currency),
+            event_members::payment_status,
+            event_members::checked_in_at,
+            event_members::joined_at,
         ))
         .load::<EventMember>(conn)
         .expect("Error getting event members");
@@ -85,7 +109,7 @@ pub fn create_event(conn: &mut PgConnection, event_data: NewEvent) -> EventWithM
         .first::<EventOwner>(conn)
         .expect("Error getting event owner");
 
-    let amount: i64 = members.iter().map(|m| m.amount).sum();
+    let amount: i64 = members.iter().map(|m| m.amount.minor).sum();
     let members_count = members.len() as i64;
 
     EventWithMembers {
@@ -97,21 +121,29 @@ pub fn create_event(conn: &mut PgConnection, event_data: NewEvent) -> EventWithM
         category: event.category,
         start_time: event.start_time,
         end_time: event.end_time,
-        min_amount: event.min_amount,
-        max_amount: event.max_amount,
-        amount,
+        min_amount: Money::from_db(event.min_amount, &event.currency),
+        max_amount: Money::from_db(event.max_amount, &event.currency),
+        amount: Money::from_db(amount, &event.currency),
         established: event.established,
+        cancelled: event.cancelled,
+        expired: event.expired,
+        location: event.location,
+        tags: Vec::new(),
+        distance_km: None,
+        currency: event.currency,
         members: Some(members),
         members_count: members_count,
+        payment_summary: None,
+        attendance: None,
     }
 }
 
+#[instrument(skip_all)]
 pub fn get_events(conn: &mut PgConnection) -> Vec<EventWithMembers> {
     use crate::schema::events;
-    use crate::schema::event_members;
-    use crate::schema::users;
 
     let event = events::table
+        .filter(events::deleted_at.is_null())
         .order(events::start_time.asc())
         .then_order_by(events::end_time.asc())
         .select(Event::as_select())
@@ -120,47 +152,123 @@ pub fn get_events(conn: &mut PgConnection) -> Vec<EventWithMembers> {
 
     event
         .into_iter()
-        .map(|e| {
-            let members = event_members::table
-            .filter(event_members::event_id.eq(e.id))
-            .inner_join(users::table)
-            .select((
-                users::name,
-                users::email,
-                users::phone,
-                event_members::amount,
-            ))
-            .load::<EventMember>(conn)
-            .expect("Error getting event members");
+        .map(|e| event_with_member_count(conn, e))
+        .collect()
+}
 
-            let owner = users::table
-            .filter(users::id.eq(e.user_id))
-            .select(EventOwner::as_select())
-            .first::<EventOwner>(conn)
-            .expect("Error getting event owner");
-    
-            let amount: i64 = members.iter().map(|m| m.amount).sum();
+/// Listing view of an event: owner and member count, without the members
+/// themselves.
+fn event_with_member_count(conn: &mut PgConnection, e: Event) -> EventWithMembers {
+    use crate::schema::event_members;
+    use crate::schema::events;
+    use crate::schema::users;
 
-            EventWithMembers {
-                id: e.id,
-                user_id: e.user_id,
-                owner: owner,
-                name: e.name,
-                description: e.description,
-                category: e.category,
-                start_time: e.start_time,
-                end_time: e.end_time,
-                min_amount: e.min_amount,
-                max_amount: e.max_amount,
-                amount,
-                established: e.established,
-                members: None,
-                members_count: members.len() as i64,
+    let members = event_members::table
+        .filter(event_members::event_id.eq(e.id))
+        .inner_join(users::table)
+        .inner_join(events::table)
+        .select((
+            users::name,
+            users::email,
+            users::phone,
+            (event_members::amount, events::currency),
+            event_members::payment_status,
+            event_members::checked_in_at,
+            event_members::joined_at,
+        ))
+        .load::<EventMember>(conn)
+        .expect("Error getting event members");
+
+    let owner = users::table
+        .filter(users::id.eq(e.user_id))
+        .select(EventOwner::as_select())
+        .first::<EventOwner>(conn)
+        .expect("Error getting event owner");
+
+    let amount: i64 = members.iter().map(|m| m.amount.minor).sum();
+
+    EventWithMembers {
+        id: e.id,
+        user_id: e.user_id,
+        owner: owner,
+        name: e.name,
+        description: e.description,
+        category: e.category,
+        start_time: e.start_time,
+        end_time: e.end_time,
+        min_amount: Money::from_db(e.min_amount, &e.currency),
+        max_amount: Money::from_db(e.max_amount, &e.currency),
+        amount: Money::from_db(amount, &e.currency),
+        established: e.established,
+        cancelled: e.cancelled,
+        expired: e.expired,
+        location: e.location,
+        tags: get_event_tags(conn, e.id),
+        distance_km: None,
+        currency: e.currency,
+        members: None,
+        members_count: members.len() as i64,
+        payment_summary: None,
+        attendance: None,
+    }
+}
+
+/// Events within `radius_km` of a point, nearest first. The bounding box
+/// narrows the scan through the (latitude, longitude) index; haversine
+/// then drops the corners.
+#[instrument(skip_all)]
+pub fn get_events_near(
+    conn: &mut PgConnection,
+    lat: f64,
+    lng: f64,
+    radius_km: f64,
+) -> Vec<EventWithMembers> {
+    use crate::schema::events;
+
+    let bbox = BoundingBox::around(lat, lng, radius_km);
+    let mut query = events::table
+        .filter(events::deleted_at.is_null())
+        .filter(events::latitude.between(bbox.min_lat, bbox.max_lat))
+        .into_boxed();
+    if bbox.wraps() {
+        query = query.filter(
+            events::longitude
+                .ge(bbox.min_lng)
+                .or(events::longitude.le(bbox.max_lng)),
+        );
+    } else {
+        query = query.filter(events::longitude.between(bbox.min_lng, bbox.max_lng));
+    }
+
+    let candidates = query
+        .select(Event::as_select())
+        .load::<Event>(conn)
+        .expect("Error getting events");
+
+    let mut nearby: Vec<(f64, Event)> = candidates
+        .into_iter()
+        .filter_map(|e| {
+            let distance = haversine_km(lat, lng, e.location.latitude?, e.location.longitude?);
+            if distance <= radius_km {
+                Some((distance, e))
+            } else {
+                None
             }
         })
+        .collect();
+    nearby.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    nearby
+        .into_iter()
+        .map(|(distance, e)| {
+            let mut event = event_with_member_count(conn, e);
+            event.distance_km = Some(distance);
+            event
+        })
         .collect()
 }
 
+#[instrument(skip_all)]
 pub fn get_event_by_id(
     conn: &mut PgConnection,
     event_id: Uuid,
@@ -172,6 +280,7 @@ pub fn get_event_by_id(
 
     let ret = events::table
         .filter(events::id.eq(event_id))
+        .filter(events::deleted_at.is_null())
         .select(Event::as_select())
         .first::<Event>(conn);
     let event: Event;
@@ -198,37 +307,58 @@ pub fn get_event_by_id(
         category: event.category,
         start_time: event.start_time,
         end_time: event.end_time,
-        min_amount: event.min_amount,
-        max_amount: event.max_amount,
-        amount: 0,
+        min_amount: Money::from_db(event.min_amount, &event.currency),
+        max_amount: Money::from_db(event.max_amount, &event.currency),
+        amount: Money::from_db(0, &event.currency),
         established: event.established,
+        cancelled: event.cancelled,
+        expired: event.expired,
+        location: event.location,
+        tags: get_event_tags(conn, event.id),
+        distance_km: None,
+        currency: event.currency,
         members: None,
         members_count: 0,
+        payment_summary: None,
+        attendance: None,
     };
 
     let members = event_members::table
             .filter(event_members::event_id.eq(event.id))
             .inner_join(users::table)
+            .inner_join(events::table)
             .select((
                 users::name,
                 users::email,
                 users::phone,
-                event_members::amount,
+                (event_members::amount, events::currency),
+                event_members::payment_status,
+                event_members::checked_in_at,
+                event_members::joined_at,
             ))
             .load::<EventMember>(conn)
             .expect("Error getting event members");
     
-    let amount: i64 = members.iter().map(|m| m.amount).sum();
+    let amount: i64 = members.iter().map(|m| m.amount.minor).sum();
 
     data.members_count = members.len() as i64;
     if event.user_id == user_id {
+        if event.established {
+            data.payment_summary = Some(get_payment_summary(conn, event.id));
+            let checked_in = members.iter().filter(|m| m.checked_in_at.is_some()).count() as i64;
+            data.attendance = Some(AttendanceSummary {
+                checked_in,
+                not_checked_in: members.len() as i64 - checked_in,
+            });
+        }
         data.members = Some(members);
     }
-    data.amount = amount;
+    data.amount = Money::from_db(amount, &data.currency);
 
     Some(data)
 }
 
+#[instrument(skip_all)]
 pub fn get_event_members(conn: &mut PgConnection, event_id: Uuid) -> Vec<Uuid> {
     use crate::schema::event_members;
 
@@ -239,6 +369,7 @@ pub fn get_event_members(conn: &mut PgConnection, event_id: Uuid) -> Vec<Uuid> {
         .expect("Error getting event members")
 }
 
+#[instrument(skip_all)]
 pub fn update_event(
     conn: &mut PgConnection,
     event_id: Uuid,
@@ -257,16 +388,20 @@ pub fn update_event(
     let members = event_members::table
         .filter(event_members::event_id.eq(event.id))
         .inner_join(users::table)
+        .inner_join(events::table)
         .select((
             users::name,
             users::email,
             users::phone,
-            event_members::amount,
+            (event_members::amount, events::currency),
+            event_members::payment_status,
+            event_members::checked_in_at,
+            event_members::joined_at,
         ))
         .load::<EventMember>(conn)
         .expect("Error getting event members");
 
-    let amount: i64 = members.iter().map(|m| m.amount).sum();
+    let amount: i64 = members.iter().map(|m| m.amount.minor).sum();
     let members_count = members.len() as i64;
     let owner = users::table
         .filter(users::id.eq(event.user_id))
@@ -283,23 +418,36 @@ pub fn update_event(
         category: event.category,
         start_time: event.start_time,
         end_time: event.end_time,
-        min_amount: event.min_amount,
-        max_amount: event.max_amount,
-        amount,
+        min_amount: Money::from_db(event.min_amount, &event.currency),
+        max_amount: Money::from_db(event.max_amount, &event.currency),
+        amount: Money::from_db(amount, &event.currency),
         established: event.established,
+        cancelled: event.cancelled,
+        expired: event.expired,
+        location: event.location,
+        tags: get_event_tags(conn, event.id),
+        distance_km: None,
+        currency: event.currency,
         members: Some(members),
         members_count: members_count,
+        payment_summary: None,
+        attendance: None,
     }
 }
 
+/// Hides the event right away; `purge_deleted_events` removes the rows
+/// for good once they are old enough.
+#[instrument(skip_all)]
 pub fn delete_event(conn: &mut PgConnection, event_id: Uuid) -> bool {
     use crate::schema::events;
 
-    diesel::delete(events::table.find(event_id))
+    diesel::update(events::table.find(event_id))
+        .set(events::deleted_at.eq(chrono::Utc::now().naive_utc()))
         .execute(conn)
         .is_ok()
 }
 
+#[instrument(skip_all)]
 pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<EventWithMembers> {
     use crate::schema::event_members;
     use crate::schema::events;
@@ -307,6 +455,7 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
 
     let event1: Vec<Event> = events::table
         .filter(events::user_id.eq(user_id))
+        .filter(events::deleted_at.is_null())
         .order(events::start_time.asc())
         .then_order_by(events::end_time.asc())
         .select(Event::as_select())
@@ -316,6 +465,7 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
     let event2: Vec<Event> = event_members::table
         .filter(event_members::user_id.eq(user_id))
         .inner_join(events::table)
+        .filter(events::deleted_at.is_null())
         .select(Event::as_select())
         .load::<Event>(conn)
         .expect("Error getting events");
@@ -330,11 +480,15 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
             let members = event_members::table
             .filter(event_members::event_id.eq(e.id))
             .inner_join(users::table)
+            .inner_join(events::table)
             .select((
                 users::name,
                 users::email,
                 users::phone,
-                event_members::amount,
+                (event_members::amount, events::currency),
+                event_members::payment_status,
+                event_members::checked_in_at,
+                event_members::joined_at,
             ))
             .load::<EventMember>(conn)
             .expect("Error getting event members");
@@ -344,7 +498,7 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
             .first::<EventOwner>(conn)
             .expect("Error getting event owner");
     
-            let amount: i64 = members.iter().map(|m| m.amount).sum();
+            let amount: i64 = members.iter().map(|m| m.amount.minor).sum();
             let mut data = EventWithMembers {
                 id: e.id,
                 user_id: e.user_id,
@@ -354,12 +508,20 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
                 category: e.category,
                 start_time: e.start_time,
                 end_time: e.end_time,
-                min_amount: e.min_amount,
-                max_amount: e.max_amount,
-                amount,
+                min_amount: Money::from_db(e.min_amount, &e.currency),
+                max_amount: Money::from_db(e.max_amount, &e.currency),
+                amount: Money::from_db(amount, &e.currency),
                 established: e.established,
+                cancelled: e.cancelled,
+                expired: e.expired,
+                location: e.location,
+                tags: get_event_tags(conn, e.id),
+                distance_km: None,
+                currency: e.currency,
                 members: None,
                 members_count: members.len() as i64,
+                payment_summary: None,
+                attendance: None,
             };
             if e.user_id == user_id {
                 data.members = Some(members);
@@ -370,6 +532,7 @@ pub fn get_events_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> Vec<Even
         .collect()
 }
 
+#[instrument(skip_all)]
 pub fn create_event_member(
     conn: &mut PgConnection,
     event_member_data: NewEventMember,
@@ -377,9 +540,11 @@ pub fn create_event_member(
     use crate::schema::event_members;
     use crate::schema::events;
 
+    // Local like `checked_in_at`; rejoining keeps the first join time.
+    let now = chrono::Local::now().naive_local();
     conn.transaction::<(), _, _>(|conn| {
         diesel::insert_into(event_members::table)
-            .values(&event_member_data)
+            .values((&event_member_data, event_members::joined_at.eq(now)))
             .on_conflict((event_members::event_id, event_members::user_id))
             .do_update()
             .set(event_members::amount.eq(event_member_data.amount))
@@ -405,6 +570,7 @@ pub fn create_event_member(
     })
 }
 
+#[instrument(skip_all)]
 pub fn delete_event_member(conn: &mut PgConnection, event_id: Uuid, user_id: Uuid) -> bool {
     use crate::schema::event_members;
 
@@ -417,6 +583,7 @@ pub fn delete_event_member(conn: &mut PgConnection, event_id: Uuid, user_id: Uui
     .is_ok()
 }
 
+#[instrument(skip_all)]
 pub fn create_event_msg(
     conn: &mut PgConnection,
     event_msg_data: NewEventMsg,
@@ -430,11 +597,17 @@ pub fn create_event_msg(
     event_comments::table
         .filter(event_comments::event_id.eq(event_msg_data.event_id))
         .inner_join(users::table)
-        .select(((users::name, users::avatar), event_comments::content, event_comments::created_at))
+        .select((
+            event_comments::id,
+            (users::name, users::avatar),
+            event_comments::content,
+            event_comments::created_at,
+        ))
         .order(event_comments::created_at.asc())
         .load::<EventMsg>(conn)
 }
 
+#[instrument(skip_all)]
 pub fn get_event_msg_by_event_id(
     conn: &mut PgConnection,
     event_id: Uuid,
@@ -445,17 +618,1644 @@ pub fn get_event_msg_by_event_id(
     event_comments::table
         .filter(event_comments::event_id.eq(event_id))
         .inner_join(users::table)
-        .select(((users::name, users::avatar), event_comments::content, event_comments::created_at))
+        .select((
+            event_comments::id,
+            (users::name, users::avatar),
+            event_comments::content,
+            event_comments::created_at,
+        ))
         .order(event_comments::created_at.asc())
         .load::<EventMsg>(conn)
 }
 
-pub fn get_categories(conn: &mut PgConnection) -> Vec<String> {
+/// All categories in display order, each with the number of events still
+/// open for joining.
+#[instrument(skip_all)]
+pub fn get_categories(conn: &mut PgConnection) -> Result<Vec<CategoryWithCount>, Error> {
+    use crate::schema::categories;
     use crate::schema::events;
-    
-    events::table
-        .select(events::category)
-        .distinct()
-        .load::<String>(conn)
-        .expect("Error getting categories")
-}
\ No newline at end of file
+    use diesel::dsl::count_star;
+
+    let list = categories::table
+        .select(Category::as_select())
+        .order((categories::sort_order.asc(), categories::name.asc()))
+        .load::<Category>(conn)?;
+    let counts: std::collections::HashMap<String, i64> = events::table
+        .filter(events::deleted_at.is_null())
+        .filter(events::cancelled.eq(false))
+        .filter(events::expired.eq(false))
+        .filter(events::end_time.gt(chrono::Local::now().naive_local()))
+        .group_by(events::category)
+        .select((events::category, count_star()))
+        .load::<(String, i64)>(conn)?
+        .into_iter()
+        .collect();
+
+    Ok(list
+        .into_iter()
+        .map(|category| CategoryWithCount {
+            open_events: counts.get(&category.slug).copied().unwrap_or(0),
+            category,
+        })
+        .collect())
+}
+
+/// Both times are local, like event times and `joined_at`.
+#[instrument(skip_all)]
+pub fn get_event_gauges(
+    conn: &mut PgConnection,
+    now: chrono::NaiveDateTime,
+    joined_since: chrono::NaiveDateTime,
+) -> Result<EventGauges, Error> {
+    use crate::schema::event_members;
+    use crate::schema::events;
+
+    let open = events::table
+        .filter(events::deleted_at.is_null())
+        .filter(events::cancelled.eq(false))
+        .filter(events::expired.eq(false))
+        .filter(events::end_time.gt(now));
+    Ok(EventGauges {
+        open: open.count().get_result(conn)?,
+        established: open.filter(events::established.eq(true)).count().get_result(conn)?,
+        joined_last_minute: event_members::table
+            .filter(event_members::joined_at.gt(joined_since))
+            .count()
+            .get_result(conn)?,
+    })
+}
+
+#[instrument(skip_all)]
+pub fn get_category(conn: &mut PgConnection, slug: &str) -> Result<Option<Category>, Error> {
+    use crate::schema::categories;
+
+    categories::table
+        .find(slug)
+        .select(Category::as_select())
+        .first::<Category>(conn)
+        .optional()
+}
+
+#[instrument(skip_all)]
+pub fn create_category(conn: &mut PgConnection, category_data: NewCategory) -> Result<Category, Error> {
+    use crate::schema::categories;
+
+    diesel::insert_into(categories::table)
+        .values(&category_data)
+        .returning(Category::as_select())
+        .get_result::<Category>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn update_category(
+    conn: &mut PgConnection,
+    slug: &str,
+    category_data: UpdateCategory,
+) -> Result<Category, Error> {
+    use crate::schema::categories;
+
+    diesel::update(categories::table.find(slug))
+        .set(&category_data)
+        .returning(Category::as_select())
+        .get_result::<Category>(conn)
+}
+
+/// Whether any event (deleted ones included, they still hold the foreign
+/// key until purged) or subcategory refers to `slug`.
+#[instrument(skip_all)]
+pub fn category_in_use(conn: &mut PgConnection, slug: &str) -> Result<bool, Error> {
+    use crate::schema::categories;
+    use crate::schema::events;
+    use diesel::dsl::exists;
+
+    let has_events = diesel::select(exists(events::table.filter(events::category.eq(slug))))
+        .get_result::<bool>(conn)?;
+    let has_children = diesel::select(exists(categories::table.filter(categories::parent.eq(slug))))
+        .get_result::<bool>(conn)?;
+    Ok(has_events || has_children)
+}
+
+#[instrument(skip_all)]
+pub fn delete_category(conn: &mut PgConnection, slug: &str) -> Result<usize, Error> {
+    use crate::schema::categories;
+
+    diesel::delete(categories::table.find(slug)).execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn create_payment(conn: &mut PgConnection, payment_data: NewPayment) -> Result<Payment, Error> {
+    use crate::schema::payments;
+
+    diesel::insert_into(payments::table)
+        .values(&payment_data)
+        .returning(Payment::as_select())
+        .get_result::<Payment>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_payment_by_id(conn: &mut PgConnection, payment_id: Uuid) -> Option<Payment> {
+    use crate::schema::payments;
+
+    payments::table
+        .filter(payments::id.eq(payment_id))
+        .select(Payment::as_select())
+        .first::<Payment>(conn)
+        .ok()
+}
+
+#[instrument(skip_all)]
+pub fn get_payments_by_event_id(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+) -> Result<Vec<Payment>, Error> {
+    use crate::schema::payments;
+
+    payments::table
+        .filter(payments::event_id.eq(event_id))
+        .order(payments::paid_at.asc())
+        .select(Payment::as_select())
+        .load::<Payment>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_payments_by_event_and_user(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<Payment>, Error> {
+    use crate::schema::payments;
+
+    payments::table
+        .filter(payments::event_id.eq(event_id))
+        .filter(payments::user_id.eq(user_id))
+        .order(payments::paid_at.asc())
+        .select(Payment::as_select())
+        .load::<Payment>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn update_payment_status(
+    conn: &mut PgConnection,
+    payment_id: Uuid,
+    new_status: &str,
+) -> Result<Payment, Error> {
+    use crate::schema::payments;
+
+    let confirmed_at = if new_status == PAYMENT_STATUS_CONFIRMED {
+        Some(chrono::Utc::now().naive_utc())
+    } else {
+        None
+    };
+
+    diesel::update(payments::table.find(payment_id))
+        .set((
+            payments::status.eq(new_status),
+            payments::confirmed_at.eq(confirmed_at),
+        ))
+        .returning(Payment::as_select())
+        .get_result::<Payment>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_payment_summary(conn: &mut PgConnection, event_id: Uuid) -> PaymentSummary {
+    use crate::schema::event_members;
+    use crate::schema::events;
+    use crate::schema::payments;
+
+    let currency: String = events::table
+        .filter(events::id.eq(event_id))
+        .select(events::currency)
+        .first::<String>(conn)
+        .expect("Error getting event");
+
+    let pledges: Vec<(Uuid, i64)> = event_members::table
+        .filter(event_members::event_id.eq(event_id))
+        .select((event_members::user_id, event_members::amount))
+        .load::<(Uuid, i64)>(conn)
+        .expect("Error getting event members");
+
+    let ledger: Vec<(Uuid, i64, String, String)> = payments::table
+        .filter(payments::event_id.eq(event_id))
+        .select((
+            payments::user_id,
+            payments::amount,
+            payments::kind,
+            payments::status,
+        ))
+        .load::<(Uuid, i64, String, String)>(conn)
+        .expect("Error getting payments");
+
+    let mut expected_amount: i64 = 0;
+    let mut paid_amount: i64 = 0;
+    let mut refunded_amount: i64 = 0;
+    let mut pending_amount: i64 = 0;
+    let mut outstanding_amount: i64 = 0;
+    let mut members_outstanding: i64 = 0;
+    for (member_id, expected) in pledges.iter() {
+        let mut paid: i64 = 0;
+        for (payer_id, amount, kind, status) in ledger.iter() {
+            if payer_id != member_id {
+                continue;
+            }
+            if status == PAYMENT_STATUS_PENDING && kind == PAYMENT_KIND_PAYMENT {
+                pending_amount += amount;
+            }
+            if status != PAYMENT_STATUS_CONFIRMED {
+                continue;
+            }
+            if kind == PAYMENT_KIND_REFUND {
+                refunded_amount += amount;
+                paid -= amount;
+            } else {
+                paid_amount += amount;
+                paid += amount;
+            }
+        }
+        let outstanding = (expected - paid).max(0);
+        expected_amount += expected;
+        outstanding_amount += outstanding;
+        if outstanding > 0 {
+            members_outstanding += 1;
+        }
+    }
+
+    PaymentSummary {
+        expected_amount: Money::from_db(expected_amount, &currency),
+        paid_amount: Money::from_db(paid_amount, &currency),
+        refunded_amount: Money::from_db(refunded_amount, &currency),
+        pending_amount: Money::from_db(pending_amount, &currency),
+        outstanding_amount: Money::from_db(outstanding_amount, &currency),
+        members_outstanding,
+    }
+}
+
+struct MemberLedger {
+    expected: i64,
+    paid: i64,
+    pending: bool,
+    refunded: bool,
+}
+
+fn get_member_ledger(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Result<MemberLedger, Error> {
+    use crate::schema::event_members;
+    use crate::schema::payments;
+
+    let expected: i64 = event_members::table
+        .filter(event_members::event_id.eq(event_id))
+        .filter(event_members::user_id.eq(user_id))
+        .select(event_members::amount)
+        .first::<i64>(conn)?;
+
+    let ledger: Vec<(i64, String, String)> = payments::table
+        .filter(payments::event_id.eq(event_id))
+        .filter(payments::user_id.eq(user_id))
+        .select((payments::amount, payments::kind, payments::status))
+        .load::<(i64, String, String)>(conn)?;
+
+    let mut data = MemberLedger {
+        expected,
+        paid: 0,
+        pending: false,
+        refunded: false,
+    };
+    for (amount, kind, status) in ledger {
+        if status == PAYMENT_STATUS_PENDING {
+            data.pending = true;
+        }
+        if status != PAYMENT_STATUS_CONFIRMED {
+            continue;
+        }
+        if kind == PAYMENT_KIND_REFUND {
+            data.refunded = true;
+            data.paid -= amount;
+        } else {
+            data.paid += amount;
+        }
+    }
+
+    Ok(data)
+}
+
+#[instrument(skip_all)]
+pub fn get_member_outstanding(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Result<i64, Error> {
+    let ledger = get_member_ledger(conn, event_id, user_id)?;
+
+    Ok((ledger.expected - ledger.paid).max(0))
+}
+
+#[instrument(skip_all)]
+pub fn refresh_pledge_status(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Result<String, Error> {
+    use crate::schema::event_members;
+
+    let ledger = get_member_ledger(conn, event_id, user_id)?;
+    let status = if ledger.expected > 0 && ledger.paid >= ledger.expected {
+        PLEDGE_PAID
+    } else if ledger.paid > 0 {
+        PLEDGE_PARTIAL
+    } else if ledger.refunded {
+        PLEDGE_REFUNDED
+    } else if ledger.pending {
+        PLEDGE_PENDING
+    } else {
+        PLEDGE_UNPAID
+    };
+
+    diesel::update(
+        event_members::table
+            .filter(event_members::event_id.eq(event_id))
+            .filter(event_members::user_id.eq(user_id)),
+    )
+    .set(event_members::payment_status.eq(status))
+    .execute(conn)?;
+
+    Ok(status.to_string())
+}
+
+#[instrument(skip_all)]
+pub fn set_payment_provider_ref(
+    conn: &mut PgConnection,
+    payment_id: Uuid,
+    provider_ref: &str,
+) -> Result<Payment, Error> {
+    use crate::schema::payments;
+
+    diesel::update(payments::table.find(payment_id))
+        .set(payments::provider_ref.eq(provider_ref))
+        .returning(Payment::as_select())
+        .get_result::<Payment>(conn)
+}
+
+/// Applies a verified provider webhook to the ledger. Redelivered
+/// notifications are recorded in `payment_webhook_events` and skipped, so
+/// calling this twice with the same event is a no-op.
+#[instrument(skip_all)]
+pub fn reconcile_provider_event(
+    conn: &mut PgConnection,
+    provider: &str,
+    event: &ProviderEvent,
+) -> Result<Option<Payment>, Error> {
+    use crate::schema::payment_webhook_events;
+    use crate::schema::payments;
+
+    let new_status = match event.kind {
+        ProviderEventKind::Succeeded => PAYMENT_STATUS_CONFIRMED,
+        ProviderEventKind::Failed => PAYMENT_STATUS_REJECTED,
+        ProviderEventKind::Ignored => return Ok(None),
+    };
+
+    conn.transaction::<Option<Payment>, _, _>(|conn| {
+        // An unknown checkout is NotFound, and nothing is recorded, so the
+        // provider redelivers once the checkout has been saved.
+        let payment = payments::table
+            .filter(payments::provider_ref.eq(&event.checkout_ref))
+            .select(Payment::as_select())
+            .first::<Payment>(conn)?;
+
+        let inserted = diesel::insert_into(payment_webhook_events::table)
+            .values((
+                payment_webhook_events::provider.eq(provider),
+                payment_webhook_events::event_ref.eq(&event.id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+        if payment.status != PAYMENT_STATUS_PENDING {
+            return Ok(Some(payment));
+        }
+
+        let confirmed_at = if new_status == PAYMENT_STATUS_CONFIRMED {
+            Some(chrono::Utc::now().naive_utc())
+        } else {
+            None
+        };
+        let reference = event.payment_ref.clone().unwrap_or(payment.reference);
+        let payment = diesel::update(payments::table.find(payment.id))
+            .set((
+                payments::status.eq(new_status),
+                payments::confirmed_at.eq(confirmed_at),
+                payments::reference.eq(reference),
+            ))
+            .returning(Payment::as_select())
+            .get_result::<Payment>(conn)?;
+        refresh_pledge_status(conn, payment.event_id, payment.user_id)?;
+
+        Ok(Some(payment))
+    })
+}
+
+/// Returns false if the event was already cancelled, so concurrent cancels
+/// only refund once.
+#[instrument(skip_all)]
+pub fn cancel_event(conn: &mut PgConnection, event_id: Uuid) -> Result<bool, Error> {
+    use crate::schema::events;
+
+    diesel::update(events::table.find(event_id))
+        .filter(events::cancelled.eq(false))
+        .set(events::cancelled.eq(true))
+        .execute(conn)
+        .map(|rows| rows == 1)
+}
+
+/// Confirmed payments captured by `provider` that can be refunded through it
+/// and haven't been yet.
+#[instrument(skip_all)]
+pub fn get_refundable_payments(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    provider: &str,
+) -> Vec<Payment> {
+    use crate::schema::payments;
+
+    let refunds = diesel::alias!(payments as refunds);
+    let refunded_ids = refunds
+        .select(refunds.field(payments::refund_of))
+        .filter(refunds.field(payments::refund_of).is_not_null());
+    payments::table
+        .filter(payments::event_id.eq(event_id))
+        .filter(payments::method.eq(provider))
+        .filter(payments::kind.eq(PAYMENT_KIND_PAYMENT))
+        .filter(payments::status.eq(PAYMENT_STATUS_CONFIRMED))
+        .filter(payments::provider_ref.is_not_null())
+        .filter(diesel::dsl::not(payments::id.nullable().eq_any(refunded_ids)))
+        .select(Payment::as_select())
+        .load::<Payment>(conn)
+        .expect("Error getting payments")
+}
+
+#[instrument(skip_all)]
+pub fn get_refund_by_payment_id(conn: &mut PgConnection, payment_id: Uuid) -> Option<Payment> {
+    use crate::schema::payments;
+
+    payments::table
+        .filter(payments::refund_of.eq(payment_id))
+        .select(Payment::as_select())
+        .first::<Payment>(conn)
+        .ok()
+}
+
+/// Drops recipients who turned `kind` off, either for this event or as
+/// their default. Anyone without a preference is notified.
+#[instrument(skip_all)]
+pub fn filter_notification_recipients(
+    conn: &mut PgConnection,
+    recipients: Vec<Uuid>,
+    event_id: Uuid,
+    kind: &str,
+) -> Result<Vec<Uuid>, Error> {
+    use crate::schema::notification_preferences;
+
+    let prefs: Vec<NotificationPreference> = notification_preferences::table
+        .filter(notification_preferences::user_id.eq_any(&recipients))
+        .filter(notification_preferences::kind.eq(kind))
+        .filter(notification_preferences::event_id.eq_any(vec![event_id, Uuid::nil()]))
+        .select(NotificationPreference::as_select())
+        .load::<NotificationPreference>(conn)?;
+
+    Ok(recipients
+        .into_iter()
+        .filter(|user_id| {
+            let event_pref = prefs
+                .iter()
+                .find(|p| p.user_id == *user_id && p.event_id == event_id);
+            let default_pref = prefs
+                .iter()
+                .find(|p| p.user_id == *user_id && p.event_id.is_nil());
+            match (event_pref, default_pref) {
+                (Some(p), _) => p.enabled,
+                (None, Some(p)) => p.enabled,
+                (None, None) => true,
+            }
+        })
+        .collect())
+}
+
+#[instrument(skip_all)]
+pub fn create_notifications(
+    conn: &mut PgConnection,
+    recipients: &[Uuid],
+    event_id: Uuid,
+    kind: &str,
+    message: &str,
+) -> Result<usize, Error> {
+    use crate::schema::notifications;
+
+    let values: Vec<NewNotification> = recipients
+        .iter()
+        .map(|user_id| NewNotification {
+            user_id: *user_id,
+            event_id,
+            kind: kind.to_string(),
+            message: message.to_string(),
+        })
+        .collect();
+    if values.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_into(notifications::table)
+        .values(&values)
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_notifications(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    unread_only: bool,
+    limit: i64,
+) -> Result<Vec<Notification>, Error> {
+    use crate::schema::notifications;
+
+    let mut query = notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .into_boxed();
+    if unread_only {
+        query = query.filter(notifications::read_at.is_null());
+    }
+
+    query
+        .order(notifications::created_at.desc())
+        .limit(limit)
+        .select(Notification::as_select())
+        .load::<Notification>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn count_unread_notifications(conn: &mut PgConnection, user_id: Uuid) -> Result<i64, Error> {
+    use crate::schema::notifications;
+
+    notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .filter(notifications::read_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_notification_read(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    notification_id: Uuid,
+) -> Result<usize, Error> {
+    use crate::schema::notifications;
+
+    diesel::update(
+        notifications::table
+            .filter(notifications::id.eq(notification_id))
+            .filter(notifications::user_id.eq(user_id)),
+    )
+    .set(notifications::read_at.eq(chrono::Utc::now().naive_utc()))
+    .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_all_notifications_read(conn: &mut PgConnection, user_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::notifications;
+
+    diesel::update(
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::read_at.is_null()),
+    )
+    .set(notifications::read_at.eq(chrono::Utc::now().naive_utc()))
+    .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_notification_preferences(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<NotificationPreference>, Error> {
+    use crate::schema::notification_preferences;
+
+    notification_preferences::table
+        .filter(notification_preferences::user_id.eq(user_id))
+        .select(NotificationPreference::as_select())
+        .load::<NotificationPreference>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn set_notification_preference(
+    conn: &mut PgConnection,
+    preference: NotificationPreference,
+) -> Result<NotificationPreference, Error> {
+    use crate::schema::notification_preferences;
+
+    diesel::insert_into(notification_preferences::table)
+        .values(&preference)
+        .on_conflict((
+            notification_preferences::user_id,
+            notification_preferences::event_id,
+            notification_preferences::kind,
+        ))
+        .do_update()
+        .set(notification_preferences::enabled.eq(preference.enabled))
+        .returning(NotificationPreference::as_select())
+        .get_result::<NotificationPreference>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn enqueue_emails(conn: &mut PgConnection, emails: &[NewOutboxEmail]) -> Result<usize, Error> {
+    use crate::schema::email_outbox;
+
+    if emails.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_into(email_outbox::table)
+        .values(emails)
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_due_emails(conn: &mut PgConnection, limit: i64) -> Result<Vec<OutboxEmail>, Error> {
+    use crate::schema::email_outbox;
+
+    email_outbox::table
+        .filter(email_outbox::status.eq(EMAIL_STATUS_PENDING))
+        .filter(email_outbox::run_at.le(chrono::Utc::now().naive_utc()))
+        .order(email_outbox::run_at.asc())
+        .limit(limit)
+        .select(OutboxEmail::as_select())
+        .load::<OutboxEmail>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_email_sent(conn: &mut PgConnection, email_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::email_outbox;
+
+    diesel::update(email_outbox::table.find(email_id))
+        .set((
+            email_outbox::status.eq(EMAIL_STATUS_SENT),
+            email_outbox::sent_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+}
+
+/// Records a failed attempt. With `retry_at` the email stays pending until
+/// then; without it the email is given up on.
+#[instrument(skip_all)]
+pub fn mark_email_failed(
+    conn: &mut PgConnection,
+    email_id: Uuid,
+    error: &str,
+    retry_at: Option<chrono::NaiveDateTime>,
+) -> Result<usize, Error> {
+    use crate::schema::email_outbox;
+
+    let status = if retry_at.is_some() {
+        EMAIL_STATUS_PENDING
+    } else {
+        EMAIL_STATUS_FAILED
+    };
+
+    diesel::update(email_outbox::table.find(email_id))
+        .set((
+            email_outbox::status.eq(status),
+            email_outbox::attempts.eq(email_outbox::attempts + 1),
+            email_outbox::last_error.eq(error),
+            email_outbox::run_at.eq(retry_at.unwrap_or(chrono::Utc::now().naive_utc())),
+        ))
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_undigested_comment_notifications(
+    conn: &mut PgConnection,
+) -> Result<Vec<Notification>, Error> {
+    use crate::schema::notifications;
+
+    notifications::table
+        .filter(notifications::kind.eq(NOTIFY_COMMENT_CREATED))
+        .filter(notifications::emailed.eq(false))
+        .order(notifications::created_at.asc())
+        .select(Notification::as_select())
+        .load::<Notification>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_notifications_emailed(
+    conn: &mut PgConnection,
+    notification_ids: &[Uuid],
+) -> Result<usize, Error> {
+    use crate::schema::notifications;
+
+    diesel::update(notifications::table.filter(notifications::id.eq_any(notification_ids)))
+        .set(notifications::emailed.eq(true))
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_events_needing_reminder(
+    conn: &mut PgConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<Vec<Event>, Error> {
+    use crate::schema::events;
+
+    events::table
+        .filter(events::start_time.gt(from))
+        .filter(events::start_time.le(to))
+        .filter(events::cancelled.eq(false))
+        .filter(events::deleted_at.is_null())
+        .filter(events::reminder_sent_at.is_null())
+        .select(Event::as_select())
+        .load::<Event>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_reminder_sent(conn: &mut PgConnection, event_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::events;
+
+    diesel::update(events::table.find(event_id))
+        .set(events::reminder_sent_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn is_admin(conn: &mut PgConnection, user_id: Uuid) -> bool {
+    use crate::schema::users;
+
+    users::table
+        .filter(users::id.eq(user_id))
+        .select(users::is_admin)
+        .first::<bool>(conn)
+        .unwrap_or(false)
+}
+
+#[instrument(skip_all)]
+pub fn create_webhook_endpoint(
+    conn: &mut PgConnection,
+    endpoint_data: NewWebhookEndpoint,
+) -> Result<WebhookEndpoint, Error> {
+    use crate::schema::webhook_endpoints;
+
+    diesel::insert_into(webhook_endpoints::table)
+        .values(&endpoint_data)
+        .returning(WebhookEndpoint::as_select())
+        .get_result::<WebhookEndpoint>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_webhook_endpoint(conn: &mut PgConnection, endpoint_id: Uuid) -> Option<WebhookEndpoint> {
+    use crate::schema::webhook_endpoints;
+
+    webhook_endpoints::table
+        .filter(webhook_endpoints::id.eq(endpoint_id))
+        .select(WebhookEndpoint::as_select())
+        .first::<WebhookEndpoint>(conn)
+        .ok()
+}
+
+/// Endpoints registered by `user_id`, or every endpoint for admins.
+#[instrument(skip_all)]
+pub fn get_webhook_endpoints(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    all: bool,
+) -> Result<Vec<WebhookEndpoint>, Error> {
+    use crate::schema::webhook_endpoints;
+
+    let mut query = webhook_endpoints::table.into_boxed();
+    if !all {
+        query = query.filter(webhook_endpoints::user_id.eq(user_id));
+    }
+
+    query
+        .order(webhook_endpoints::created_at.desc())
+        .select(WebhookEndpoint::as_select())
+        .load::<WebhookEndpoint>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn delete_webhook_endpoint(conn: &mut PgConnection, endpoint_id: Uuid) -> bool {
+    use crate::schema::webhook_endpoints;
+
+    diesel::delete(webhook_endpoints::table.find(endpoint_id))
+        .execute(conn)
+        .is_ok()
+}
+
+/// Active endpoints that subscribed to `event_type`, either for this event
+/// or globally.
+#[instrument(skip_all)]
+pub fn get_subscribed_webhook_endpoints(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    event_type: &str,
+) -> Result<Vec<WebhookEndpoint>, Error> {
+    use crate::schema::webhook_endpoints;
+
+    webhook_endpoints::table
+        .filter(webhook_endpoints::active.eq(true))
+        .filter(
+            webhook_endpoints::event_id
+                .eq(event_id)
+                .or(webhook_endpoints::event_id.is_null()),
+        )
+        .filter(webhook_endpoints::event_types.contains(vec![event_type.to_string()]))
+        .select(WebhookEndpoint::as_select())
+        .load::<WebhookEndpoint>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn enqueue_webhook_deliveries(
+    conn: &mut PgConnection,
+    deliveries: &[NewWebhookDelivery],
+) -> Result<usize, Error> {
+    use crate::schema::webhook_deliveries;
+
+    if deliveries.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_into(webhook_deliveries::table)
+        .values(deliveries)
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_due_webhook_deliveries(
+    conn: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<(WebhookDelivery, WebhookEndpoint)>, Error> {
+    use crate::schema::webhook_deliveries;
+    use crate::schema::webhook_endpoints;
+
+    webhook_deliveries::table
+        .inner_join(webhook_endpoints::table)
+        .filter(webhook_deliveries::status.eq(DELIVERY_STATUS_PENDING))
+        .filter(webhook_deliveries::run_at.le(chrono::Utc::now().naive_utc()))
+        .order(webhook_deliveries::run_at.asc())
+        .limit(limit)
+        .select((WebhookDelivery::as_select(), WebhookEndpoint::as_select()))
+        .load::<(WebhookDelivery, WebhookEndpoint)>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn mark_webhook_delivered(
+    conn: &mut PgConnection,
+    delivery_id: Uuid,
+    response_status: i32,
+) -> Result<usize, Error> {
+    use crate::schema::webhook_deliveries;
+
+    diesel::update(webhook_deliveries::table.find(delivery_id))
+        .set((
+            webhook_deliveries::status.eq(DELIVERY_STATUS_DELIVERED),
+            webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+            webhook_deliveries::response_status.eq(Some(response_status)),
+            webhook_deliveries::last_error.eq(None::<String>),
+            webhook_deliveries::delivered_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+}
+
+/// Records a failed attempt. With `retry_at` the delivery stays pending
+/// until then; without it the delivery is given up on.
+#[instrument(skip_all)]
+pub fn mark_webhook_failed(
+    conn: &mut PgConnection,
+    delivery_id: Uuid,
+    response_status: Option<i32>,
+    error: &str,
+    retry_at: Option<chrono::NaiveDateTime>,
+) -> Result<usize, Error> {
+    use crate::schema::webhook_deliveries;
+
+    let status = if retry_at.is_some() {
+        DELIVERY_STATUS_PENDING
+    } else {
+        DELIVERY_STATUS_FAILED
+    };
+
+    diesel::update(webhook_deliveries::table.find(delivery_id))
+        .set((
+            webhook_deliveries::status.eq(status),
+            webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+            webhook_deliveries::response_status.eq(response_status),
+            webhook_deliveries::last_error.eq(error),
+            webhook_deliveries::run_at.eq(retry_at.unwrap_or(chrono::Utc::now().naive_utc())),
+        ))
+        .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_webhook_deliveries(
+    conn: &mut PgConnection,
+    endpoint_id: Uuid,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>, Error> {
+    use crate::schema::webhook_deliveries;
+
+    webhook_deliveries::table
+        .filter(webhook_deliveries::endpoint_id.eq(endpoint_id))
+        .order(webhook_deliveries::created_at.desc())
+        .limit(limit)
+        .select(WebhookDelivery::as_select())
+        .load::<WebhookDelivery>(conn)
+}
+
+/// Marks open events that ended without being established as expired.
+#[instrument(skip_all)]
+pub fn expire_past_events(conn: &mut PgConnection, now: chrono::NaiveDateTime) -> Result<usize, Error> {
+    use crate::schema::events;
+
+    diesel::update(
+        events::table
+            .filter(events::end_time.lt(now))
+            .filter(events::established.eq(false))
+            .filter(events::cancelled.eq(false))
+            .filter(events::expired.eq(false))
+            .filter(events::deleted_at.is_null()),
+    )
+    .set(events::expired.eq(true))
+    .execute(conn)
+}
+
+/// Permanently removes events soft-deleted before `deleted_before`.
+/// Payments, comments, notifications, emails and webhooks cascade.
+#[instrument(skip_all)]
+pub fn purge_deleted_events(
+    conn: &mut PgConnection,
+    deleted_before: chrono::NaiveDateTime,
+) -> Result<usize, Error> {
+    use crate::schema::event_members;
+    use crate::schema::events;
+    use crate::schema::notification_preferences;
+
+    conn.transaction::<usize, Error, _>(|conn| {
+        let ids: Vec<Uuid> = events::table
+            .filter(events::deleted_at.lt(deleted_before))
+            .select(events::id)
+            .load::<Uuid>(conn)?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        diesel::delete(event_members::table.filter(event_members::event_id.eq_any(&ids)))
+            .execute(conn)?;
+        diesel::delete(
+            notification_preferences::table
+                .filter(notification_preferences::event_id.eq_any(&ids)),
+        )
+        .execute(conn)?;
+        diesel::delete(events::table.filter(events::id.eq_any(&ids))).execute(conn)
+    })
+}
+
+#[instrument(skip_all)]
+pub fn enqueue_job(conn: &mut PgConnection, job_data: NewJob) -> Result<Job, Error> {
+    use crate::schema::jobs;
+
+    diesel::insert_into(jobs::table)
+        .values(&job_data)
+        .returning(Job::as_select())
+        .get_result::<Job>(conn)
+}
+
+/// Whether a job of `kind` is waiting or running, so schedules don't pile
+/// up duplicates while a replica is busy or down.
+#[instrument(skip_all)]
+pub fn has_pending_job(conn: &mut PgConnection, kind: &str) -> Result<bool, Error> {
+    use crate::schema::jobs;
+
+    diesel::select(diesel::dsl::exists(
+        jobs::table
+            .filter(jobs::kind.eq(kind))
+            .filter(jobs::status.eq_any(vec![JOB_STATUS_QUEUED, JOB_STATUS_RUNNING])),
+    ))
+    .get_result::<bool>(conn)
+}
+
+/// Claims up to `limit` due jobs for `worker`. Rows locked by another
+/// replica's claim are skipped rather than waited on, and running jobs
+/// whose lock is older than `stale_before` are taken over.
+#[instrument(skip_all)]
+pub fn claim_jobs(
+    conn: &mut PgConnection,
+    worker: &str,
+    limit: i64,
+    stale_before: chrono::NaiveDateTime,
+) -> Result<Vec<Job>, Error> {
+    use crate::schema::jobs;
+
+    let now = chrono::Utc::now().naive_utc();
+    conn.transaction::<Vec<Job>, Error, _>(|conn| {
+        let ids: Vec<Uuid> = jobs::table
+            .filter(
+                jobs::status
+                    .eq(JOB_STATUS_QUEUED)
+                    .and(jobs::run_at.le(now))
+                    .or(jobs::status
+                        .eq(JOB_STATUS_RUNNING)
+                        .and(jobs::locked_at.assume_not_null().lt(stale_before))),
+            )
+            .order(jobs::run_at.asc())
+            .limit(limit)
+            .select(jobs::id)
+            .for_update()
+            .skip_locked()
+            .load::<Uuid>(conn)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        diesel::update(jobs::table.filter(jobs::id.eq_any(&ids)))
+            .set((
+                jobs::status.eq(JOB_STATUS_RUNNING),
+                jobs::locked_by.eq(worker),
+                jobs::locked_at.eq(now),
+                jobs::attempts.eq(jobs::attempts + 1),
+            ))
+            .returning(Job::as_select())
+            .get_results::<Job>(conn)
+    })
+}
+
+#[instrument(skip_all)]
+pub fn mark_job_succeeded(conn: &mut PgConnection, job_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::jobs;
+
+    diesel::update(jobs::table.find(job_id))
+        .set((
+            jobs::status.eq(JOB_STATUS_SUCCEEDED),
+            jobs::locked_by.eq(None::<String>),
+            jobs::locked_at.eq(None::<chrono::NaiveDateTime>),
+            jobs::last_error.eq(None::<String>),
+            jobs::finished_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+}
+
+/// Records a failed run. With `retry_at` the job is queued again for then;
+/// without it the job is dead-lettered for an admin to look at.
+#[instrument(skip_all)]
+pub fn mark_job_failed(
+    conn: &mut PgConnection,
+    job_id: Uuid,
+    error: &str,
+    retry_at: Option<chrono::NaiveDateTime>,
+) -> Result<usize, Error> {
+    use crate::schema::jobs;
+
+    let now = chrono::Utc::now().naive_utc();
+    let (status, run_at, finished_at) = match retry_at {
+        Some(t) => (JOB_STATUS_QUEUED, t, None),
+        None => (JOB_STATUS_DEAD, now, Some(now)),
+    };
+
+    diesel::update(jobs::table.find(job_id))
+        .set((
+            jobs::status.eq(status),
+            jobs::run_at.eq(run_at),
+            jobs::locked_by.eq(None::<String>),
+            jobs::locked_at.eq(None::<chrono::NaiveDateTime>),
+            jobs::last_error.eq(error),
+            jobs::finished_at.eq(finished_at),
+        ))
+        .execute(conn)
+}
+
+/// Puts a dead job back in the queue with a fresh set of attempts.
+#[instrument(skip_all)]
+pub fn retry_dead_job(conn: &mut PgConnection, job_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::jobs;
+
+    diesel::update(
+        jobs::table
+            .filter(jobs::id.eq(job_id))
+            .filter(jobs::status.eq(JOB_STATUS_DEAD)),
+    )
+    .set((
+        jobs::status.eq(JOB_STATUS_QUEUED),
+        jobs::attempts.eq(0),
+        jobs::run_at.eq(chrono::Utc::now().naive_utc()),
+        jobs::finished_at.eq(None::<chrono::NaiveDateTime>),
+    ))
+    .execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_jobs(
+    conn: &mut PgConnection,
+    status: Option<String>,
+    kind: Option<String>,
+    limit: i64,
+) -> Result<Vec<Job>, Error> {
+    use crate::schema::jobs;
+
+    let mut query = jobs::table.into_boxed();
+    if let Some(status) = status {
+        query = query.filter(jobs::status.eq(status));
+    }
+    if let Some(kind) = kind {
+        query = query.filter(jobs::kind.eq(kind));
+    }
+
+    query
+        .order(jobs::created_at.desc())
+        .limit(limit)
+        .select(Job::as_select())
+        .load::<Job>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn count_jobs_by_status(conn: &mut PgConnection) -> Result<Vec<JobCount>, Error> {
+    use crate::schema::jobs;
+
+    jobs::table
+        .group_by(jobs::status)
+        .select((jobs::status, diesel::dsl::count_star()))
+        .order(jobs::status.asc())
+        .load::<JobCount>(conn)
+}
+
+/// Drops finished jobs older than `finished_before`. Dead jobs are kept
+/// until someone retries or inspects them.
+#[instrument(skip_all)]
+pub fn purge_finished_jobs(
+    conn: &mut PgConnection,
+    finished_before: chrono::NaiveDateTime,
+) -> Result<usize, Error> {
+    use crate::schema::jobs;
+
+    diesel::delete(
+        jobs::table
+            .filter(jobs::status.eq(JOB_STATUS_SUCCEEDED))
+            .filter(jobs::finished_at.lt(finished_before)),
+    )
+    .execute(conn)
+}
+
+/// Registers a recurring job, updating its cron expression if it changed.
+#[instrument(skip_all)]
+pub fn upsert_job_schedule(conn: &mut PgConnection, schedule: JobSchedule) -> Result<usize, Error> {
+    use crate::schema::job_schedules;
+    use diesel::upsert::excluded;
+
+    diesel::insert_into(job_schedules::table)
+        .values(&schedule)
+        .on_conflict(job_schedules::name)
+        .do_update()
+        .set(job_schedules::cron.eq(excluded(job_schedules::cron)))
+        .execute(conn)
+}
+
+/// Locks the schedules that are due. Must run inside a transaction; rows
+/// another replica is already handling are skipped.
+#[instrument(skip_all)]
+pub fn lock_due_job_schedules(
+    conn: &mut PgConnection,
+    now: chrono::NaiveDateTime,
+) -> Result<Vec<JobSchedule>, Error> {
+    use crate::schema::job_schedules;
+
+    job_schedules::table
+        .filter(job_schedules::next_run_at.le(now))
+        .select(JobSchedule::as_select())
+        .for_update()
+        .skip_locked()
+        .load::<JobSchedule>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn advance_job_schedule(
+    conn: &mut PgConnection,
+    name: &str,
+    ran_at: chrono::NaiveDateTime,
+    next_run_at: chrono::NaiveDateTime,
+) -> Result<usize, Error> {
+    use crate::schema::job_schedules;
+
+    diesel::update(job_schedules::table.find(name))
+        .set((
+            job_schedules::last_run_at.eq(ran_at),
+            job_schedules::next_run_at.eq(next_run_at),
+        ))
+        .execute(conn)
+}
+
+/// Full-text search over event name, category and description, best
+/// match first. With `substring` set, ILIKE on `pattern` is used as well
+/// for scripts the tokenizer can't split into words (e.g. Chinese); see
+/// `search::SearchFilter::new`.
+#[instrument(skip_all)]
+pub fn search_events(conn: &mut PgConnection, filter: &SearchFilter) -> Result<Vec<EventSearchRow>, Error> {
+    use diesel::sql_types::{BigInt, Bool, Nullable, Text, Timestamp};
+
+    diesel::sql_query(
+        "SELECT e.id, e.name, e.description, e.category, e.start_time, e.end_time, \
+                e.established, e.cancelled, e.expired, \
+                ts_rank(e.search_vector, plainto_tsquery('simple', $1)) AS rank \
+         FROM events e \
+         WHERE e.deleted_at IS NULL \
+           AND (e.search_vector @@ plainto_tsquery('simple', $1) \
+                OR ($3 AND (e.name ILIKE $2 OR e.category ILIKE $2 OR e.description ILIKE $2))) \
+           AND ($4::TEXT IS NULL OR e.category = $4) \
+           AND ($5::TIMESTAMP IS NULL OR e.start_time >= $5) \
+           AND ($6::TIMESTAMP IS NULL OR e.start_time < $6) \
+         ORDER BY rank DESC, e.start_time ASC \
+         LIMIT $7",
+    )
+    .bind::<Text, _>(filter.query.clone())
+    .bind::<Text, _>(filter.pattern.clone())
+    .bind::<Bool, _>(filter.substring)
+    .bind::<Nullable<Text>, _>(filter.category.clone())
+    .bind::<Nullable<Timestamp>, _>(filter.from)
+    .bind::<Nullable<Timestamp>, _>(filter.to)
+    .bind::<BigInt, _>(filter.limit)
+    .load::<EventSearchRow>(conn)
+}
+
+/// Comment counterpart of `search_events`. Comments are public like
+/// `get_event_msg_by_event_id`, so only deleted events are left out; the
+/// filters apply to the event the comment belongs to.
+#[instrument(skip_all)]
+pub fn search_comments(conn: &mut PgConnection, filter: &SearchFilter) -> Result<Vec<CommentSearchRow>, Error> {
+    use diesel::sql_types::{BigInt, Bool, Nullable, Text, Timestamp};
+
+    diesel::sql_query(
+        "SELECT c.id, c.event_id, e.name AS event_name, u.name AS author, c.content, c.created_at, \
+                ts_rank(c.search_vector, plainto_tsquery('simple', $1)) AS rank \
+         FROM event_comments c \
+         JOIN events e ON e.id = c.event_id \
+         JOIN users u ON u.id = c.user_id \
+         WHERE e.deleted_at IS NULL \
+           AND (c.search_vector @@ plainto_tsquery('simple', $1) \
+                OR ($3 AND c.content ILIKE $2)) \
+           AND ($4::TEXT IS NULL OR e.category = $4) \
+           AND ($5::TIMESTAMP IS NULL OR e.start_time >= $5) \
+           AND ($6::TIMESTAMP IS NULL OR e.start_time < $6) \
+         ORDER BY rank DESC, c.created_at DESC \
+         LIMIT $7",
+    )
+    .bind::<Text, _>(filter.query.clone())
+    .bind::<Text, _>(filter.pattern.clone())
+    .bind::<Bool, _>(filter.substring)
+    .bind::<Nullable<Text>, _>(filter.category.clone())
+    .bind::<Nullable<Timestamp>, _>(filter.from)
+    .bind::<Nullable<Timestamp>, _>(filter.to)
+    .bind::<BigInt, _>(filter.limit)
+    .load::<CommentSearchRow>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_comment_author(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    comment_id: Uuid,
+) -> Result<Option<Uuid>, Error> {
+    use crate::schema::event_comments;
+
+    event_comments::table
+        .filter(event_comments::id.eq(comment_id))
+        .filter(event_comments::event_id.eq(event_id))
+        .select(event_comments::user_id)
+        .first::<Uuid>(conn)
+        .optional()
+}
+
+/// Stores a new attachment. Covers and avatars are single-valued, so the
+/// previous one is removed in the same transaction and returned for the
+/// caller to drop from storage.
+#[instrument(skip_all)]
+pub fn create_attachment(
+    conn: &mut PgConnection,
+    attachment_data: NewAttachment,
+) -> Result<(Attachment, Vec<Attachment>), Error> {
+    use crate::schema::attachments;
+
+    conn.transaction::<_, Error, _>(|conn| {
+        let replaced = match attachment_data.kind.as_str() {
+            ATTACHMENT_EVENT_COVER => diesel::delete(
+                attachments::table
+                    .filter(attachments::kind.eq(ATTACHMENT_EVENT_COVER))
+                    .filter(attachments::event_id.eq(attachment_data.event_id)),
+            )
+            .returning(Attachment::as_select())
+            .get_results::<Attachment>(conn)?,
+            ATTACHMENT_AVATAR => diesel::delete(
+                attachments::table
+                    .filter(attachments::kind.eq(ATTACHMENT_AVATAR))
+                    .filter(attachments::user_id.eq(attachment_data.user_id)),
+            )
+            .returning(Attachment::as_select())
+            .get_results::<Attachment>(conn)?,
+            _ => Vec::new(),
+        };
+        let attachment = diesel::insert_into(attachments::table)
+            .values(&attachment_data)
+            .returning(Attachment::as_select())
+            .get_result::<Attachment>(conn)?;
+        Ok((attachment, replaced))
+    })
+}
+
+#[instrument(skip_all)]
+pub fn get_attachment(
+    conn: &mut PgConnection,
+    attachment_id: Uuid,
+) -> Result<Option<Attachment>, Error> {
+    use crate::schema::attachments;
+
+    attachments::table
+        .find(attachment_id)
+        .select(Attachment::as_select())
+        .first::<Attachment>(conn)
+        .optional()
+}
+
+#[instrument(skip_all)]
+pub fn get_event_attachments(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+) -> Result<Vec<Attachment>, Error> {
+    use crate::schema::attachments;
+
+    attachments::table
+        .filter(attachments::event_id.eq(event_id))
+        .select(Attachment::as_select())
+        .order(attachments::created_at.asc())
+        .load::<Attachment>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_avatar(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<Attachment>, Error> {
+    use crate::schema::attachments;
+
+    attachments::table
+        .filter(attachments::kind.eq(ATTACHMENT_AVATAR))
+        .filter(attachments::user_id.eq(user_id))
+        .select(Attachment::as_select())
+        .order(attachments::created_at.desc())
+        .first::<Attachment>(conn)
+        .optional()
+}
+
+#[instrument(skip_all)]
+pub fn delete_attachment(conn: &mut PgConnection, attachment_id: Uuid) -> Result<usize, Error> {
+    use crate::schema::attachments;
+
+    diesel::delete(attachments::table.find(attachment_id)).execute(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_event_tags(conn: &mut PgConnection, event_id: Uuid) -> Vec<String> {
+    use crate::schema::event_tags;
+
+    event_tags::table
+        .filter(event_tags::event_id.eq(event_id))
+        .select(event_tags::tag)
+        .order(event_tags::tag.asc())
+        .load::<String>(conn)
+        .expect("Error getting event tags")
+}
+
+/// Replaces the tags of an event with `tags`, which should already be
+/// normalized (see `tags::normalize_tags`).
+#[instrument(skip_all)]
+pub fn set_event_tags(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    tags: &[String],
+) -> Result<Vec<String>, Error> {
+    use crate::schema::event_tags;
+
+    conn.transaction::<_, Error, _>(|conn| {
+        diesel::delete(event_tags::table.filter(event_tags::event_id.eq(event_id))).execute(conn)?;
+        let rows: Vec<_> = tags
+            .iter()
+            .map(|tag| (event_tags::event_id.eq(event_id), event_tags::tag.eq(tag)))
+            .collect();
+        if !rows.is_empty() {
+            diesel::insert_into(event_tags::table).values(&rows).execute(conn)?;
+        }
+        Ok(get_event_tags(conn, event_id))
+    })
+}
+
+/// Inserts imported events with their tags, all or none.
+#[instrument(skip_all)]
+pub fn create_events(
+    conn: &mut PgConnection,
+    new_events: Vec<(NewEvent, Vec<String>)>,
+) -> Result<Vec<Uuid>, Error> {
+    use crate::schema::events;
+
+    conn.transaction::<_, Error, _>(|conn| {
+        let mut ids = Vec::with_capacity(new_events.len());
+        for (event, tags) in new_events {
+            let id = diesel::insert_into(events::table)
+                .values(&event)
+                .returning(events::id)
+                .get_result::<Uuid>(conn)?;
+            if !tags.is_empty() {
+                set_event_tags(conn, id, &tags)?;
+            }
+            ids.push(id);
+        }
+        Ok(ids)
+    })
+}
+
+/// Tags by number of (not deleted) events using them, most used first.
+/// `prefix` narrows the list down for autocomplete; normalized tags never
+/// contain `%` or `_`, so it needs no escaping.
+#[instrument(skip_all)]
+pub fn get_tag_counts(
+    conn: &mut PgConnection,
+    prefix: &str,
+    limit: i64,
+) -> Result<Vec<TagCount>, Error> {
+    use crate::schema::event_tags;
+    use crate::schema::events;
+    use diesel::dsl::count_star;
+
+    event_tags::table
+        .inner_join(events::table)
+        .filter(events::deleted_at.is_null())
+        .filter(event_tags::tag.like(format!("{}%", prefix)))
+        .group_by(event_tags::tag)
+        .select((event_tags::tag, count_star()))
+        .order((count_star().desc(), event_tags::tag.asc()))
+        .limit(limit)
+        .load::<TagCount>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn create_event_template(
+    conn: &mut PgConnection,
+    template_data: NewEventTemplate,
+) -> Result<EventTemplate, Error> {
+    use crate::schema::event_templates;
+
+    diesel::insert_into(event_templates::table)
+        .values(&template_data)
+        .returning(EventTemplate::as_select())
+        .get_result::<EventTemplate>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_event_templates(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<EventTemplate>, Error> {
+    use crate::schema::event_templates;
+
+    event_templates::table
+        .filter(event_templates::user_id.eq(user_id))
+        .select(EventTemplate::as_select())
+        .order(event_templates::created_at.desc())
+        .load::<EventTemplate>(conn)
+}
+
+#[instrument(skip_all)]
+pub fn get_event_template(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    template_id: Uuid,
+) -> Result<Option<EventTemplate>, Error> {
+    use crate::schema::event_templates;
+
+    event_templates::table
+        .filter(event_templates::id.eq(template_id))
+        .filter(event_templates::user_id.eq(user_id))
+        .select(EventTemplate::as_select())
+        .first::<EventTemplate>(conn)
+        .optional()
+}
+
+#[instrument(skip_all)]
+pub fn delete_event_template(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    template_id: Uuid,
+) -> Result<usize, Error> {
+    use crate::schema::event_templates;
+
+    diesel::delete(
+        event_templates::table
+            .filter(event_templates::id.eq(template_id))
+            .filter(event_templates::user_id.eq(user_id)),
+    )
+    .execute(conn)
+}
+
+/// The member's check-in token, storing `new_token` first if they don't
+/// have one yet. `None` if the user isn't a member of the event.
+#[instrument(skip_all)]
+pub fn get_or_create_checkin_token(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    user_id: Uuid,
+    new_token: &str,
+) -> Result<Option<String>, Error> {
+    use crate::schema::event_members;
+
+    diesel::update(
+        event_members::table
+            .filter(event_members::event_id.eq(event_id))
+            .filter(event_members::user_id.eq(user_id))
+            .filter(event_members::checkin_token.is_null()),
+    )
+    .set(event_members::checkin_token.eq(new_token))
+    .execute(conn)?;
+
+    event_members::table
+        .filter(event_members::event_id.eq(event_id))
+        .filter(event_members::user_id.eq(user_id))
+        .select(event_members::checkin_token)
+        .first::<Option<String>>(conn)
+        .optional()
+        .map(|token| token.flatten())
+}
+
+/// Marks the member holding `token` as present. Scanning the same code
+/// twice keeps the first timestamp. `None` if no member of the event
+/// holds the token.
+#[instrument(skip_all)]
+pub fn check_in_member(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    token: &str,
+    checked_in_by: Uuid,
+) -> Result<Option<CheckinResult>, Error> {
+    use crate::schema::event_members;
+    use crate::schema::users;
+
+    conn.transaction::<_, Error, _>(|conn| {
+        let member = event_members::table
+            .filter(event_members::event_id.eq(event_id))
+            .filter(event_members::checkin_token.eq(token))
+            .inner_join(users::table)
+            .select((users::id, users::name, event_members::checked_in_at))
+            .for_update()
+            .first::<(Uuid, String, Option<chrono::NaiveDateTime>)>(conn)
+            .optional()?;
+        let (user_id, name, checked_in_at) = match member {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        if let Some(checked_in_at) = checked_in_at {
+            return Ok(Some(CheckinResult {
+                user_id,
+                name,
+                checked_in_at,
+                already_checked_in: true,
+            }));
+        }
+
+        let now = chrono::Local::now().naive_local();
+        diesel::update(
+            event_members::table
+                .filter(event_members::event_id.eq(event_id))
+                .filter(event_members::user_id.eq(user_id)),
+        )
+        .set((
+            event_members::checked_in_at.eq(now),
+            event_members::checked_in_by.eq(checked_in_by),
+        ))
+        .execute(conn)?;
+        Ok(Some(CheckinResult {
+            user_id,
+            name,
+            checked_in_at: now,
+            already_checked_in: false,
+        }))
+    })
+}
+
+#[instrument(skip_all)]
+pub fn get_attendance_counts(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    now: chrono::NaiveDateTime,
+) -> Result<AttendanceCounts, Error> {
+    use diesel::sql_types::{Timestamp, Uuid as SqlUuid};
+
+    diesel::sql_query(
+        "SELECT COUNT(*) FILTER (WHERE m.checked_in_at IS NOT NULL) AS attended, \
+                COUNT(*) FILTER (WHERE m.checked_in_at IS NULL AND e.end_time < $2 \
+                    AND EXISTS (SELECT 1 FROM event_members o \
+                                WHERE o.event_id = m.event_id AND o.checked_in_at IS NOT NULL)) AS no_shows \
+         FROM event_members m \
+         JOIN events e ON e.id = m.event_id \
+         WHERE m.user_id = $1 \
+           AND e.established AND NOT e.cancelled AND e.deleted_at IS NULL",
+    )
+    .bind::<SqlUuid, _>(user_id)
+    .bind::<Timestamp, _>(now)
+    .get_result::<AttendanceCounts>(conn)
+}
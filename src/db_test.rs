@@ -1,3 +1,24 @@
+#[cfg(test)]
+/// Events need an existing category; tests all file theirs under this one.
+fn test_category(conn: &mut diesel::pg::PgConnection) {
+    use crate::db::{create_category, get_category};
+    use crate::models::NewCategory;
+
+    if get_category(conn, "test_event").unwrap().is_none() {
+        create_category(
+            conn,
+            NewCategory {
+                slug: "test_event".to_string(),
+                name: "Test".to_string(),
+                icon: String::new(),
+                sort_order: 0,
+                parent: None,
+            },
+        )
+        .ok();
+    }
+}
+
 #[test]
 fn test_get_or_create_user() {
     use crate::db::get_or_create_user;
@@ -110,6 +131,7 @@ fn test_create_event() {
     );
     let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
     let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
     let event_data = NewEvent { 
         name: "test_event".to_string(),
         description: "test_event".to_string(),
@@ -119,6 +141,8 @@ fn test_create_event() {
         user_id: user.id,
         max_amount: 10,
         min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
      };
 
     let data = create_event(&mut conn, event_data);
@@ -154,6 +178,7 @@ fn test_get_events() {
     );
     let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
     let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
     let event_data = NewEvent { 
         name: "test_event".to_string(),
         description: "test_event".to_string(),
@@ -163,6 +188,8 @@ fn test_get_events() {
         user_id: user.id,
         max_amount: 10,
         min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
      };
      
 
@@ -170,7 +197,7 @@ fn test_get_events() {
     let events = get_events(&mut conn);
     delete_event(&mut conn, data.id);
 
-    assert!(events.len() > 0);
+    assert!(!events.is_empty());
 }
 
 #[test]
@@ -200,6 +227,7 @@ fn test_get_event_by_id() {
     );
     let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
     let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
     let event_data = NewEvent { 
         name: "test_event".to_string(),
         description: "test_event".to_string(),
@@ -209,6 +237,8 @@ fn test_get_event_by_id() {
         user_id: user.id,
         max_amount: 10,
         min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
      };
 
     let data = create_event(&mut conn, event_data);
@@ -248,6 +278,7 @@ fn test_delete_event() {
     );
     let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
     let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
     let event_data = NewEvent { 
         name: "test_event".to_string(),
         description: "test_event".to_string(),
@@ -257,6 +288,8 @@ fn test_delete_event() {
         user_id: user.id,
         max_amount: 10,
         min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
      };
 
     let data = create_event(&mut conn, event_data);
@@ -265,3 +298,827 @@ fn test_delete_event() {
     let event = get_event_by_id(&mut conn, data.id, Uuid::nil());
     assert!(event.is_none());
 }
+
+#[test]
+fn test_payment_summary() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::create_event_member;
+    use crate::db::create_payment;
+    use crate::db::get_payment_summary;
+    use crate::db::update_payment_status;
+    use crate::db::delete_event_member;
+    use crate::db::delete_event;
+    use crate::models::{NewEvent, NewEventMember, NewPayment};
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_payment_summary".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let event_data = NewEvent { 
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 100,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+     };
+
+    let data = create_event(&mut conn, event_data);
+    create_event_member(&mut conn, NewEventMember {
+        event_id: data.id,
+        user_id: user.id,
+        amount: 50,
+    }).unwrap();
+    create_payment(&mut conn, NewPayment {
+        event_id: data.id,
+        user_id: user.id,
+        amount: 30,
+        kind: "payment".to_string(),
+        method: "cash".to_string(),
+        reference: "".to_string(),
+        status: "confirmed".to_string(),
+        paid_at: None,
+        confirmed_at: None,
+        provider_ref: None,
+        refund_of: None,
+    }).unwrap();
+    let claim = create_payment(&mut conn, NewPayment {
+        event_id: data.id,
+        user_id: user.id,
+        amount: 20,
+        kind: "payment".to_string(),
+        method: "transfer".to_string(),
+        reference: "12345".to_string(),
+        status: "pending".to_string(),
+        paid_at: None,
+        confirmed_at: None,
+        provider_ref: None,
+        refund_of: None,
+    }).unwrap();
+
+    let pending = get_payment_summary(&mut conn, data.id);
+    update_payment_status(&mut conn, claim.id, "confirmed").unwrap();
+    let settled = get_payment_summary(&mut conn, data.id);
+    delete_event_member(&mut conn, data.id, user.id);
+    delete_event(&mut conn, data.id);
+
+    assert_eq!(pending.expected_amount.minor, 50);
+    assert_eq!(pending.paid_amount.minor, 30);
+    assert_eq!(pending.pending_amount.minor, 20);
+    assert_eq!(pending.outstanding_amount.minor, 20);
+    assert_eq!(settled.outstanding_amount.minor, 0);
+    assert_eq!(settled.members_outstanding, 0);
+}
+
+#[test]
+fn test_notification_preferences() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::delete_event;
+    use crate::db::create_notifications;
+    use crate::db::count_unread_notifications;
+    use crate::db::filter_notification_recipients;
+    use crate::db::mark_all_notifications_read;
+    use crate::db::set_notification_preference;
+    use crate::models::{NewEvent, NotificationPreference};
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+    use uuid::Uuid;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_notification_preferences".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let event_data = NewEvent { 
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 10,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+     };
+    let data = create_event(&mut conn, event_data);
+
+    set_notification_preference(&mut conn, NotificationPreference {
+        user_id: user.id,
+        event_id: Uuid::nil(),
+        kind: "comment_created".to_string(),
+        enabled: false,
+    }).unwrap();
+    set_notification_preference(&mut conn, NotificationPreference {
+        user_id: user.id,
+        event_id: data.id,
+        kind: "comment_created".to_string(),
+        enabled: true,
+    }).unwrap();
+    let event_override = filter_notification_recipients(
+        &mut conn, vec![user.id], data.id, "comment_created").unwrap();
+    let muted = filter_notification_recipients(
+        &mut conn, vec![user.id], Uuid::new_v4(), "comment_created").unwrap();
+
+    mark_all_notifications_read(&mut conn, user.id).unwrap();
+    create_notifications(&mut conn, &event_override, data.id, "comment_created", "hi").unwrap();
+    let unread = count_unread_notifications(&mut conn, user.id).unwrap();
+    mark_all_notifications_read(&mut conn, user.id).unwrap();
+    let read = count_unread_notifications(&mut conn, user.id).unwrap();
+    delete_event(&mut conn, data.id);
+
+    assert_eq!(event_override, vec![user.id]);
+    assert!(muted.is_empty());
+    assert_eq!(unread, 1);
+    assert_eq!(read, 0);
+}
+
+#[test]
+fn test_soft_delete_and_purge_event() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::delete_event;
+    use crate::db::expire_past_events;
+    use crate::db::get_events;
+    use crate::db::purge_deleted_events;
+    use crate::models::NewEvent;
+    use crate::schema::events;
+    use diesel::pg::PgConnection;
+    use diesel::prelude::*;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_soft_delete_and_purge_event".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let event_data = NewEvent { 
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 10,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+     };
+    let data = create_event(&mut conn, event_data);
+
+    expire_past_events(&mut conn, Local::now().naive_local()).unwrap();
+    let expired: bool = events::table
+        .find(data.id)
+        .select(events::expired)
+        .first(&mut conn)
+        .unwrap();
+
+    delete_event(&mut conn, data.id);
+    let listed = get_events(&mut conn).iter().any(|e| e.id == data.id);
+    let kept = events::table.find(data.id).count().get_result::<i64>(&mut conn).unwrap();
+
+    purge_deleted_events(&mut conn, Utc::now().naive_utc() + Duration::minutes(1)).unwrap();
+    let purged = events::table.find(data.id).count().get_result::<i64>(&mut conn).unwrap();
+
+    assert!(expired);
+    assert!(!listed);
+    assert_eq!(kept, 1);
+    assert_eq!(purged, 0);
+}
+
+#[test]
+fn test_claim_and_dead_letter_job() {
+    use crate::db::claim_jobs;
+    use crate::db::enqueue_job;
+    use crate::db::mark_job_failed;
+    use crate::db::retry_dead_job;
+    use crate::models::{Job, NewJob, JOB_STATUS_DEAD, JOB_STATUS_QUEUED, JOB_STATUS_RUNNING};
+    use crate::schema::jobs;
+    use diesel::pg::PgConnection;
+    use diesel::prelude::*;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let now = Utc::now().naive_utc();
+    let job = enqueue_job(&mut conn, NewJob {
+        kind: "test_claim_and_dead_letter_job".to_string(),
+        payload: "".to_string(),
+        run_at: now - Duration::seconds(1),
+    }).unwrap();
+
+    let first = claim_jobs(&mut conn, "worker-a", 100, now - Duration::minutes(15)).unwrap();
+    let second = claim_jobs(&mut conn, "worker-b", 100, now - Duration::minutes(15)).unwrap();
+    let claimed = first.iter().find(|j| j.id == job.id).unwrap();
+
+    mark_job_failed(&mut conn, job.id, "boom", None).unwrap();
+    let dead: Job = jobs::table.find(job.id).select(Job::as_select()).first(&mut conn).unwrap();
+    retry_dead_job(&mut conn, job.id).unwrap();
+    let requeued: Job = jobs::table.find(job.id).select(Job::as_select()).first(&mut conn).unwrap();
+    diesel::delete(jobs::table.find(job.id)).execute(&mut conn).unwrap();
+
+    assert_eq!(claimed.status, JOB_STATUS_RUNNING);
+    assert_eq!(claimed.locked_by.as_deref(), Some("worker-a"));
+    assert_eq!(claimed.attempts, 1);
+    assert!(!second.iter().any(|j| j.id == job.id));
+    assert_eq!(dead.status, JOB_STATUS_DEAD);
+    assert_eq!(dead.last_error.as_deref(), Some("boom"));
+    assert_eq!(requeued.status, JOB_STATUS_QUEUED);
+    assert_eq!(requeued.attempts, 0);
+}
+
+#[test]
+fn test_search_events() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::delete_event;
+    use crate::db::search_events;
+    use crate::models::{NewEvent, SearchQuery};
+    use crate::search::SearchFilter;
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_search_events".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let event_data = NewEvent { 
+        name: "test_search_events zxqramen".to_string(),
+        description: "Group order for 豚骨拉麵".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 10,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+     };
+    let data = create_event(&mut conn, event_data);
+
+    let search = |conn: &mut PgConnection, q: &str, category: Option<&str>| {
+        let query = SearchQuery {
+            q: q.to_string(),
+            category: category.map(|c| c.to_string()),
+            from: None,
+            to: None,
+            comments: false,
+            limit: None,
+        };
+        search_events(conn, &SearchFilter::new(&query, 20))
+            .unwrap()
+            .iter()
+            .any(|e| e.id == data.id)
+    };
+
+    let by_word = search(&mut conn, "ZXQRAMEN", None);
+    let by_substring = search(&mut conn, "拉麵", None);
+    let other_category = search(&mut conn, "zxqramen", Some("other"));
+    delete_event(&mut conn, data.id);
+    let deleted = search(&mut conn, "zxqramen", None);
+
+    assert!(by_word);
+    assert!(by_substring);
+    assert!(!other_category);
+    assert!(!deleted);
+}
+
+#[test]
+fn test_get_events_near() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::delete_event;
+    use crate::db::get_events_near;
+    use crate::models::{EventLocation, NewEvent};
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_get_events_near".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let new_event = |lat: f64, lng: f64| NewEvent { 
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 10,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: EventLocation {
+            address: None,
+            venue: Some("test_venue".to_string()),
+            latitude: Some(lat),
+            longitude: Some(lng),
+        },
+     };
+    // Taipei Main Station, then Kaohsiung.
+    let near = create_event(&mut conn, new_event(25.0478, 121.5170));
+    let far = create_event(&mut conn, new_event(22.6273, 120.3014));
+
+    let events = get_events_near(&mut conn, 25.0340, 121.5645, 10.0);
+    delete_event(&mut conn, near.id);
+    delete_event(&mut conn, far.id);
+
+    let found = events.iter().find(|e| e.id == near.id).unwrap();
+    assert!((found.distance_km.unwrap() - 5.03).abs() < 0.01);
+    assert!(!events.iter().any(|e| e.id == far.id));
+    assert!(events.windows(2).all(|w| w[0].distance_km <= w[1].distance_km));
+}
+
+#[test]
+fn test_categories() {
+    use crate::db::{
+        category_in_use, create_category, create_event, delete_category, delete_event,
+        get_categories, get_category, get_or_create_user, purge_deleted_events, update_category,
+    };
+    use crate::models::{NewCategory, NewEvent, UpdateCategory};
+    use chrono::{Duration, Local, Utc};
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_categories".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+
+    test_category(&mut conn);
+    delete_category(&mut conn, "test-child").unwrap();
+    delete_category(&mut conn, "test-parent").unwrap();
+    let parent = create_category(
+        &mut conn,
+        NewCategory {
+            slug: "test-parent".to_string(),
+            name: "Parent".to_string(),
+            icon: "star".to_string(),
+            sort_order: 1,
+            parent: None,
+        },
+    )
+    .unwrap();
+    create_category(
+        &mut conn,
+        NewCategory {
+            slug: "test-child".to_string(),
+            name: "Child".to_string(),
+            icon: String::new(),
+            sort_order: 0,
+            parent: Some(parent.slug.clone()),
+        },
+    )
+    .unwrap();
+    assert!(category_in_use(&mut conn, "test-parent").unwrap());
+    assert!(!category_in_use(&mut conn, "test-child").unwrap());
+
+    let open_count = |conn: &mut PgConnection| {
+        get_categories(conn)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.category.slug == "test-child")
+            .map(|c| c.open_events)
+    };
+    assert_eq!(open_count(&mut conn), Some(0));
+
+    let start = Local::now().naive_local() + Duration::days(1);
+    let event = create_event(
+        &mut conn,
+        NewEvent {
+            name: "test_event".to_string(),
+            description: "test_event".to_string(),
+            category: "test-child".to_string(),
+            start_time: start,
+            end_time: start + Duration::hours(2),
+            user_id: user.id,
+            max_amount: 10,
+            min_amount: 1,
+            currency: "TWD".to_string(),
+            location: Default::default(),
+        },
+    );
+    assert_eq!(open_count(&mut conn), Some(1));
+    assert!(category_in_use(&mut conn, "test-child").unwrap());
+
+    // Soft-deleted events drop out of the count but still hold the category.
+    delete_event(&mut conn, event.id);
+    assert_eq!(open_count(&mut conn), Some(0));
+    assert!(category_in_use(&mut conn, "test-child").unwrap());
+    purge_deleted_events(&mut conn, Utc::now().naive_utc() + Duration::seconds(1)).unwrap();
+    assert!(!category_in_use(&mut conn, "test-child").unwrap());
+
+    let updated = update_category(
+        &mut conn,
+        "test-child",
+        UpdateCategory {
+            name: Some("Renamed".to_string()),
+            icon: None,
+            sort_order: None,
+            parent: Some(None),
+        },
+    )
+    .unwrap();
+    assert_eq!(updated.name, "Renamed");
+    assert_eq!(updated.parent, None);
+    assert!(!category_in_use(&mut conn, "test-parent").unwrap());
+
+    assert_eq!(delete_category(&mut conn, "test-child").unwrap(), 1);
+    assert_eq!(delete_category(&mut conn, "test-parent").unwrap(), 1);
+    assert!(get_category(&mut conn, "test-child").unwrap().is_none());
+}
+
+#[test]
+fn test_event_tags() {
+    use crate::db::{
+        create_event, delete_event, get_event_by_id, get_or_create_user, get_tag_counts,
+        set_event_tags,
+    };
+    use crate::models::NewEvent;
+    use chrono::*;
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use dotenvy;
+    use std::env;
+    use uuid::Uuid;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_event_tags".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let new_event = || NewEvent {
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 10,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+    };
+    let first = create_event(&mut conn, new_event());
+    let second = create_event(&mut conn, new_event());
+    assert!(first.tags.is_empty());
+
+    let tags = set_event_tags(
+        &mut conn,
+        first.id,
+        &["zxqtag-weekend".to_string(), "zxqtag-vegan".to_string()],
+    )
+    .unwrap();
+    assert_eq!(tags, vec!["zxqtag-vegan".to_string(), "zxqtag-weekend".to_string()]);
+    set_event_tags(&mut conn, second.id, &["zxqtag-vegan".to_string()]).unwrap();
+
+    let counts = get_tag_counts(&mut conn, "zxqtag-", 10).unwrap();
+    let counts: Vec<(String, i64)> = counts.into_iter().map(|c| (c.tag, c.count)).collect();
+    assert_eq!(
+        counts,
+        vec![("zxqtag-vegan".to_string(), 2), ("zxqtag-weekend".to_string(), 1)]
+    );
+    assert_eq!(get_tag_counts(&mut conn, "zxqtag-w", 10).unwrap().len(), 1);
+
+    // Replacing drops tags that aren't in the new set.
+    set_event_tags(&mut conn, first.id, &["zxqtag-bulk".to_string()]).unwrap();
+    let event = get_event_by_id(&mut conn, first.id, Uuid::nil()).unwrap();
+    assert_eq!(event.tags, vec!["zxqtag-bulk".to_string()]);
+    set_event_tags(&mut conn, first.id, &[]).unwrap();
+
+    // Deleted events no longer count.
+    delete_event(&mut conn, second.id);
+    assert!(get_tag_counts(&mut conn, "zxqtag-", 10).unwrap().is_empty());
+    delete_event(&mut conn, first.id);
+}
+
+#[test]
+fn test_check_in_and_attendance() {
+    use crate::db::{
+        check_in_member, create_event, create_event_member, delete_event, get_attendance_counts,
+        get_event_by_id, get_or_create_checkin_token, get_or_create_user,
+    };
+    use crate::checkin::generate_token;
+    use crate::models::{NewEvent, NewEventMember};
+    use crate::schema::events;
+    use chrono::*;
+    use diesel::pg::PgConnection;
+    use diesel::prelude::*;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let owner = get_or_create_user(
+        &mut conn,
+        "test_check_in_owner".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let present = get_or_create_user(
+        &mut conn,
+        "test_check_in_present".to_string(),
+        "test_present".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let absent = get_or_create_user(
+        &mut conn,
+        "test_check_in_absent".to_string(),
+        "test_absent".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let now = Local::now().naive_local();
+    let before = get_attendance_counts(&mut conn, absent.id, now).unwrap();
+
+    test_category(&mut conn);
+    let event = create_event(
+        &mut conn,
+        NewEvent {
+            name: "test_event".to_string(),
+            description: "test_event".to_string(),
+            category: "test_event".to_string(),
+            start_time: now - Duration::hours(3),
+            end_time: now - Duration::hours(1),
+            user_id: owner.id,
+            max_amount: 10,
+            min_amount: 1,
+            currency: "TWD".to_string(),
+            location: Default::default(),
+        },
+    );
+    for user_id in [present.id, absent.id] {
+        create_event_member(
+            &mut conn,
+            NewEventMember {
+                event_id: event.id,
+                user_id,
+                amount: 1,
+            },
+        )
+        .unwrap();
+    }
+    diesel::update(events::table.find(event.id))
+        .set(events::established.eq(true))
+        .execute(&mut conn)
+        .unwrap();
+
+    // The token is created once and then reused.
+    let token = get_or_create_checkin_token(&mut conn, event.id, present.id, &generate_token())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        get_or_create_checkin_token(&mut conn, event.id, present.id, "ci_other").unwrap(),
+        Some(token.clone())
+    );
+    assert_eq!(
+        get_or_create_checkin_token(&mut conn, event.id, owner.id, "ci_owner").unwrap(),
+        None
+    );
+
+    assert!(check_in_member(&mut conn, event.id, "ci_unknown", owner.id).unwrap().is_none());
+    let first = check_in_member(&mut conn, event.id, &token, owner.id).unwrap().unwrap();
+    assert_eq!(first.user_id, present.id);
+    assert!(!first.already_checked_in);
+    let second = check_in_member(&mut conn, event.id, &token, owner.id).unwrap().unwrap();
+    assert!(second.already_checked_in);
+    assert_eq!(second.checked_in_at, first.checked_in_at);
+
+    let view = get_event_by_id(&mut conn, event.id, owner.id).unwrap();
+    let attendance = view.attendance.unwrap();
+    assert_eq!((attendance.checked_in, attendance.not_checked_in), (1, 1));
+
+    let after = get_attendance_counts(&mut conn, absent.id, now).unwrap();
+    assert_eq!(after.no_shows, before.no_shows + 1);
+    assert_eq!(after.attended, before.attended);
+    assert!(get_attendance_counts(&mut conn, present.id, now).unwrap().attended >= 1);
+
+    delete_event(&mut conn, event.id);
+    assert_eq!(get_attendance_counts(&mut conn, absent.id, now).unwrap().no_shows, before.no_shows);
+}
+
+#[test]
+fn test_reconcile_unknown_checkout_is_retried() {
+    use crate::db::get_or_create_user;
+    use crate::db::create_event;
+    use crate::db::create_event_member;
+    use crate::db::create_payment;
+    use crate::db::reconcile_provider_event;
+    use crate::db::delete_event_member;
+    use crate::db::delete_event;
+    use crate::models::{NewEvent, NewEventMember, NewPayment};
+    use crate::payment::{ProviderEvent, ProviderEventKind};
+    use diesel::pg::PgConnection;
+    use diesel::result::Error;
+    use diesel::Connection;
+    use chrono::*;
+    use dotenvy;
+    use std::env;
+    use uuid::Uuid;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_reconcile".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    let d = NaiveDate::from_ymd_opt(2015, 6, 3).unwrap();
+    let t = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+    test_category(&mut conn);
+    let data = create_event(&mut conn, NewEvent {
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: NaiveDateTime::new(d, t),
+        end_time: NaiveDateTime::new(d, t),
+        user_id: user.id,
+        max_amount: 100,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+    });
+    create_event_member(&mut conn, NewEventMember {
+        event_id: data.id,
+        user_id: user.id,
+        amount: 50,
+    }).unwrap();
+
+    let checkout_ref = format!("cs_{}", Uuid::new_v4());
+    let event = ProviderEvent {
+        id: format!("evt_{}", Uuid::new_v4()),
+        checkout_ref: checkout_ref.clone(),
+        payment_ref: Some("pi_1".to_string()),
+        kind: ProviderEventKind::Succeeded,
+    };
+    // The webhook beat the checkout to the database.
+    let early = reconcile_provider_event(&mut conn, "fake", &event);
+
+    create_payment(&mut conn, NewPayment {
+        event_id: data.id,
+        user_id: user.id,
+        amount: 50,
+        kind: "payment".to_string(),
+        method: "card".to_string(),
+        reference: "".to_string(),
+        status: "pending".to_string(),
+        paid_at: None,
+        confirmed_at: None,
+        provider_ref: Some(checkout_ref),
+        refund_of: None,
+    }).unwrap();
+    let retried = reconcile_provider_event(&mut conn, "fake", &event).unwrap();
+    let duplicate = reconcile_provider_event(&mut conn, "fake", &event).unwrap();
+    delete_event_member(&mut conn, data.id, user.id);
+    delete_event(&mut conn, data.id);
+
+    assert!(matches!(early, Err(Error::NotFound)));
+    assert_eq!(retried.unwrap().status, "confirmed");
+    assert!(duplicate.is_none());
+}
+
+#[test]
+fn test_joined_at_uses_local_clock() {
+    use crate::db::{create_event, create_event_member, delete_event, get_event_by_id, get_or_create_user};
+    use crate::models::{NewEvent, NewEventMember};
+    use chrono::*;
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_joined_at_uses_local_clock".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    test_category(&mut conn);
+    let start = Local::now().naive_local() + Duration::days(1);
+    let event = create_event(&mut conn, NewEvent {
+        name: "test_event".to_string(),
+        description: "test_event".to_string(),
+        category: "test_event".to_string(),
+        start_time: start,
+        end_time: start,
+        user_id: user.id,
+        max_amount: 100,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+    });
+    create_event_member(&mut conn, NewEventMember {
+        event_id: event.id,
+        user_id: user.id,
+        amount: 50,
+    }).unwrap();
+
+    let members = get_event_by_id(&mut conn, event.id, user.id).unwrap().members.unwrap();
+    delete_event(&mut conn, event.id);
+    let joined_at = members[0].joined_at.unwrap();
+    assert!((Local::now().naive_local() - joined_at).num_seconds().abs() < 60);
+}
@@ -0,0 +1,107 @@
+#[test]
+fn test_templates_escape_html() {
+    use crate::email::templates::event_established;
+    use chrono::NaiveDate;
+
+    let start = NaiveDate::from_ymd_opt(2023, 6, 3)
+        .unwrap()
+        .and_hms_opt(12, 30, 0)
+        .unwrap();
+    let rendered = event_established(
+        "Alice",
+        "<b>Ramen</b> & friends",
+        start,
+        "https://app.example/?event_id=1",
+        "https://api.example/api/v1/unsubscribe?token=abc",
+    );
+
+    assert_eq!(rendered.subject, "<b>Ramen</b> & friends is established");
+    assert!(rendered.html.contains("&lt;b&gt;Ramen&lt;/b&gt; &amp; friends"));
+    assert!(!rendered.html.contains("<b>Ramen"));
+    assert!(rendered.text.contains("<b>Ramen</b> & friends"));
+    assert!(rendered.text.contains("2023-06-03 12:30"));
+    assert!(rendered.text.contains("unsubscribe?token=abc"));
+}
+
+#[test]
+fn test_unsubscribe_token() {
+    use crate::email::{unsubscribe_token, verify_unsubscribe_token};
+    use uuid::Uuid;
+
+    let user_id = Uuid::new_v4();
+    let token = unsubscribe_token(b"secret", user_id, "email_comment_digest");
+
+    assert!(verify_unsubscribe_token(b"secret", user_id, "email_comment_digest", &token));
+    assert!(!verify_unsubscribe_token(b"secret", user_id, "email_event_reminder", &token));
+    assert!(!verify_unsubscribe_token(b"other", user_id, "email_comment_digest", &token));
+    assert!(!verify_unsubscribe_token(b"secret", Uuid::new_v4(), "email_comment_digest", &token));
+}
+
+#[test]
+fn test_capture_transport() {
+    use crate::email::templates::comment_digest;
+    use crate::email::{CaptureTransport, Email, EmailTransport};
+
+    let transport = CaptureTransport::default();
+    let rendered = comment_digest(
+        "Bob",
+        &["Alice commented on Ramen".to_string(), "Carol commented on Ramen".to_string()],
+        "https://app.example/",
+        "https://api.example/unsubscribe",
+    );
+    transport
+        .send(&Email {
+            to: "bob@example.com".to_string(),
+            subject: rendered.subject,
+            html: rendered.html,
+            text: rendered.text,
+            unsubscribe_url: None,
+        })
+        .unwrap();
+
+    let sent = transport.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "bob@example.com");
+    assert!(sent[0].text.contains("were 2 new comments"));
+    assert!(sent[0].text.contains("- Carol commented on Ramen"));
+}
+
+#[test]
+fn test_retry_backoff() {
+    use crate::email::worker::retry_at;
+    use chrono::{Duration, NaiveDate};
+
+    let now = NaiveDate::from_ymd_opt(2023, 6, 3)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+
+    assert_eq!(retry_at(0, now), Some(now + Duration::minutes(1)));
+    assert_eq!(retry_at(3, now), Some(now + Duration::minutes(8)));
+    assert_eq!(retry_at(4, now), None);
+}
+
+#[test]
+fn test_one_click_unsubscribe_headers() {
+    use crate::email::{build_message, Email};
+
+    let from = "O2Gather <noreply@o2gather.app>".parse().unwrap();
+    let email = Email {
+        to: "bob@example.com".to_string(),
+        subject: "Hi".to_string(),
+        html: "<p>Hi</p>".to_string(),
+        text: "Hi".to_string(),
+        unsubscribe_url: Some("https://api.example/api/v1/unsubscribe?token=abc".to_string()),
+    };
+
+    let message = build_message(&from, &email).unwrap();
+    let headers = message.headers();
+    assert_eq!(
+        headers.get_raw("List-Unsubscribe"),
+        Some("<https://api.example/api/v1/unsubscribe?token=abc>")
+    );
+    assert_eq!(headers.get_raw("List-Unsubscribe-Post"), Some("List-Unsubscribe=One-Click"));
+
+    let plain = build_message(&from, &Email { unsubscribe_url: None, ..email }).unwrap();
+    assert!(plain.headers().get_raw("List-Unsubscribe").is_none());
+}
@@ -0,0 +1,153 @@
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, Message, MultiPart};
+use lettre::Transport;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::signing::{hmac_hex, hmac_verify};
+
+pub mod templates;
+pub mod worker;
+
+mod email_test;
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    /// Sent as `List-Unsubscribe`, with RFC 8058 one-click support.
+    pub unsubscribe_url: Option<String>,
+}
+
+pub trait EmailTransport: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+pub struct EmailSettings {
+    /// Base url of this backend, used for unsubscribe links.
+    pub public_url: String,
+    /// Frontend url that emails link to.
+    pub app_url: String,
+    pub secret: Vec<u8>,
+}
+
+fn build_message(from: &Mailbox, email: &Email) -> Result<Message, String> {
+    let to: Mailbox = email.to.parse().map_err(|e| format!("invalid recipient: {}", e))?;
+    let mut message = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject.clone())
+        .multipart(MultiPart::alternative_plain_html(
+            email.text.clone(),
+            email.html.clone(),
+        ))
+        .map_err(|e| e.to_string())?;
+    if let Some(url) = &email.unsubscribe_url {
+        let headers = message.headers_mut();
+        headers.insert_raw(HeaderValue::new(
+            HeaderName::new_from_ascii_str("List-Unsubscribe"),
+            format!("<{}>", url),
+        ));
+        headers.insert_raw(HeaderValue::new(
+            HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+            "List-Unsubscribe=One-Click".to_string(),
+        ));
+    }
+    Ok(message)
+}
+
+pub struct SmtpTransport {
+    inner: lettre::SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpTransport {
+    pub fn new(url: &str, from: &str) -> Result<Self, String> {
+        let inner = lettre::SmtpTransport::from_url(url)
+            .map_err(|e| e.to_string())?
+            .build();
+        let from = from.parse().map_err(|e| format!("invalid sender: {}", e))?;
+        Ok(SmtpTransport { inner, from })
+    }
+}
+
+impl EmailTransport for SmtpTransport {
+    fn send(&self, email: &Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+        self.inner.send(&message).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// Writes every email as an `.eml` file into a directory, for local
+/// development.
+pub struct FileTransport {
+    inner: lettre::FileTransport,
+    from: Mailbox,
+}
+
+impl FileTransport {
+    pub fn new(dir: &str, from: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let from = from.parse().map_err(|e| format!("invalid sender: {}", e))?;
+        Ok(FileTransport {
+            inner: lettre::FileTransport::new(dir),
+            from,
+        })
+    }
+}
+
+impl EmailTransport for FileTransport {
+    fn send(&self, email: &Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+        self.inner.send(&message).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// Keeps sent emails in memory so tests can inspect them.
+#[derive(Default)]
+pub struct CaptureTransport {
+    pub sent: Mutex<Vec<Email>>,
+}
+
+impl EmailTransport for CaptureTransport {
+    fn send(&self, email: &Email) -> Result<(), String> {
+        self.sent.lock().unwrap().push(Email {
+            to: email.to.clone(),
+            subject: email.subject.clone(),
+            html: email.html.clone(),
+            text: email.text.clone(),
+            unsubscribe_url: email.unsubscribe_url.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Only call on a validated config. Fails if the transport can't be set
+/// up, e.g. when the mail directory can't be created.
+pub fn transport_from_config(config: &Config) -> Result<Arc<dyn EmailTransport>, String> {
+    match config.email_transport.as_str() {
+        "smtp" => Ok(Arc::new(SmtpTransport::new(&config.smtp_url, &config.email_from)?)),
+        "capture" => Ok(Arc::new(CaptureTransport::default())),
+        _ => Ok(Arc::new(FileTransport::new(&config.email_dir, &config.email_from)?)),
+    }
+}
+
+pub fn unsubscribe_token(secret: &[u8], user_id: Uuid, kind: &str) -> String {
+    hmac_hex(secret, format!("{}:{}", user_id, kind).as_bytes())
+}
+
+pub fn verify_unsubscribe_token(secret: &[u8], user_id: Uuid, kind: &str, token: &str) -> bool {
+    hmac_verify(secret, format!("{}:{}", user_id, kind).as_bytes(), token)
+}
+
+pub fn unsubscribe_url(settings: &EmailSettings, user_id: Uuid, kind: &str) -> String {
+    format!(
+        "{}/api/v1/unsubscribe?user_id={}&kind={}&token={}",
+        settings.public_url.trim_end_matches('/'),
+        user_id,
+        kind,
+        unsubscribe_token(&settings.secret, user_id, kind)
+    )
+}
@@ -0,0 +1,163 @@
+use chrono::NaiveDateTime;
+
+pub struct Rendered {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders plain `body_lines` into both bodies, with the greeting, a link
+/// to the app and the unsubscribe footer.
+fn layout(
+    subject: String,
+    user_name: &str,
+    body_lines: &[String],
+    link: &str,
+    unsubscribe_url: &str,
+) -> Rendered {
+    let mut html = format!(
+        "<!DOCTYPE html><html><body><p>Hi {},</p>",
+        escape_html(user_name)
+    );
+    for line in body_lines {
+        html.push_str(&format!("<p>{}</p>", escape_html(line)));
+    }
+    html.push_str(&format!(
+        "<p><a href=\"{}\">Open O2Gather</a></p>\
+         <hr><p style=\"font-size:12px;color:#888\">\
+         <a href=\"{}\">Unsubscribe</a> from these emails.</p></body></html>",
+        escape_html(link),
+        escape_html(unsubscribe_url)
+    ));
+
+    let mut text = format!("Hi {},\n\n", user_name);
+    for line in body_lines {
+        text.push_str(line);
+        text.push_str("\n\n");
+    }
+    text.push_str(&format!(
+        "Open O2Gather: {}\n\n--\nUnsubscribe from these emails: {}\n",
+        link, unsubscribe_url
+    ));
+
+    Rendered {
+        subject,
+        html,
+        text,
+    }
+}
+
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub fn event_established(
+    user_name: &str,
+    event_name: &str,
+    start_time: NaiveDateTime,
+    link: &str,
+    unsubscribe_url: &str,
+) -> Rendered {
+    layout(
+        format!("{} is established", event_name),
+        user_name,
+        &[format!(
+            "{} has reached its goal and is now established. It starts at {}.",
+            event_name,
+            format_time(start_time)
+        )],
+        link,
+        unsubscribe_url,
+    )
+}
+
+pub fn event_cancelled(
+    user_name: &str,
+    event_name: &str,
+    link: &str,
+    unsubscribe_url: &str,
+) -> Rendered {
+    layout(
+        format!("{} has been cancelled", event_name),
+        user_name,
+        &[format!(
+            "{} has been cancelled by its organizer. Online payments are refunded automatically.",
+            event_name
+        )],
+        link,
+        unsubscribe_url,
+    )
+}
+
+pub fn event_reminder(
+    user_name: &str,
+    event_name: &str,
+    start_time: NaiveDateTime,
+    link: &str,
+    unsubscribe_url: &str,
+) -> Rendered {
+    layout(
+        format!("Reminder: {} starts soon", event_name),
+        user_name,
+        &[format!(
+            "{} starts at {}, in less than 24 hours.",
+            event_name,
+            format_time(start_time)
+        )],
+        link,
+        unsubscribe_url,
+    )
+}
+
+pub fn comment_digest(
+    user_name: &str,
+    comments: &[String],
+    link: &str,
+    unsubscribe_url: &str,
+) -> Rendered {
+    let mut lines = vec![format!(
+        "There {} in your events during the last hour:",
+        if comments.len() == 1 {
+            "was 1 new comment".to_string()
+        } else {
+            format!("were {} new comments", comments.len())
+        }
+    )];
+    lines.extend(comments.iter().map(|c| format!("- {}", c)));
+
+    layout(
+        "New comments in your events".to_string(),
+        user_name,
+        &lines,
+        link,
+        unsubscribe_url,
+    )
+}
+
+/// Page behind the unsubscribe link; the button posts to `action`.
+pub fn unsubscribe_confirmation(kind: &str, action: &str) -> String {
+    let kind = kind.trim_start_matches("email_").replace('_', " ");
+    format!(
+        "<!DOCTYPE html><html><body>\
+         <p>Stop receiving {} emails from O2Gather?</p>\
+         <form method=\"post\" action=\"{}\"><button type=\"submit\">Unsubscribe</button></form>\
+         </body></html>",
+        escape_html(&kind),
+        escape_html(action)
+    )
+}
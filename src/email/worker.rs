@@ -0,0 +1,199 @@
+use chrono::{Duration as ChronoDuration, NaiveDateTime};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::db;
+use crate::email::templates;
+use crate::email::{unsubscribe_url, Email, EmailSettings, EmailTransport};
+use crate::models::{
+    NewOutboxEmail, OutboxEmail, EMAIL_COMMENT_DIGEST, EMAIL_EVENT_CANCELLED,
+    EMAIL_EVENT_ESTABLISHED, EMAIL_EVENT_REMINDER, NOTIFY_EVENT_CANCELLED,
+    NOTIFY_EVENT_ESTABLISHED,
+};
+use crate::notify;
+
+const BATCH_SIZE: i64 = 50;
+const MAX_ATTEMPTS: i32 = 5;
+
+/// The email sent for an in-app notification kind, if any. Comments are
+/// not mapped here since they go out as hourly digests instead.
+pub fn email_kind_for(notify_kind: &str) -> Option<&'static str> {
+    match notify_kind {
+        NOTIFY_EVENT_ESTABLISHED => Some(EMAIL_EVENT_ESTABLISHED),
+        NOTIFY_EVENT_CANCELLED => Some(EMAIL_EVENT_CANCELLED),
+        _ => None,
+    }
+}
+
+/// Exponential backoff after a failed attempt, or `None` once the email
+/// has used up its attempts.
+pub fn retry_at(attempts: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if attempts + 1 >= MAX_ATTEMPTS {
+        return None;
+    }
+    Some(now + ChronoDuration::minutes(1i64 << attempts))
+}
+
+pub fn enqueue_event_emails(
+    conn: &mut PgConnection,
+    recipients: Vec<Uuid>,
+    event_id: Uuid,
+    email_kind: &str,
+) -> Result<usize, Error> {
+    let recipients = db::filter_notification_recipients(conn, recipients, event_id, email_kind)?;
+    let emails: Vec<NewOutboxEmail> = recipients
+        .into_iter()
+        .map(|user_id| NewOutboxEmail {
+            user_id,
+            kind: email_kind.to_string(),
+            event_id: Some(event_id),
+            payload: "".to_string(),
+        })
+        .collect();
+
+    db::enqueue_emails(conn, &emails)
+}
+
+pub fn enqueue_event_reminders(conn: &mut PgConnection) -> Result<usize, Error> {
+    let now = chrono::Local::now().naive_local();
+    let events = db::get_events_needing_reminder(conn, now, now + ChronoDuration::hours(24))?;
+
+    let mut count = 0;
+    for event in events {
+        count += conn.transaction::<usize, Error, _>(|conn| {
+            let audience = notify::event_audience(conn, event.id, event.user_id, Uuid::nil());
+            let queued = enqueue_event_emails(conn, audience, event.id, EMAIL_EVENT_REMINDER)?;
+            db::mark_reminder_sent(conn, event.id)?;
+            Ok(queued)
+        })?;
+    }
+
+    Ok(count)
+}
+
+/// Folds every comment notification not emailed yet into one digest per
+/// user.
+pub fn enqueue_comment_digests(conn: &mut PgConnection) -> Result<usize, Error> {
+    conn.transaction::<usize, Error, _>(|conn| {
+        let pending = db::get_undigested_comment_notifications(conn)?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut allowed: HashMap<(Uuid, Uuid), bool> = HashMap::new();
+        let mut digests: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for n in pending.iter() {
+            let send = match allowed.entry((n.user_id, n.event_id)) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    let recipients = db::filter_notification_recipients(
+                        conn,
+                        vec![n.user_id],
+                        n.event_id,
+                        EMAIL_COMMENT_DIGEST,
+                    )?;
+                    *e.insert(!recipients.is_empty())
+                }
+            };
+            if send {
+                digests.entry(n.user_id).or_default().push(n.message.clone());
+            }
+        }
+
+        let emails: Vec<NewOutboxEmail> = digests
+            .into_iter()
+            .map(|(user_id, messages)| NewOutboxEmail {
+                user_id,
+                kind: EMAIL_COMMENT_DIGEST.to_string(),
+                event_id: None,
+                payload: serde_json::to_string(&messages).unwrap(),
+            })
+            .collect();
+        let ids: Vec<Uuid> = pending.iter().map(|n| n.id).collect();
+        db::mark_notifications_emailed(conn, &ids)?;
+
+        db::enqueue_emails(conn, &emails)
+    })
+}
+
+/// Builds the email for an outbox row from current data. `None` means
+/// there is nothing to send any more, e.g. the user has no address.
+pub fn render(conn: &mut PgConnection, email: &OutboxEmail, settings: &EmailSettings) -> Option<Email> {
+    let user = db::get_user_by_id(conn, email.user_id);
+    if user.email.is_empty() {
+        return None;
+    }
+    let unsubscribe = unsubscribe_url(settings, user.id, &email.kind);
+
+    let rendered = if email.kind == EMAIL_COMMENT_DIGEST {
+        let comments: Vec<String> = serde_json::from_str(&email.payload).ok()?;
+        templates::comment_digest(&user.name, &comments, &settings.app_url, &unsubscribe)
+    } else {
+        let event = db::get_event_by_id(conn, email.event_id?, Uuid::nil())?;
+        let link = format!("{}?event_id={}", settings.app_url, event.id);
+        match email.kind.as_str() {
+            EMAIL_EVENT_ESTABLISHED => templates::event_established(
+                &user.name,
+                &event.name,
+                event.start_time,
+                &link,
+                &unsubscribe,
+            ),
+            EMAIL_EVENT_CANCELLED => {
+                templates::event_cancelled(&user.name, &event.name, &link, &unsubscribe)
+            }
+            EMAIL_EVENT_REMINDER => templates::event_reminder(
+                &user.name,
+                &event.name,
+                event.start_time,
+                &link,
+                &unsubscribe,
+            ),
+            _ => return None,
+        }
+    };
+
+    Some(Email {
+        to: user.email,
+        subject: rendered.subject,
+        html: rendered.html,
+        text: rendered.text,
+        unsubscribe_url: Some(unsubscribe),
+    })
+}
+
+pub fn send_due_emails(
+    conn: &mut PgConnection,
+    transport: &dyn EmailTransport,
+    settings: &EmailSettings,
+) -> Result<usize, Error> {
+    let due = db::get_due_emails(conn, BATCH_SIZE)?;
+
+    let mut sent = 0;
+    for outbox in due {
+        let email = match render(conn, &outbox, settings) {
+            Some(e) => e,
+            None => {
+                db::mark_email_failed(conn, outbox.id, "nothing to send", None)?;
+                continue;
+            }
+        };
+        match transport.send(&email) {
+            Ok(_) => {
+                db::mark_email_sent(conn, outbox.id)?;
+                sent += 1;
+            }
+            Err(e) => {
+                log::warn!("sending email {} failed: {}", outbox.id, e);
+                let next = retry_at(outbox.attempts, chrono::Utc::now().naive_utc());
+                db::mark_email_failed(conn, outbox.id, &e, next)?;
+            }
+        }
+    }
+
+    Ok(sent)
+}
@@ -0,0 +1,167 @@
+use chrono::NaiveDateTime;
+use rust_xlsxwriter::{Format, Workbook};
+
+use crate::models::{EventMember, EventWithMembers};
+use crate::money::Money;
+
+pub const FORMAT_CSV: &str = "csv";
+pub const FORMAT_XLSX: &str = "xlsx";
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+pub const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+const HEADERS: [&str; 8] = [
+    "Name",
+    "Email",
+    "Phone",
+    "Pledge",
+    "Currency",
+    "Payment status",
+    "Joined at",
+    "Checked in at",
+];
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub enum Cell {
+    Text(String),
+    Amount(Money),
+    Blank,
+}
+
+/// Spreadsheet apps run cells starting with these as formulas, so user
+/// supplied text (names, phones...) could smuggle one into the organizer's
+/// sheet. Prefixing a quote makes them plain text.
+pub fn escape_cell(value: &str) -> String {
+    match value.chars().next() {
+        Some('=') | Some('+') | Some('-') | Some('@') | Some('\t') | Some('\r') => {
+            format!("'{}", value)
+        }
+        _ => value.to_string(),
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    let value = escape_cell(value);
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn time_cell(time: Option<NaiveDateTime>) -> Cell {
+    match time {
+        Some(time) => Cell::Text(time.format(TIME_FORMAT).to_string()),
+        None => Cell::Blank,
+    }
+}
+
+fn member_row(member: &EventMember) -> Vec<Cell> {
+    vec![
+        Cell::Text(member.name.clone()),
+        Cell::Text(member.email.clone()),
+        Cell::Text(member.phone.clone()),
+        Cell::Amount(member.amount),
+        Cell::Text(member.amount.currency.code.to_string()),
+        Cell::Text(member.payment_status.clone()),
+        time_cell(member.joined_at),
+        time_cell(member.checked_in_at),
+    ]
+}
+
+fn total_row(label: &str, amount: Money) -> Vec<Cell> {
+    vec![
+        Cell::Text(label.to_string()),
+        Cell::Blank,
+        Cell::Blank,
+        Cell::Amount(amount),
+        Cell::Text(amount.currency.code.to_string()),
+    ]
+}
+
+/// Header, one row per member ordered by join time, then the totals. Paid
+/// and outstanding totals come from the payment summary when there is one.
+pub fn rows(event: &EventWithMembers) -> Vec<Vec<Cell>> {
+    let mut members: Vec<&EventMember> = event.members.iter().flatten().collect();
+    members.sort_by_key(|m| m.joined_at);
+
+    let mut rows: Vec<Vec<Cell>> = vec![HEADERS.iter().map(|h| Cell::Text(h.to_string())).collect()];
+    rows.extend(members.iter().map(|m| member_row(m)));
+    rows.push(Vec::new());
+    rows.push(vec![
+        Cell::Text("Members".to_string()),
+        Cell::Text(members.len().to_string()),
+    ]);
+    rows.push(total_row("Total pledged", event.amount));
+    if let Some(summary) = &event.payment_summary {
+        rows.push(total_row("Total paid", summary.paid_amount));
+        rows.push(total_row("Total refunded", summary.refunded_amount));
+        rows.push(total_row("Outstanding", summary.outstanding_amount));
+    }
+    rows
+}
+
+pub fn to_csv(rows: &[Vec<Cell>]) -> String {
+    // Excel only reads the file as UTF-8 with a BOM up front.
+    let mut out = String::from("\u{feff}");
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Text(text) => csv_field(text),
+                Cell::Amount(amount) => amount.to_decimal(),
+                Cell::Blank => String::new(),
+            })
+            .collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn amount_format(amount: &Money) -> Format {
+    match amount.currency.scale {
+        0 => Format::new().set_num_format("#,##0"),
+        scale => Format::new().set_num_format(format!("#,##0.{}", "0".repeat(scale as usize))),
+    }
+}
+
+pub fn to_xlsx(rows: &[Vec<Cell>]) -> Result<Vec<u8>, String> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Members").map_err(|e| e.to_string())?;
+    let bold = Format::new().set_bold();
+    for (r, row) in rows.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            let (r, c) = (r as u32, c as u16);
+            match cell {
+                Cell::Text(text) if r == 0 => sheet.write_string_with_format(r, c, text, &bold),
+                Cell::Text(text) => sheet.write_string(r, c, escape_cell(text)),
+                Cell::Amount(amount) => {
+                    let value = amount.minor as f64 / 10f64.powi(amount.currency.scale as i32);
+                    sheet.write_number_with_format(r, c, value, &amount_format(amount))
+                }
+                Cell::Blank => continue,
+            }
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    for c in 0..HEADERS.len() {
+        sheet.set_column_width(c as u16, 18).map_err(|e| e.to_string())?;
+    }
+    workbook.save_to_buffer().map_err(|e| e.to_string())
+}
+
+/// Event names are free text; keep the download name to plain ASCII so it
+/// fits in a header as-is.
+pub fn file_name(event_name: &str, format: &str) -> String {
+    let mut name: String = event_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(64)
+        .collect();
+    if name.trim_matches('_').is_empty() {
+        name = "event".to_string();
+    }
+    format!("{}-members.{}", name, format)
+}
@@ -0,0 +1,68 @@
+#[test]
+fn test_escape_cell() {
+    use crate::export::escape_cell;
+
+    assert_eq!(escape_cell("Alice"), "Alice");
+    assert_eq!(escape_cell("=HYPERLINK(\"http://evil\")"), "'=HYPERLINK(\"http://evil\")");
+    assert_eq!(escape_cell("+886912345678"), "'+886912345678");
+    assert_eq!(escape_cell("-1+1"), "'-1+1");
+    assert_eq!(escape_cell("@SUM(A1)"), "'@SUM(A1)");
+    assert_eq!(escape_cell("\t=1"), "'\t=1");
+    assert_eq!(escape_cell("\r=1"), "'\r=1");
+    assert_eq!(escape_cell("a=1"), "a=1");
+    assert_eq!(escape_cell(""), "");
+}
+
+#[test]
+fn test_csv_field() {
+    use crate::export::csv_field;
+
+    assert_eq!(csv_field("Alice"), "Alice");
+    assert_eq!(csv_field("Doe, Jane"), "\"Doe, Jane\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("=1,2"), "\"'=1,2\"");
+    assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+}
+
+#[test]
+fn test_to_csv() {
+    use crate::export::{to_csv, Cell};
+    use crate::money::Money;
+
+    let rows = vec![
+        vec![Cell::Text("Name".to_string()), Cell::Text("Pledge".to_string())],
+        vec![
+            Cell::Text("=cmd|' /C calc'!A0".to_string()),
+            Cell::Amount(Money::from_db(14950, "TWD")),
+        ],
+        Vec::new(),
+        vec![Cell::Text("Total pledged".to_string()), Cell::Blank, Cell::Amount(Money::from_db(14950, "TWD"))],
+    ];
+    assert_eq!(
+        to_csv(&rows),
+        "\u{feff}Name,Pledge\r\n'=cmd|' /C calc'!A0,149.50\r\n\r\nTotal pledged,,149.50\r\n"
+    );
+}
+
+#[test]
+fn test_to_xlsx() {
+    use crate::export::{to_xlsx, Cell};
+    use crate::money::Money;
+
+    let rows = vec![
+        vec![Cell::Text("Name".to_string()), Cell::Text("Pledge".to_string())],
+        vec![Cell::Text("@evil".to_string()), Cell::Amount(Money::from_db(100, "TWD"))],
+    ];
+    let xlsx = to_xlsx(&rows).unwrap();
+    // An xlsx file is a zip archive.
+    assert_eq!(&xlsx[..4], b"PK\x03\x04");
+}
+
+#[test]
+fn test_file_name() {
+    use crate::export::file_name;
+
+    assert_eq!(file_name("Board games night", "csv"), "Board_games_night-members.csv");
+    assert_eq!(file_name("../../etc", "xlsx"), "______etc-members.xlsx");
+    assert_eq!(file_name("桌遊之夜", "csv"), "event-members.csv");
+}
@@ -0,0 +1,94 @@
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+/// Derived from the same radius as `haversine_km` so the bounding box
+/// never cuts off points the exact check would accept.
+const KM_PER_DEGREE_LAT: f64 = EARTH_RADIUS_KM * std::f64::consts::PI / 180.0;
+
+pub const MAX_RADIUS_KM: f64 = 500.0;
+
+/// Parses `near=lat,lng` from the events listing.
+pub fn parse_near(near: &str) -> Result<(f64, f64), String> {
+    let (lat, lng) = match near.split_once(',') {
+        Some(p) => p,
+        None => return Err("near should be formatted as lat,lng".to_string()),
+    };
+    let lat = lat.trim().parse::<f64>().map_err(|_| format!("Invalid latitude {}", lat))?;
+    let lng = lng.trim().parse::<f64>().map_err(|_| format!("Invalid longitude {}", lng))?;
+    coordinates_check(Some(lat), Some(lng))?;
+    Ok((lat, lng))
+}
+
+/// Coordinates are optional, but must be given together and be in range.
+pub fn coordinates_check(latitude: Option<f64>, longitude: Option<f64>) -> Result<(), String> {
+    match (latitude, longitude) {
+        (None, None) => Ok(()),
+        (Some(lat), Some(lng)) => {
+            if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+                return Err(format!("Latitude {} is out of range", lat));
+            }
+            if !lng.is_finite() || !(-180.0..=180.0).contains(&lng) {
+                return Err(format!("Longitude {} is out of range", lng));
+            }
+            Ok(())
+        }
+        _ => Err("Latitude and longitude should be set together".to_string()),
+    }
+}
+
+/// Great-circle distance in kilometres.
+pub fn haversine_km(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lng = (lng2 - lng1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Latitude/longitude bounds that contain every point within `radius_km`.
+/// Used as an index-friendly prefilter before the exact haversine check.
+#[derive(Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    /// When the box crosses the antimeridian `min_lng > max_lng`, meaning
+    /// the longitude range wraps around.
+    pub min_lng: f64,
+    pub max_lng: f64,
+}
+
+impl BoundingBox {
+    pub fn around(lat: f64, lng: f64, radius_km: f64) -> BoundingBox {
+        let d_lat = radius_km / KM_PER_DEGREE_LAT;
+        let min_lat = (lat - d_lat).max(-90.0);
+        let max_lat = (lat + d_lat).min(90.0);
+
+        // Near the poles the circle covers every longitude.
+        let widest = lat.abs().max(min_lat.abs()).max(max_lat.abs());
+        if widest >= 89.0 {
+            return BoundingBox { min_lat, max_lat, min_lng: -180.0, max_lng: 180.0 };
+        }
+        let d_lng = radius_km / (KM_PER_DEGREE_LAT * widest.to_radians().cos());
+        if d_lng >= 180.0 {
+            return BoundingBox { min_lat, max_lat, min_lng: -180.0, max_lng: 180.0 };
+        }
+
+        let wrap = |v: f64| {
+            if v < -180.0 {
+                v + 360.0
+            } else if v > 180.0 {
+                v - 360.0
+            } else {
+                v
+            }
+        };
+        BoundingBox {
+            min_lat,
+            max_lat,
+            min_lng: wrap(lng - d_lng),
+            max_lng: wrap(lng + d_lng),
+        }
+    }
+
+    pub fn wraps(&self) -> bool {
+        self.min_lng > self.max_lng
+    }
+}
@@ -0,0 +1,58 @@
+#[test]
+fn test_parse_near_and_coordinates_check() {
+    use crate::geo::{coordinates_check, parse_near};
+
+    assert_eq!(parse_near("25.034, 121.5645"), Ok((25.034, 121.5645)));
+    assert!(parse_near("25.034").is_err());
+    assert!(parse_near("abc,121").is_err());
+    assert!(parse_near("91,121").is_err());
+    assert!(parse_near("25,181").is_err());
+
+    assert!(coordinates_check(None, None).is_ok());
+    assert!(coordinates_check(Some(25.0), Some(121.0)).is_ok());
+    assert!(coordinates_check(Some(25.0), None).is_err());
+    assert!(coordinates_check(Some(f64::NAN), Some(121.0)).is_err());
+}
+
+#[test]
+fn test_haversine_km() {
+    use crate::geo::haversine_km;
+
+    // Taipei 101 to Taipei Main Station.
+    let d = haversine_km(25.0340, 121.5645, 25.0478, 121.5170);
+    assert!((d - 5.03).abs() < 0.01, "got {}", d);
+    // Taipei 101 to Kaohsiung Main Station.
+    let d = haversine_km(25.0340, 121.5645, 22.6273, 120.3014);
+    assert!((d - 296.85).abs() < 0.1, "got {}", d);
+    assert_eq!(haversine_km(25.0, 121.0, 25.0, 121.0), 0.0);
+    // Across the antimeridian.
+    let d = haversine_km(0.0, 179.9, 0.0, -179.9);
+    assert!((d - 22.24).abs() < 0.01, "got {}", d);
+}
+
+#[test]
+fn test_bounding_box() {
+    use crate::geo::{haversine_km, BoundingBox};
+
+    let bbox = BoundingBox::around(25.0340, 121.5645, 10.0);
+    assert!(!bbox.wraps());
+    assert!(bbox.min_lat < 25.0340 && bbox.max_lat > 25.0340);
+    // Every point on the circle must be inside the box.
+    for bearing in 0..360 {
+        let b = (bearing as f64).to_radians();
+        let lat = 25.0340 + 0.0899 * b.cos();
+        let lng = 121.5645 + 0.0899 * b.sin() / 25.0340f64.to_radians().cos();
+        if haversine_km(25.0340, 121.5645, lat, lng) <= 10.0 {
+            assert!(lat >= bbox.min_lat && lat <= bbox.max_lat);
+            assert!(lng >= bbox.min_lng && lng <= bbox.max_lng);
+        }
+    }
+
+    let wrapped = BoundingBox::around(0.0, 179.95, 50.0);
+    assert!(wrapped.wraps());
+    assert!(wrapped.min_lng > 179.0 && wrapped.max_lng < -179.0);
+
+    let polar = BoundingBox::around(89.5, 10.0, 100.0);
+    assert_eq!((polar.min_lng, polar.max_lng), (-180.0, 180.0));
+    assert_eq!(polar.max_lat, 90.0);
+}
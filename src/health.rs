@@ -0,0 +1,92 @@
+use diesel::prelude::*;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::models::{ComponentHealth, HealthReport};
+use crate::PgPool;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_FAIL: &str = "fail";
+/// Upper bound for each dependency check, well below typical probe
+/// timeouts.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn component(started: Instant, result: Result<(), String>) -> ComponentHealth {
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    match result {
+        Ok(()) => ComponentHealth {
+            status: STATUS_OK.to_string(),
+            latency_ms,
+            error: None,
+        },
+        Err(e) => ComponentHealth {
+            status: STATUS_FAIL.to_string(),
+            latency_ms,
+            error: Some(e),
+        },
+    }
+}
+
+pub fn failed(error: &str) -> ComponentHealth {
+    ComponentHealth {
+        status: STATUS_FAIL.to_string(),
+        latency_ms: 0.0,
+        error: Some(error.to_string()),
+    }
+}
+
+/// Runs `SELECT 1` over a pooled connection, then looks for migrations in
+/// `migrations/` that the database hasn't run yet. Blocking.
+pub fn check_database(pool: &PgPool) -> (ComponentHealth, ComponentHealth) {
+    let started = Instant::now();
+    let mut conn = match pool.get_timeout(CHECK_TIMEOUT) {
+        Ok(conn) => conn,
+        Err(e) => {
+            let error = format!("no connection: {}", e);
+            return (
+                component(started, Err(error.clone())),
+                component(Instant::now(), Err(error)),
+            );
+        }
+    };
+    let database = component(
+        started,
+        diesel::sql_query("SELECT 1")
+            .execute(&mut conn)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    );
+
+    let started = Instant::now();
+    let pending = match conn.pending_migrations(MIGRATIONS) {
+        Ok(pending) if pending.is_empty() => Ok(()),
+        Ok(pending) => Err(format!("{} pending", pending.len())),
+        Err(e) => Err(e.to_string()),
+    };
+    (database, component(started, pending))
+}
+
+pub async fn check_redis(client: &redis::Client) -> ComponentHealth {
+    let started = Instant::now();
+    let ping = async {
+        let mut conn = client.get_async_connection().await?;
+        redis::cmd("PING").query_async::<_, String>(&mut conn).await
+    };
+    let result = match actix_web::rt::time::timeout(CHECK_TIMEOUT, ping).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    };
+    component(started, result)
+}
+
+/// Overall status is ok only if every component is.
+pub fn report(components: BTreeMap<String, ComponentHealth>) -> HealthReport {
+    let healthy = components.values().all(|c| c.status == STATUS_OK);
+    HealthReport {
+        status: if healthy { STATUS_OK } else { STATUS_FAIL }.to_string(),
+        components,
+    }
+}
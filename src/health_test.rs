@@ -0,0 +1,67 @@
+#[test]
+fn test_report() {
+    use crate::health::{report, STATUS_FAIL, STATUS_OK};
+    use crate::models::ComponentHealth;
+    use std::collections::BTreeMap;
+
+    let ok = || ComponentHealth {
+        status: STATUS_OK.to_string(),
+        latency_ms: 1.5,
+        error: None,
+    };
+    assert_eq!(report(BTreeMap::new()).status, STATUS_OK);
+
+    let mut components = BTreeMap::new();
+    components.insert("database".to_string(), ok());
+    components.insert("redis".to_string(), ok());
+    assert_eq!(report(components).status, STATUS_OK);
+
+    let mut components = BTreeMap::new();
+    components.insert("database".to_string(), ok());
+    components.insert(
+        "redis".to_string(),
+        ComponentHealth {
+            status: STATUS_FAIL.to_string(),
+            latency_ms: 2000.0,
+            error: Some("timed out".to_string()),
+        },
+    );
+    let report = report(components);
+    assert_eq!(report.status, STATUS_FAIL);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["components"]["redis"]["error"], "timed out");
+    assert!(json["components"]["database"].get("error").is_none());
+}
+
+#[actix_web::test]
+async fn test_check_redis_unreachable() {
+    use crate::health::{check_redis, STATUS_FAIL};
+
+    // Nothing listens on the discard port.
+    let client = redis::Client::open("redis://127.0.0.1:9/").unwrap();
+    let health = check_redis(&client).await;
+    assert_eq!(health.status, STATUS_FAIL);
+    assert!(health.error.is_some());
+}
+
+#[test]
+fn test_check_database() {
+    use crate::health::{check_database, STATUS_OK};
+    use diesel::pg::PgConnection;
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use dotenvy;
+    use std::env;
+
+    dotenvy::from_filename(".env.test").ok();
+
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(ConnectionManager::<PgConnection>::new(
+            env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
+        ))
+        .unwrap();
+    let (database, migrations) = check_database(&pool);
+    assert_eq!(database.status, STATUS_OK);
+    assert_eq!(migrations.status, STATUS_OK);
+}
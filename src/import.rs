@@ -0,0 +1,437 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+use crate::models::{EventLocation, NewEvent};
+use crate::money::Money;
+
+pub const FORMAT_CSV: &str = "csv";
+pub const FORMAT_ICS: &str = "ics";
+pub const MAX_IMPORT_BYTES: usize = 1024 * 1024;
+pub const MAX_IMPORT_ROWS: usize = 500;
+
+const CSV_COLUMNS: [&str; 13] = [
+    "name",
+    "description",
+    "category",
+    "start_time",
+    "end_time",
+    "min_amount",
+    "max_amount",
+    "currency",
+    "address",
+    "venue",
+    "latitude",
+    "longitude",
+    "tags",
+];
+const REQUIRED_CSV_COLUMNS: [&str; 3] = ["name", "start_time", "end_time"];
+
+/// Used where the file doesn't say: every .ics event, and CSV rows with an
+/// empty category or currency.
+pub struct ImportDefaults {
+    pub category: Option<String>,
+    pub currency: String,
+}
+
+/// One event read from the file. `row` is the CSV line or the position of
+/// the VEVENT, so errors can be traced back to the file.
+pub struct ImportedRow {
+    pub row: usize,
+    pub name: String,
+    pub event: Result<(NewEvent, Vec<String>), String>,
+}
+
+/// By extension first, then the declared content type, then the content.
+pub fn detect_format(file_name: &str, content_type: Option<&str>, data: &str) -> Option<&'static str> {
+    let file_name = file_name.to_ascii_lowercase();
+    if file_name.ends_with(".csv") {
+        return Some(FORMAT_CSV);
+    }
+    if file_name.ends_with(".ics") || file_name.ends_with(".ical") {
+        return Some(FORMAT_ICS);
+    }
+    match content_type {
+        Some("text/csv") => return Some(FORMAT_CSV),
+        Some("text/calendar") => return Some(FORMAT_ICS),
+        _ => {}
+    }
+    let data = data.trim_start_matches('\u{feff}').trim_start();
+    if data.to_ascii_uppercase().starts_with("BEGIN:VCALENDAR") {
+        Some(FORMAT_ICS)
+    } else if !data.is_empty() {
+        Some(FORMAT_CSV)
+    } else {
+        None
+    }
+}
+
+/// RFC 3339, stored like the unix seconds the JSON API takes, or a plain
+/// date time which is taken as written, e.g. "2026-10-19 19:00".
+pub fn parse_time(value: &str) -> Result<NaiveDateTime, String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.naive_utc());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time);
+        }
+    }
+    Err(format!("Invalid time {}", value))
+}
+
+fn parse_amount(value: &str, currency: &str) -> Result<i64, String> {
+    if value.is_empty() {
+        return Ok(0);
+    }
+    Money::parse(value, currency)
+        .map(|m| m.minor)
+        .map_err(|e| e.to_string())
+}
+
+fn parse_coordinate(value: &str, column: &str) -> Result<Option<f64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<f64>()
+        .map(Some)
+        .map_err(|_| format!("Invalid {} {}", column, value))
+}
+
+fn split_tags(value: &str) -> Vec<String> {
+    value
+        .split([',', ';'])
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// CSV with a header row naming `NewEvent` fields, plus `tags`. Amounts are
+/// in major units ("149.5"), as people type them in a spreadsheet, and tags
+/// are separated by commas or semicolons.
+pub fn parse_csv(data: &str, defaults: &ImportDefaults) -> Result<Vec<ImportedRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.to_ascii_lowercase())
+        .collect();
+    for header in &headers {
+        if !CSV_COLUMNS.contains(&header.as_str()) {
+            return Err(format!("Unknown column {}", header));
+        }
+    }
+    for column in REQUIRED_CSV_COLUMNS {
+        if !headers.iter().any(|h| h == column) {
+            return Err(format!("Missing column {}", column));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        if rows.len() == MAX_IMPORT_ROWS {
+            return Err(format!("At most {} events can be imported at once", MAX_IMPORT_ROWS));
+        }
+        let row = record.position().map(|p| p.line() as usize).unwrap_or(rows.len() + 2);
+        let values: HashMap<&str, &str> = headers
+            .iter()
+            .map(|h| h.as_str())
+            .zip(record.iter())
+            .collect();
+        let name = values.get("name").copied().unwrap_or("").to_string();
+        rows.push(ImportedRow {
+            row,
+            name,
+            event: csv_event(&values, defaults),
+        });
+    }
+    Ok(rows)
+}
+
+fn csv_event(
+    values: &HashMap<&str, &str>,
+    defaults: &ImportDefaults,
+) -> Result<(NewEvent, Vec<String>), String> {
+    let field = |column: &str| values.get(column).copied().unwrap_or("");
+    if field("name").is_empty() {
+        return Err("Missing name".to_string());
+    }
+    let category = match non_empty(field("category")).or(defaults.category.clone()) {
+        Some(category) => category,
+        None => return Err("Missing category".to_string()),
+    };
+    let currency = non_empty(field("currency")).unwrap_or(defaults.currency.clone());
+    let event = NewEvent {
+        name: field("name").to_string(),
+        description: field("description").to_string(),
+        category,
+        start_time: parse_time(field("start_time"))?,
+        end_time: parse_time(field("end_time"))?,
+        min_amount: parse_amount(field("min_amount"), &currency)?,
+        max_amount: parse_amount(field("max_amount"), &currency)?,
+        user_id: Default::default(),
+        currency,
+        location: EventLocation {
+            address: non_empty(field("address")),
+            venue: non_empty(field("venue")),
+            latitude: parse_coordinate(field("latitude"), "latitude")?,
+            longitude: parse_coordinate(field("longitude"), "longitude")?,
+        },
+    };
+    Ok((event, split_tags(field("tags"))))
+}
+
+/// Joins folded lines back; a line starting with a space or tab continues
+/// the previous one (RFC 5545 3.1).
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+pub fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Splits "NAME;PARAM=x:value"; the first colon outside quotes ends the
+/// parameters.
+fn split_property(line: &str) -> Option<(String, String, &str)> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => {
+                let (head, value) = (&line[..i], &line[i + 1..]);
+                let (name, params) = head.split_once(';').unwrap_or((head, ""));
+                return Some((name.to_ascii_uppercase(), params.to_ascii_uppercase(), value));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// DATE values are all-day and start at midnight. UTC times ("...Z") are
+/// stored like the unix seconds the JSON API takes; floating and TZID
+/// times are taken as written.
+pub fn parse_ics_time(params: &str, value: &str) -> Result<(NaiveDateTime, bool), String> {
+    let value = value.trim();
+    let date_only = params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME");
+    if date_only || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|t| (t, true))
+            .ok_or_else(|| format!("Invalid time {}", value));
+    }
+    NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .map(|t| (t, false))
+        .map_err(|_| format!("Invalid time {}", value))
+}
+
+/// Keeps `Duration` constructors, which panic on overflow, in range.
+const MAX_DURATION_PART: i64 = 1_000_000;
+
+/// "P1DT2H30M", "PT90M", "P2W"; signed durations are not accepted.
+pub fn parse_ics_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration {}", value);
+    let rest = value.trim().strip_prefix('P').ok_or_else(invalid)?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            _ => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                if n > MAX_DURATION_PART {
+                    return Err(invalid());
+                }
+                number.clear();
+                let part = match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return Err(invalid()),
+                };
+                total = total.checked_add(&part).ok_or_else(invalid)?;
+            }
+        }
+    }
+    if !number.is_empty() || rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+#[derive(Default)]
+struct VEvent {
+    summary: String,
+    description: String,
+    start: Option<Result<(NaiveDateTime, bool), String>>,
+    end: Option<Result<(NaiveDateTime, bool), String>>,
+    duration: Option<String>,
+    location: Option<String>,
+    geo: Option<String>,
+    categories: Vec<String>,
+    recurring: bool,
+    cancelled: bool,
+}
+
+impl VEvent {
+    fn into_event(self, defaults: &ImportDefaults) -> Result<(NewEvent, Vec<String>), String> {
+        if self.cancelled {
+            return Err("Cancelled events are not imported".to_string());
+        }
+        if self.recurring {
+            return Err("Recurring events are not supported".to_string());
+        }
+        if self.summary.is_empty() {
+            return Err("Missing SUMMARY".to_string());
+        }
+        let category = defaults.category.clone().ok_or("Missing category")?;
+        let (start_time, all_day) = self.start.ok_or("Missing DTSTART")??;
+        // Without DTEND or DURATION an event lasts a day if it's all-day,
+        // otherwise it ends when it starts (RFC 5545 3.6.1).
+        let end_time = match (self.end, self.duration) {
+            (Some(end), _) => end?.0,
+            (None, Some(duration)) => start_time
+                .checked_add_signed(parse_ics_duration(&duration)?)
+                .ok_or_else(|| format!("Invalid duration {}", duration))?,
+            (None, None) if all_day => start_time
+                .checked_add_signed(Duration::days(1))
+                .ok_or("Invalid DTSTART")?,
+            (None, None) => start_time,
+        };
+        let (latitude, longitude) = match self.geo {
+            Some(geo) => {
+                let (lat, lon) = match geo.split_once(';') {
+                    Some(pair) => pair,
+                    None => return Err(format!("Invalid GEO {}", geo)),
+                };
+                (
+                    parse_coordinate(lat.trim(), "latitude")?,
+                    parse_coordinate(lon.trim(), "longitude")?,
+                )
+            }
+            None => (None, None),
+        };
+        let event = NewEvent {
+            name: self.summary,
+            description: self.description,
+            category,
+            start_time,
+            end_time,
+            min_amount: 0,
+            max_amount: 0,
+            user_id: Default::default(),
+            currency: defaults.currency.clone(),
+            location: EventLocation {
+                address: self.location,
+                venue: None,
+                latitude,
+                longitude,
+            },
+        };
+        Ok((event, self.categories))
+    }
+}
+
+/// Each VEVENT becomes an event with no pledge range; CATEGORIES become
+/// tags and the category comes from `defaults`. Alarms and other nested
+/// components are skipped.
+pub fn parse_ics(data: &str, defaults: &ImportDefaults) -> Result<Vec<ImportedRow>, String> {
+    let lines = unfold(data.trim_start_matches('\u{feff}'));
+    if !lines
+        .iter()
+        .any(|l| l.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("Missing BEGIN:VCALENDAR".to_string());
+    }
+
+    let mut rows = Vec::new();
+    let mut current: Option<VEvent> = None;
+    let mut nested = 0;
+    for line in &lines {
+        let (name, params, value) = match split_property(line) {
+            Some(property) => property,
+            None => continue,
+        };
+        let upper = value.trim().to_ascii_uppercase();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if upper == "VEVENT" => {
+                if rows.len() == MAX_IMPORT_ROWS {
+                    return Err(format!("At most {} events can be imported at once", MAX_IMPORT_ROWS));
+                }
+                current = Some(VEvent::default());
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if upper == "VEVENT" => {
+                let event = current.take().unwrap();
+                let name = event.summary.clone();
+                rows.push(ImportedRow {
+                    row: rows.len() + 1,
+                    name,
+                    event: event.into_event(defaults),
+                });
+            }
+            (_, Some(_)) if nested > 0 => {}
+            ("SUMMARY", Some(event)) => event.summary = unescape_text(value).trim().to_string(),
+            ("DESCRIPTION", Some(event)) => event.description = unescape_text(value),
+            ("DTSTART", Some(event)) => event.start = Some(parse_ics_time(&params, value)),
+            ("DTEND", Some(event)) => event.end = Some(parse_ics_time(&params, value)),
+            ("DURATION", Some(event)) => event.duration = Some(value.to_string()),
+            ("LOCATION", Some(event)) => event.location = non_empty(unescape_text(value).trim()),
+            ("GEO", Some(event)) => event.geo = Some(value.to_string()),
+            ("CATEGORIES", Some(event)) => event.categories.extend(
+                value
+                    .split(',')
+                    .map(|c| unescape_text(c).trim().to_string())
+                    .filter(|c| !c.is_empty()),
+            ),
+            ("RRULE", Some(event)) | ("RDATE", Some(event)) => event.recurring = true,
+            ("STATUS", Some(event)) => event.cancelled = upper == "CANCELLED",
+            _ => {}
+        }
+    }
+    if current.is_some() {
+        return Err("Missing END:VEVENT".to_string());
+    }
+    Ok(rows)
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+fn defaults() -> crate::import::ImportDefaults {
+    crate::import::ImportDefaults {
+        category: Some("test_event".to_string()),
+        currency: "TWD".to_string(),
+    }
+}
+
+#[test]
+fn test_detect_format() {
+    use crate::import::{detect_format, FORMAT_CSV, FORMAT_ICS};
+
+    assert_eq!(detect_format("events.CSV", None, "BEGIN:VCALENDAR"), Some(FORMAT_CSV));
+    assert_eq!(detect_format("cal.ics", None, ""), Some(FORMAT_ICS));
+    assert_eq!(detect_format("upload", Some("text/calendar"), "name"), Some(FORMAT_ICS));
+    assert_eq!(detect_format("", None, "\u{feff}begin:vcalendar\r\n"), Some(FORMAT_ICS));
+    assert_eq!(detect_format("", None, "name,start_time"), Some(FORMAT_CSV));
+    assert_eq!(detect_format("", None, "  "), None);
+}
+
+#[test]
+fn test_parse_time() {
+    use crate::import::parse_time;
+    use chrono::NaiveDate;
+
+    let expected = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(19, 0, 0).unwrap();
+    assert_eq!(parse_time("2026-10-19 19:00"), Ok(expected));
+    assert_eq!(parse_time("2026-10-19T19:00:00"), Ok(expected));
+    assert_eq!(parse_time("2026-10-20T03:00:00+08:00"), Ok(expected));
+    assert_eq!(parse_time("19/10/2026"), Err("Invalid time 19/10/2026".to_string()));
+}
+
+#[test]
+fn test_parse_csv() {
+    use crate::import::parse_csv;
+
+    let csv = "\u{feff}Name,category,start_time,end_time,min_amount,max_amount,tags,latitude,longitude\n\
+               Board games,,2026-10-19 19:00,2026-10-19 22:00,149.5,300,\"games, night\",25.03,121.56\n\
+               ,,2026-10-19 19:00,2026-10-19 22:00,,,,,\n\
+               Hike,outdoor,2026-10-20 08:00,2026-10-20 12:00,1.005,,,,\n";
+    let rows = parse_csv(csv, &defaults()).unwrap();
+    assert_eq!(rows.len(), 3);
+
+    assert_eq!(rows[0].row, 2);
+    let (event, tags) = rows[0].event.as_ref().unwrap();
+    assert_eq!(event.name, "Board games");
+    assert_eq!(event.category, "test_event");
+    assert_eq!(event.currency, "TWD");
+    assert_eq!(event.min_amount, 14950);
+    assert_eq!(event.max_amount, 30000);
+    assert_eq!(event.location.latitude, Some(25.03));
+    assert_eq!(*tags, ["games", "night"]);
+
+    assert_eq!(rows[1].event.as_ref().err(), Some(&"Missing name".to_string()));
+    assert_eq!(rows[2].row, 4);
+    assert_eq!(rows[2].name, "Hike");
+    assert!(rows[2].event.is_err());
+}
+
+#[test]
+fn test_parse_csv_columns() {
+    use crate::import::parse_csv;
+
+    assert_eq!(
+        parse_csv("name,start_time,end_time,price\n", &defaults()).err(),
+        Some("Unknown column price".to_string())
+    );
+    assert_eq!(
+        parse_csv("name,start_time\n", &defaults()).err(),
+        Some("Missing column end_time".to_string())
+    );
+}
+
+#[test]
+fn test_parse_ics_duration() {
+    use crate::import::parse_ics_duration;
+    use chrono::Duration;
+
+    assert_eq!(parse_ics_duration("PT1H30M"), Ok(Duration::minutes(90)));
+    assert_eq!(parse_ics_duration("P1DT2H"), Ok(Duration::hours(26)));
+    assert_eq!(parse_ics_duration("P2W"), Ok(Duration::weeks(2)));
+    assert!(parse_ics_duration("P").is_err());
+    assert!(parse_ics_duration("PT1D").is_err());
+    assert!(parse_ics_duration("-PT1H").is_err());
+    assert!(parse_ics_duration("P99999999999999999D").is_err());
+}
+
+#[test]
+fn test_parse_ics() {
+    use crate::import::parse_ics;
+    use chrono::NaiveDate;
+
+    let ics = "BEGIN:VCALENDAR\r\n\
+               VERSION:2.0\r\n\
+               BEGIN:VEVENT\r\n\
+               SUMMARY:Board games\\, night\r\n\
+               DESCRIPTION:Bring snacks\\nand friends\r\n\
+               DTSTART;TZID=\"Asia/Taipei\":20261019T190000\r\n\
+               DURATION:PT3H\r\n\
+               LOCATION:Taipei\r\n\
+               GEO:25.03;121.56\r\n\
+               CATEGORIES:games,ni\r\n \
+               ght\r\n\
+               BEGIN:VALARM\r\n\
+               DESCRIPTION:Reminder\r\n\
+               END:VALARM\r\n\
+               END:VEVENT\r\n\
+               BEGIN:VEVENT\r\n\
+               SUMMARY:Holiday\r\n\
+               DTSTART;VALUE=DATE:20261025\r\n\
+               END:VEVENT\r\n\
+               BEGIN:VEVENT\r\n\
+               SUMMARY:Weekly\r\n\
+               DTSTART:20261019T110000Z\r\n\
+               RRULE:FREQ=WEEKLY\r\n\
+               END:VEVENT\r\n\
+               END:VCALENDAR\r\n";
+    let rows = parse_ics(ics, &defaults()).unwrap();
+    assert_eq!(rows.len(), 3);
+
+    let (event, tags) = rows[0].event.as_ref().unwrap();
+    let start = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(19, 0, 0).unwrap();
+    assert_eq!(event.name, "Board games, night");
+    assert_eq!(event.description, "Bring snacks\nand friends");
+    assert_eq!(event.start_time, start);
+    assert_eq!(event.end_time, NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(22, 0, 0).unwrap());
+    assert_eq!(event.location.address, Some("Taipei".to_string()));
+    assert_eq!(event.location.longitude, Some(121.56));
+    assert_eq!(event.category, "test_event");
+    assert_eq!((event.min_amount, event.max_amount), (0, 0));
+    assert_eq!(*tags, ["games", "night"]);
+
+    let (event, _) = rows[1].event.as_ref().unwrap();
+    assert_eq!(event.end_time - event.start_time, chrono::Duration::days(1));
+
+    assert_eq!(rows[2].row, 3);
+    assert_eq!(rows[2].event.as_ref().err(), Some(&"Recurring events are not supported".to_string()));
+
+    assert!(parse_ics("BEGIN:VEVENT\r\nEND:VEVENT\r\n", &defaults()).is_err());
+    assert!(parse_ics("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\n", &defaults()).is_err());
+}
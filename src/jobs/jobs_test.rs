@@ -0,0 +1,210 @@
+#[test]
+fn test_cron_parse() {
+    use crate::jobs::schedule::Cron;
+
+    assert!(Cron::parse("* * * * *").is_ok());
+    assert!(Cron::parse("*/5 0-6,22 1,15 * 1-5").is_ok());
+    assert!(Cron::parse("0 0 * * 7").is_ok());
+    assert!(Cron::parse("* * * *").is_err());
+    assert!(Cron::parse("60 * * * *").is_err());
+    assert!(Cron::parse("* 24 * * *").is_err());
+    assert!(Cron::parse("* * 0 * *").is_err());
+    assert!(Cron::parse("*/0 * * * *").is_err());
+    assert!(Cron::parse("5-1 * * * *").is_err());
+    assert!(Cron::parse("a * * * *").is_err());
+}
+
+#[test]
+fn test_cron_next_after() {
+    use crate::jobs::schedule::Cron;
+    use chrono::NaiveDate;
+
+    let at = |y, mo, d, h, mi| {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    };
+    let now = NaiveDate::from_ymd_opt(2026, 10, 19)
+        .unwrap()
+        .and_hms_opt(10, 7, 30)
+        .unwrap();
+
+    let every_minute = Cron::parse("* * * * *").unwrap();
+    assert_eq!(every_minute.next_after(now), Some(at(2026, 10, 19, 10, 8)));
+
+    let every_five = Cron::parse("*/5 * * * *").unwrap();
+    assert_eq!(every_five.next_after(now), Some(at(2026, 10, 19, 10, 10)));
+    assert_eq!(
+        every_five.next_after(at(2026, 10, 19, 10, 10)),
+        Some(at(2026, 10, 19, 10, 15))
+    );
+
+    let hourly = Cron::parse("0 * * * *").unwrap();
+    assert_eq!(hourly.next_after(now), Some(at(2026, 10, 19, 11, 0)));
+
+    let nightly = Cron::parse("30 3 * * *").unwrap();
+    assert_eq!(nightly.next_after(now), Some(at(2026, 10, 20, 3, 30)));
+
+    // 2026-10-19 is a Monday, so the next Sunday is the 25th.
+    let sundays = Cron::parse("0 9 * * 0").unwrap();
+    assert_eq!(sundays.next_after(now), Some(at(2026, 10, 25, 9, 0)));
+    assert_eq!(Cron::parse("0 9 * * 7").unwrap(), sundays);
+
+    // Either day field may match when both are given.
+    let first_or_friday = Cron::parse("0 0 1 * 5").unwrap();
+    assert_eq!(first_or_friday.next_after(now), Some(at(2026, 10, 23, 0, 0)));
+
+    let leap_day = Cron::parse("0 0 29 2 *").unwrap();
+    assert_eq!(leap_day.next_after(now), Some(at(2028, 2, 29, 0, 0)));
+
+    let never = Cron::parse("0 0 31 2 *").unwrap();
+    assert_eq!(never.next_after(now), None);
+}
+
+#[test]
+fn test_built_in_schedules_parse() {
+    use crate::jobs::schedule::Cron;
+    use crate::jobs::SCHEDULES;
+
+    for (name, expr) in SCHEDULES {
+        assert!(Cron::parse(expr).is_ok(), "schedule {} is invalid", name);
+    }
+}
+
+#[test]
+fn test_job_retry_backoff() {
+    use crate::jobs::retry_at;
+    use chrono::Duration;
+
+    let now = chrono::Utc::now().naive_utc();
+    assert_eq!(retry_at(1, 5, now), Some(now + Duration::seconds(60)));
+    assert_eq!(retry_at(3, 5, now), Some(now + Duration::seconds(240)));
+    assert_eq!(retry_at(5, 5, now), None);
+}
+
+#[test]
+fn test_refund_payment_job() {
+    use crate::db::{create_category, create_event, create_event_member, create_payment, get_category};
+    use crate::db::{delete_event, get_or_create_user, get_payments_by_event_and_user, get_refundable_payments};
+    use crate::email::{CaptureTransport, EmailSettings};
+    use crate::jobs::{run_job, JobContext};
+    use crate::models::{Job, NewCategory, NewEvent, NewEventMember, NewPayment, JOB_REFUND_PAYMENT};
+    use crate::payment::fake::FakeProvider;
+    use chrono::*;
+    use diesel::pg::PgConnection;
+    use diesel::Connection;
+    use dotenvy;
+    use std::env;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    dotenvy::from_filename(".env.test").ok();
+    let mut conn =
+        PgConnection::establish(&env::var("DATABASE_URL").expect("DATABASE_URL must be set"))
+            .unwrap();
+    let user = get_or_create_user(
+        &mut conn,
+        "test_refund_payment_job".to_string(),
+        "test_user".to_string(),
+        "a".to_string(),
+        "a".to_string(),
+    );
+    if get_category(&mut conn, "test_event").unwrap().is_none() {
+        create_category(&mut conn, NewCategory {
+            slug: "test_event".to_string(),
+            name: "Test".to_string(),
+            icon: String::new(),
+            sort_order: 0,
+            parent: None,
+        }).ok();
+    }
+    let start = Utc::now().naive_utc() + Duration::days(1);
+    let event = create_event(&mut conn, NewEvent {
+        name: "test_refund_payment_job".to_string(),
+        description: "test_refund_payment_job".to_string(),
+        category: "test_event".to_string(),
+        start_time: start,
+        end_time: start,
+        user_id: user.id,
+        max_amount: 100,
+        min_amount: 1,
+        currency: "TWD".to_string(),
+        location: Default::default(),
+    });
+    create_event_member(&mut conn, NewEventMember {
+        event_id: event.id,
+        user_id: user.id,
+        amount: 50,
+    }).unwrap();
+    let payment = create_payment(&mut conn, NewPayment {
+        event_id: event.id,
+        user_id: user.id,
+        amount: 50,
+        kind: "payment".to_string(),
+        method: "fake".to_string(),
+        reference: "fake_pi_test".to_string(),
+        status: "confirmed".to_string(),
+        paid_at: None,
+        confirmed_at: None,
+        provider_ref: Some("fake_cs_test".to_string()),
+        refund_of: None,
+    }).unwrap();
+
+    let ctx = JobContext {
+        email_transport: Arc::new(CaptureTransport::default()),
+        email: Arc::new(EmailSettings {
+            public_url: "http://localhost:8080".to_string(),
+            app_url: "http://localhost:3000".to_string(),
+            secret: b"secret".to_vec(),
+        }),
+        payment_provider: Arc::new(FakeProvider::new("secret".to_string())),
+    };
+    let now = Utc::now().naive_utc();
+    let job = |payload: String| Job {
+        id: Uuid::new_v4(),
+        kind: JOB_REFUND_PAYMENT.to_string(),
+        payload,
+        status: "running".to_string(),
+        attempts: 0,
+        max_attempts: 5,
+        run_at: now,
+        locked_by: None,
+        locked_at: None,
+        last_error: None,
+        created_at: now,
+        finished_at: None,
+    };
+
+    assert!(run_job(&mut conn, &ctx, &job("not-a-uuid".to_string())).is_err());
+    assert!(run_job(&mut conn, &ctx, &job(Uuid::new_v4().to_string())).is_err());
+    assert!(run_job(&mut conn, &ctx, &job(payment.id.to_string())).is_ok());
+    // A second run finds the refund and leaves the payment alone.
+    assert!(run_job(&mut conn, &ctx, &job(payment.id.to_string())).is_ok());
+    let refundable = get_refundable_payments(&mut conn, event.id, "fake");
+
+    let refunds: Vec<_> = get_payments_by_event_and_user(&mut conn, event.id, user.id)
+        .unwrap()
+        .into_iter()
+        .filter(|p| p.kind == "refund")
+        .collect();
+    delete_event(&mut conn, event.id);
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].amount, 50);
+    assert_eq!(refunds[0].status, "confirmed");
+    assert_eq!(refunds[0].refund_of, Some(payment.id));
+    assert!(refundable.is_empty());
+}
+
+#[test]
+fn test_panic_message() {
+    use crate::jobs::panic_message;
+    use std::panic::catch_unwind;
+
+    let panic = catch_unwind(|| panic!("static message")).unwrap_err();
+    assert_eq!(panic_message(panic.as_ref()), "static message");
+    let panic = catch_unwind(|| panic!("user {} not found", 42)).unwrap_err();
+    assert_eq!(panic_message(panic.as_ref()), "user 42 not found");
+    let panic = catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+    assert_eq!(panic_message(panic.as_ref()), "unknown panic");
+}
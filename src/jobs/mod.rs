@@ -0,0 +1,283 @@
+pub mod schedule;
+
+mod jobs_test;
+
+use chrono::{Duration as ChronoDuration, NaiveDateTime};
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error;
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db;
+use crate::email::worker::{enqueue_comment_digests, enqueue_event_reminders, send_due_emails};
+use crate::email::{EmailSettings, EmailTransport};
+use crate::jobs::schedule::Cron;
+use crate::models::{
+    Job, JobSchedule, NewJob, JOB_COMMENT_DIGESTS, JOB_EVENT_REMINDERS, JOB_EXPIRE_EVENTS,
+    JOB_PURGE_DELETED, JOB_REFUND_PAYMENT, JOB_SEND_EMAILS, JOB_SEND_WEBHOOKS, PAYMENT_KIND_PAYMENT,
+    PAYMENT_STATUS_CONFIRMED,
+};
+use crate::payment::{self, PaymentProvider, RefundError};
+use crate::webhooks;
+use crate::PgPool;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 10;
+/// A running job whose lock is older than this is assumed to belong to a
+/// replica that died and is handed to another worker.
+const LOCK_TIMEOUT_MINUTES: i64 = 15;
+const RETRY_BASE_SECONDS: i64 = 30;
+const DELETED_RETENTION_DAYS: i64 = 30;
+const FINISHED_JOB_RETENTION_DAYS: i64 = 7;
+
+/// Recurring jobs; the schedule name doubles as the job kind.
+pub const SCHEDULES: &[(&str, &str)] = &[
+    (JOB_SEND_EMAILS, "* * * * *"),
+    (JOB_SEND_WEBHOOKS, "* * * * *"),
+    (JOB_EVENT_REMINDERS, "*/5 * * * *"),
+    (JOB_COMMENT_DIGESTS, "0 * * * *"),
+    (JOB_EXPIRE_EVENTS, "*/15 * * * *"),
+    (JOB_PURGE_DELETED, "30 3 * * *"),
+];
+
+/// What job handlers need besides a connection.
+pub struct JobContext {
+    pub email_transport: Arc<dyn EmailTransport>,
+    pub email: Arc<EmailSettings>,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+}
+
+/// Backoff after the `attempts`-th failed run, or `None` once the job has
+/// used them all up and should be dead-lettered.
+pub fn retry_at(attempts: i32, max_attempts: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if attempts >= max_attempts {
+        return None;
+    }
+    Some(now + ChronoDuration::seconds(RETRY_BASE_SECONDS << attempts.min(16)))
+}
+
+pub fn run_job(conn: &mut PgConnection, ctx: &JobContext, job: &Job) -> Result<(), String> {
+    let done = match job.kind.as_str() {
+        JOB_SEND_EMAILS => send_due_emails(conn, ctx.email_transport.as_ref(), &ctx.email),
+        JOB_SEND_WEBHOOKS => webhooks::send_due_deliveries(conn),
+        JOB_EVENT_REMINDERS => enqueue_event_reminders(conn),
+        JOB_COMMENT_DIGESTS => enqueue_comment_digests(conn),
+        JOB_EXPIRE_EVENTS => db::expire_past_events(conn, chrono::Local::now().naive_local()),
+        JOB_PURGE_DELETED => purge_deleted(conn),
+        JOB_REFUND_PAYMENT => return retry_refund(conn, ctx, &job.payload),
+        kind => return Err(format!("Unknown job kind {}", kind)),
+    };
+
+    match done {
+        Ok(count) => {
+            if count > 0 {
+                log::info!("job {} ({}) processed {} rows", job.id, job.kind, count);
+            }
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Retries a refund the provider turned down when the event was cancelled.
+fn retry_refund(conn: &mut PgConnection, ctx: &JobContext, payload: &str) -> Result<(), String> {
+    let payment_id = Uuid::parse_str(payload).map_err(|e| format!("Invalid payment id {}: {}", payload, e))?;
+    let payment = db::get_payment_by_id(conn, payment_id).ok_or(format!("Payment {} not found", payment_id))?;
+    if payment.kind != PAYMENT_KIND_PAYMENT
+        || payment.status != PAYMENT_STATUS_CONFIRMED
+        || payment.method != ctx.payment_provider.name()
+        || payment.provider_ref.is_none()
+    {
+        return Err(format!("Payment {} can't be refunded", payment_id));
+    }
+    // An earlier attempt may have gone through after the job was queued.
+    if db::get_refund_by_payment_id(conn, payment_id).is_some() {
+        log::info!("payment {} is already refunded", payment_id);
+        return Ok(());
+    }
+
+    let refund = payment::refund_payment(conn, ctx.payment_provider.as_ref(), &payment);
+    match actix_web::rt::System::new().block_on(refund) {
+        Ok(_) => {
+            log::info!("refunded payment {}", payment_id);
+            Ok(())
+        }
+        Err(RefundError::Provider(e)) => Err(e.to_string()),
+        // Running the job again would refund twice, so it counts as done.
+        Err(e @ RefundError::NotRecorded(_)) => {
+            log::error!("refund for payment {}: {}", payment_id, e);
+            Ok(())
+        }
+    }
+}
+
+fn purge_deleted(conn: &mut PgConnection) -> Result<usize, Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let events = db::purge_deleted_events(conn, now - ChronoDuration::days(DELETED_RETENTION_DAYS))?;
+    let jobs = db::purge_finished_jobs(conn, now - ChronoDuration::days(FINISHED_JOB_RETENTION_DAYS))?;
+    Ok(events + jobs)
+}
+
+/// Makes sure every entry in `SCHEDULES` has a row. Safe to call from
+/// every replica on startup.
+pub fn register_schedules(conn: &mut PgConnection) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    for (name, expr) in SCHEDULES {
+        let cron = Cron::parse(expr).expect("Invalid built-in job schedule");
+        db::upsert_job_schedule(
+            conn,
+            JobSchedule {
+                name: name.to_string(),
+                cron: expr.to_string(),
+                next_run_at: cron.next_after(now).unwrap_or(now),
+                last_run_at: None,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Enqueues a job for every due schedule and moves it to its next slot.
+/// Skipped if a job of the same kind is still waiting, so an outage
+/// doesn't leave a backlog of identical runs.
+pub fn enqueue_scheduled(conn: &mut PgConnection) -> Result<usize, Error> {
+    let now = chrono::Utc::now().naive_utc();
+    conn.transaction::<usize, Error, _>(|conn| {
+        let due = db::lock_due_job_schedules(conn, now)?;
+        let mut queued = 0;
+        for schedule in due {
+            let next = match Cron::parse(&schedule.cron) {
+                Ok(cron) => cron.next_after(now),
+                Err(e) => {
+                    log::error!("job schedule {} is invalid: {}", schedule.name, e);
+                    None
+                }
+            };
+            if !db::has_pending_job(conn, &schedule.name)? {
+                db::enqueue_job(
+                    conn,
+                    NewJob {
+                        kind: schedule.name.clone(),
+                        payload: "".to_string(),
+                        run_at: now,
+                    },
+                )?;
+                queued += 1;
+            }
+            // An unusable expression is parked a day out instead of firing
+            // every poll.
+            let next = next.unwrap_or(now + ChronoDuration::days(1));
+            db::advance_job_schedule(conn, &schedule.name, now, next)?;
+        }
+        Ok(queued)
+    })
+}
+
+/// Claims and runs one batch. Returns how many jobs were claimed.
+pub fn run_due_jobs(conn: &mut PgConnection, ctx: &JobContext, worker: &str) -> Result<usize, Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let jobs = db::claim_jobs(
+        conn,
+        worker,
+        BATCH_SIZE,
+        now - ChronoDuration::minutes(LOCK_TIMEOUT_MINUTES),
+    )?;
+
+    for job in jobs.iter() {
+        let result = match catch_unwind(AssertUnwindSafe(|| run_job(conn, ctx, job))) {
+            Ok(result) => result,
+            Err(panic) => {
+                rollback_open_transactions(conn)?;
+                Err(format!("job panicked: {}", panic_message(panic.as_ref())))
+            }
+        };
+        match result {
+            Ok(_) => {
+                db::mark_job_succeeded(conn, job.id)?;
+            }
+            Err(e) => {
+                let retry = retry_at(job.attempts, job.max_attempts, chrono::Utc::now().naive_utc());
+                if retry.is_none() {
+                    log::error!("job {} ({}) dead-lettered: {}", job.id, job.kind, e);
+                } else {
+                    log::warn!("job {} ({}) failed: {}", job.id, job.kind, e);
+                }
+                db::mark_job_failed(conn, job.id, &e, retry)?;
+            }
+        }
+    }
+
+    Ok(jobs.len())
+}
+
+/// Text of a caught panic, kept as the job's last error.
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// A panic unwinding out of `conn.transaction` leaves the transaction open.
+fn rollback_open_transactions(conn: &mut PgConnection) -> Result<(), Error> {
+    while AnsiTransactionManager::transaction_manager_status_mut(conn)
+        .transaction_depth()?
+        .is_some()
+    {
+        AnsiTransactionManager::rollback_transaction(conn)?;
+    }
+    Ok(())
+}
+
+fn worker_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or("worker".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+/// Runs the scheduler and job worker on a background thread. Every replica
+/// can run one; claiming goes through `FOR UPDATE SKIP LOCKED`. `context`
+/// is called on that thread, since reqwest's blocking client panics when
+/// built inside the async runtime.
+pub fn start<F>(pool: PgPool, context: F)
+where
+    F: FnOnce() -> JobContext + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let ctx = context();
+        let worker = worker_id();
+        let mut registered = false;
+        loop {
+            let mut claimed = 0;
+            match pool.get() {
+                Ok(mut conn) => {
+                    if !registered {
+                        match register_schedules(&mut conn) {
+                            Ok(_) => registered = true,
+                            Err(e) => log::error!("registering job schedules failed: {}", e),
+                        }
+                    }
+                    if let Err(e) = enqueue_scheduled(&mut conn) {
+                        log::error!("enqueueing scheduled jobs failed: {}", e);
+                    }
+                    match run_due_jobs(&mut conn, &ctx, &worker) {
+                        Ok(n) => claimed = n,
+                        Err(e) => log::error!("running jobs failed: {}", e),
+                    }
+                }
+                Err(e) => log::error!("job worker couldn't get db connection: {}", e),
+            }
+            // Keep draining while there is a backlog.
+            if claimed < BATCH_SIZE as usize {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    });
+}
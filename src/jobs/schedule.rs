@@ -0,0 +1,115 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// A five-field cron expression (`minute hour day-of-month month
+/// day-of-week`), evaluated in UTC. Each field accepts `*`, numbers, ranges
+/// `a-b`, steps `*/n` or `a-b/n`, and comma separated lists of those.
+/// Sunday is 0 (7 is accepted too).
+#[derive(Debug, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Upper bound on how far `next_after` searches; any valid expression
+/// fires at least once within a few years (Feb 29).
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits: u64 = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => match s.parse::<u32>() {
+                Ok(s) if s > 0 => (r, s),
+                _ => return Err(format!("Invalid step in {}", part)),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            let a = a.parse::<u32>().map_err(|_| format!("Invalid value in {}", part))?;
+            let b = b.parse::<u32>().map_err(|_| format!("Invalid value in {}", part))?;
+            (a, b)
+        } else {
+            let a = range.parse::<u32>().map_err(|_| format!("Invalid value in {}", part))?;
+            // "5/15" means every 15 starting at 5.
+            if part.contains('/') { (a, max) } else { (a, a) }
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{} is out of range {}-{}", part, min, max));
+        }
+        let mut v = start;
+        while v <= end {
+            bits |= 1 << v;
+            v += step;
+        }
+    }
+    Ok(bits)
+}
+
+fn has(bits: u64, v: u32) -> bool {
+    bits & (1 << v) != 0
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Expected 5 fields in \"{}\"", expr));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Cron {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Like classic cron, when both day fields are restricted a day matches
+    /// if either of them does.
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// The first matching minute strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = after.date();
+        let mut from = after.time().hour() * 60 + after.time().minute() + 1;
+        let last = date + Duration::days(SEARCH_LIMIT_DAYS);
+
+        while date <= last {
+            if has(self.months, date.month()) && self.day_matches(date) {
+                for minute_of_day in from..24 * 60 {
+                    let (h, m) = (minute_of_day / 60, minute_of_day % 60);
+                    if has(self.hours, h) && has(self.minutes, m) {
+                        return Some(date.and_time(NaiveTime::from_hms_opt(h, m, 0)?));
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+            from = 0;
+        }
+
+        None
+    }
+}
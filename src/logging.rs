@@ -0,0 +1,236 @@
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_TYPE, REFERER, USER_AGENT};
+use actix_web::{Error, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use opentelemetry_sdk::trace::Tracer;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::metrics::UNMATCHED_ROUTE;
+use crate::telemetry;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Probes and scrapes that would drown out everything else.
+pub const QUIET_PATHS: [&str; 4] = ["/ping", "/metrics", "/health/live", "/health/ready"];
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+static SHOW_USER_IDS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Option<LogFormat> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Installs the global subscriber. `RUST_LOG` filters as before, and lines
+/// from the `log` macros are forwarded, so they carry the request span too.
+/// With a tracer, spans are exported as well.
+pub fn init(format: LogFormat, show_user_ids: bool, tracer: Option<Tracer>) {
+    SHOW_USER_IDS.store(show_user_ids, Ordering::Relaxed);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (json, text) = match format {
+        LogFormat::Json => (Some(tracing_subscriber::fmt::layer().json()), None),
+        LogFormat::Text => (None, Some(tracing_subscriber::fmt::layer())),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json)
+        .with(text)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .init();
+}
+
+/// How a user id appears in logs: a stable pseudonym unless `LOG_USER_IDS`
+/// is on, so lines of one user can still be correlated.
+pub fn redact_user(user_id: Uuid) -> String {
+    if SHOW_USER_IDS.load(Ordering::Relaxed) {
+        return user_id.to_string();
+    }
+    let digest = Sha256::digest(user_id.as_bytes());
+    format!("user-{}", hex::encode(&digest[..6]))
+}
+
+/// The caller's id if it is short and plain enough to log, otherwise a
+/// fresh one.
+pub fn request_id(header: Option<&HeaderValue>) -> String {
+    header
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        })
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Adds `request_id` to a JSON object body; anything else is left alone.
+pub fn with_request_id(body: &[u8], request_id: &str) -> Option<Vec<u8>> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    value
+        .as_object_mut()?
+        .insert("request_id".to_string(), serde_json::Value::from(request_id));
+    serde_json::to_vec(&value).ok()
+}
+
+/// The id of the current request, for handlers that need to pass it on.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Middleware that tags each request with an `X-Request-Id`, runs it inside
+/// a span carrying that id, writes the access log line, and echoes the id
+/// in the response header and in JSON error bodies.
+pub struct RequestLogging;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestLogging
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RequestLoggingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestLoggingMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestLoggingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestLoggingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let request_id = request_id(req.headers().get(REQUEST_ID_HEADER));
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let method = req.method().to_string();
+        let quiet = QUIET_PATHS.contains(&req.path());
+        let remote = req
+            .connection_info()
+            .realip_remote_addr()
+            .unwrap_or("-")
+            .to_string();
+        let header = |name: HeaderName| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("-")
+                .to_string()
+        };
+        let referer = header(REFERER);
+        let user_agent = header(USER_AGENT);
+        // The pattern, not the path, which can hold user ids.
+        let route = req.match_pattern().unwrap_or(UNMATCHED_ROUTE.to_string());
+        let span = tracing::info_span!(
+            "request",
+            otel.name = %format!("{} {}", method, route),
+            otel.kind = "server",
+            request_id = %request_id,
+            method = %method,
+            http.route = %route,
+            http.status_code = tracing::field::Empty,
+        );
+        span.set_parent(telemetry::remote_context(req.headers()));
+        let service = self.service.clone();
+
+        Box::pin(
+            async move {
+                let res = match service.call(req).await {
+                    Ok(res) => res.map_into_boxed_body(),
+                    Err(e) => {
+                        tracing::error!("{} {} failed: {}", method, route, e);
+                        return Err(e);
+                    }
+                };
+                let status = res.status();
+                tracing::Span::current().record("http.status_code", u64::from(status.as_u16()));
+                // Echo the id handlers saw, read back from the request.
+                let request_id = res
+                    .request()
+                    .extensions()
+                    .get::<RequestId>()
+                    .map(|id| id.0.clone())
+                    .unwrap_or(request_id);
+                let is_json = res
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.starts_with("application/json"))
+                    .unwrap_or(false);
+
+                let mut res = if (status.is_client_error() || status.is_server_error()) && is_json {
+                    let (http_req, http_res) = res.into_parts();
+                    let (http_res, body) = http_res.into_parts();
+                    let body = match to_bytes(body).await {
+                        Ok(body) => body,
+                        Err(e) => return Err(actix_web::error::ErrorInternalServerError(e.to_string())),
+                    };
+                    let body = with_request_id(&body, &request_id).unwrap_or_else(|| body.to_vec());
+                    ServiceResponse::new(http_req, http_res.set_body(BoxBody::new(body)))
+                } else {
+                    res
+                };
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+
+                if !quiet {
+                    tracing::info!(
+                        remote = %remote,
+                        status = status.as_u16(),
+                        latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+                        referer = %referer,
+                        user_agent = %user_agent,
+                        "{} {} {}",
+                        method,
+                        route,
+                        status.as_u16(),
+                    );
+                }
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,134 @@
+#[test]
+fn test_request_id() {
+    use crate::logging::request_id;
+    use actix_web::http::header::HeaderValue;
+
+    assert_eq!(request_id(Some(&HeaderValue::from_static("abc-123_x.y:z"))), "abc-123_x.y:z");
+    // Anything unsafe to log is replaced rather than trusted.
+    for header in ["", "has space", "quote\"", &"a".repeat(129)] {
+        let id = request_id(Some(&HeaderValue::from_str(header).unwrap()));
+        assert_ne!(id, header);
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+    }
+    assert!(uuid::Uuid::parse_str(&request_id(None)).is_ok());
+}
+
+#[test]
+fn test_with_request_id() {
+    use crate::logging::with_request_id;
+
+    let body = with_request_id(br#"{"message":"Forbidden","error_code":"403"}"#, "req-1").unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(value["request_id"], "req-1");
+    assert_eq!(value["error_code"], "403");
+
+    assert!(with_request_id(b"[1, 2]", "req-1").is_none());
+    assert!(with_request_id(b"not json", "req-1").is_none());
+}
+
+#[test]
+fn test_redact_user() {
+    use crate::logging::redact_user;
+    use uuid::Uuid;
+
+    let user_id = Uuid::new_v4();
+    let redacted = redact_user(user_id);
+    assert!(!redacted.contains(&user_id.to_string()));
+    assert_eq!(redacted, redact_user(user_id));
+    assert_ne!(redacted, redact_user(Uuid::new_v4()));
+}
+
+#[actix_web::test]
+async fn test_request_logging() {
+    use crate::logging::{RequestId, RequestLogging, REQUEST_ID_HEADER};
+    use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestLogging)
+            .route(
+                "/ok",
+                web::get().to(|req: HttpRequest| async move {
+                    let id = req.extensions().get::<RequestId>().unwrap().0.clone();
+                    HttpResponse::Ok().body(id)
+                }),
+            )
+            .route(
+                "/fail",
+                web::get().to(|| async {
+                    HttpResponse::Forbidden().json(serde_json::json!({
+                        "message": "Forbidden",
+                        "error_code": "403",
+                    }))
+                }),
+            ),
+    )
+    .await;
+
+    // Propagated from the caller, and visible to handlers.
+    let req = test::TestRequest::get()
+        .uri("/ok")
+        .insert_header((REQUEST_ID_HEADER, "from-upstream"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "from-upstream");
+    assert_eq!(test::read_body(res).await, "from-upstream");
+
+    // Generated, and echoed in the error body.
+    let req = test::TestRequest::get().uri("/fail").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status().as_u16(), 403);
+    let header = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["request_id"], header.as_str());
+    assert_eq!(body["message"], "Forbidden");
+}
+
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[actix_web::test]
+async fn test_request_logging_omits_user_ids() {
+    use crate::logging::RequestLogging;
+    use actix_web::{test, web, App, HttpResponse};
+    use tracing_subscriber::layer::SubscriberExt;
+    use uuid::Uuid;
+
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::registry().with(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || writer.clone()),
+    );
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestLogging)
+            .route("/users/{user_id}/events", web::get().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+    let user_id = Uuid::new_v4();
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/events", user_id))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("GET /users/{user_id}/events 200"), "{}", logs);
+    assert!(!logs.contains(&user_id.to_string()), "{}", logs);
+}
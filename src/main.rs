@@ -1,19 +1,76 @@
 mod api;
+mod categories;
+mod checkin;
+mod config;
+mod cors;
+mod csrf;
 mod db;
+mod email;
+mod export;
+mod geo;
+mod health;
+mod import;
+mod jobs;
+mod logging;
+mod metrics;
 mod models;
+mod money;
+mod notify;
+mod payment;
+mod ratelimit;
 mod schema;
+mod search;
+mod signing;
+mod storage;
+mod tags;
+mod telemetry;
+mod templates;
+mod uploads;
+mod webhooks;
 
+mod categories_test;
+mod checkin_test;
+mod config_test;
+mod cors_test;
+mod csrf_test;
 mod db_test;
+mod export_test;
+mod geo_test;
+mod health_test;
+mod import_test;
+mod logging_test;
+mod metrics_test;
+mod money_test;
+mod ratelimit_test;
+mod search_test;
+mod tags_test;
+mod telemetry_test;
+mod templates_test;
+mod uploads_test;
+mod webhooks_test;
 
 use actix_cors::Cors;
+use actix_session::config::BrowserSession;
 use actix_session::{storage::RedisSessionStore, SessionMiddleware};
+use actix_web::cookie::time::Duration as CookieDuration;
 use actix_web::cookie::Key;
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::{web, App, HttpServer};
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use dotenvy::dotenv;
-use env_logger::Env;
-use std::env;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::cors::CorsSettings;
+use crate::csrf::Csrf;
+use crate::email::{EmailSettings, EmailTransport};
+use crate::logging::RequestLogging;
+use crate::metrics::{MeteredSessionStore, Metrics, RequestMetrics};
+use crate::payment::PaymentProvider;
+use crate::ratelimit::{MemoryStore, RateLimit, RateLimitStore, RateLimits, RedisStore};
+use crate::storage::Storage;
+use crate::telemetry::{TraceSettings, TracedConnections};
+use crate::uploads::UploadSettings;
 
 pub type PgPool = Pool<ConnectionManager<PgConnection>>;
 pub type PgPooledConnection = PooledConnection<ConnectionManager<PgConnection>>;
@@ -22,72 +79,142 @@ pub struct MyData {
     pool: PgPool,
     google_client_id: String,
     redirect_url: String,
-    cors_enabled: bool,
+    cors: Option<Arc<CorsSettings>>,
+    payment_provider: Arc<dyn PaymentProvider>,
+    email: Arc<EmailSettings>,
+    storage: Arc<dyn Storage>,
+    uploads: Arc<UploadSettings>,
+    metrics: Arc<Metrics>,
+    redis: redis::Client,
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    let database_url: String = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let google_client_id: String = env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set");
-    let redis_url: String = env::var("REDIS_URL").expect("REDIS_URL must be set");
-    let secret_key: String = env::var("SECRET_KEY").expect("SECRET_KEY must be set");
-    let stage: String = env::var("STAGE").unwrap_or("dev".to_string());
-    let secret_key: Key = Key::from(secret_key.as_bytes());
-    let redirect_url: String = env::var("REDIRECT_URL").expect("REDIRECT_URL must be set");
-    let cors_enabled: bool = env::var("CORS").unwrap_or("false".to_string()) == "true";
+    let config: Config = match Config::load() {
+        Ok(config) => config,
+        Err(errors) => {
+            eprintln!("Invalid configuration:");
+            for error in errors {
+                eprintln!("  - {}", error);
+            }
+            std::process::exit(1);
+        }
+    };
+    // Traces are only exported when a collector is configured.
+    let tracer = config.otel_exporter_otlp_endpoint.clone().map(|endpoint| {
+        telemetry::init_propagator();
+        let settings: TraceSettings = TraceSettings {
+            endpoint,
+            sample_ratio: config.otel_traces_sampler_arg,
+        };
+        telemetry::otlp_tracer(&settings).expect("Failed to create OTLP exporter")
+    });
+    logging::init(config.log_format, config.log_user_ids, tracer);
+    let google_client_id: String = config.google_client_id.clone();
+    let email_settings: Arc<EmailSettings> = Arc::new(EmailSettings {
+        public_url: config.public_url.clone(),
+        app_url: config.redirect_url.clone(),
+        secret: config.secret_key.as_bytes().to_vec(),
+    });
+    let upload_settings: Arc<UploadSettings> = Arc::new(UploadSettings {
+        public_url: email_settings.public_url.clone(),
+        secret: config.secret_key.as_bytes().to_vec(),
+    });
+    let secret_key: Key = Key::from(config.secret_key.as_bytes());
+    let redirect_url: String = config.redirect_url.clone();
+    let cors_settings: Option<Arc<CorsSettings>> = config.cors_settings().map(Arc::new);
+    let session_ttl: CookieDuration = CookieDuration::seconds(config.session_ttl_secs);
+    let payment_provider: Arc<dyn PaymentProvider> = payment::provider_from_config(&config);
+    let storage: Arc<dyn Storage> = storage::storage_from_config(&config);
+    let metrics: Arc<Metrics> = Arc::new(Metrics::new());
     let pool: PgPool = Pool::builder()
-        .build(ConnectionManager::<PgConnection>::new(database_url))
+        .max_size(config.pool_size)
+        .event_handler(Box::new(metrics.pool_events()))
+        .connection_customizer(Box::new(TracedConnections))
+        .build(ConnectionManager::<PgConnection>::new(config.database_url.clone()))
         .expect("Failed to create pool.");
 
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
-    let session_store: RedisSessionStore = RedisSessionStore::new(redis_url).await.unwrap();
-    let addr: &str = if stage == "dev" {
-        "127.0.0.1"
-    } else {
-        "0.0.0.0"
+    let email_transport: Arc<dyn EmailTransport> = match email::transport_from_config(&config) {
+        Ok(transport) => transport,
+        Err(e) => {
+            eprintln!("Failed to set up EMAIL_TRANSPORT {}: {}", config.email_transport, e);
+            std::process::exit(1);
+        }
+    };
+    let job_email: Arc<EmailSettings> = email_settings.clone();
+    let job_payment_provider: Arc<dyn PaymentProvider> = payment_provider.clone();
+    jobs::start(pool.clone(), move || jobs::JobContext {
+        email_transport,
+        email: job_email,
+        payment_provider: job_payment_provider,
+    });
+    // The URL was validated with the rest of the config.
+    let redis: redis::Client = redis::Client::open(config.redis_url.as_str()).expect("REDIS_URL must be a valid URL");
+    let rate_limit_store: Arc<dyn RateLimitStore> = match RedisStore::new(redis.clone()).await {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            log::warn!("rate limits are per instance, Redis is unavailable: {}", e);
+            Arc::new(MemoryStore::default())
+        }
+    };
+    let rate_limits: RateLimits = config.rate_limits();
+    let trust_proxy: bool = config.rate_limit_trust_proxy;
+    let redis_sessions: RedisSessionStore = match RedisSessionStore::new(config.redis_url.clone()).await {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Failed to connect the session store to Redis: {}", e);
+            std::process::exit(1);
+        }
     };
+    let session_store: MeteredSessionStore<RedisSessionStore> =
+        MeteredSessionStore::new(redis_sessions, metrics.clone());
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let cors: Cors;
         let same_site: actix_web::cookie::SameSite;
-        if cors_enabled {
-            cors = Cors::default()
-                .supports_credentials()
-                .allow_any_header()
-                .allow_any_method()
-                .allowed_origin_fn(
-                    |_origin: &actix_web::http::header::HeaderValue,
-                     _req_head: &actix_web::dev::RequestHead| true,
-                )
-                .max_age(3600);
+        if let Some(cors_settings) = &cors_settings {
+            cors = cors_settings.cors();
             same_site = actix_web::cookie::SameSite::None;
         } else {
             cors = Cors::default();
             same_site = actix_web::cookie::SameSite::Lax;
         }
-        let logger: Logger =
-            Logger::new("%{r}a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T")
-                .exclude("/ping");
-
         App::new()
             .app_data(web::Data::new(MyData {
                 pool: pool.clone(),
                 google_client_id: google_client_id.clone(),
                 redirect_url: redirect_url.clone(),
-                cors_enabled: cors_enabled,
+                cors: cors_settings.clone(),
+                payment_provider: payment_provider.clone(),
+                email: email_settings.clone(),
+                storage: storage.clone(),
+                uploads: upload_settings.clone(),
+                metrics: metrics.clone(),
+                redis: redis.clone(),
             }))
+            .wrap(Csrf)
+            .wrap(RateLimit::new(rate_limit_store.clone(), rate_limits.clone(), trust_proxy))
             .wrap(
                 SessionMiddleware::builder(session_store.clone(), secret_key.clone())
                     .cookie_name("session".to_string())
                     .cookie_same_site(same_site)
+                    .session_lifecycle(BrowserSession::default().state_ttl(session_ttl))
                     .build(),
             )
-            .wrap(logger)
+            .wrap(RequestLogging)
+            .wrap(RequestMetrics::new(metrics.clone()))
             .wrap(cors)
             .configure(api::init)
     })
-    .bind((addr, 8080))?
+    .bind((config.bind_host(), config.port))?
     .run()
-    .await
+    .await;
+
+    // Flushing blocks until the batch exporter, which runs on this runtime,
+    // has sent what it holds.
+    actix_web::rt::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider)
+        .await
+        .ok();
+    server
 }
@@ -0,0 +1,303 @@
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration as CookieDuration;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use diesel::r2d2::event::{CheckoutEvent, HandleEvent, TimeoutEvent};
+use futures_util::future::LocalBoxFuture;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+use crate::models::EventGauges;
+
+/// Label for requests that matched no route, so scanners probing random
+/// paths can't blow up the number of series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pool_wait: Histogram,
+    pool_timeouts: IntCounter,
+    session_errors: IntCounterVec,
+    open_events: IntGauge,
+    established_events: IntGauge,
+    joins_per_minute: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let pool_wait = Histogram::with_opts(HistogramOpts::new(
+            "db_pool_wait_seconds",
+            "Time spent waiting for a database connection",
+        ))
+        .unwrap();
+        let pool_timeouts = IntCounter::new(
+            "db_pool_timeouts_total",
+            "Database connection checkouts that timed out",
+        )
+        .unwrap();
+        let session_errors = IntCounterVec::new(
+            Opts::new("session_store_errors_total", "Failed session store operations"),
+            &["operation"],
+        )
+        .unwrap();
+        let open_events = IntGauge::new("events_open", "Events that are not cancelled, expired or over").unwrap();
+        let established_events =
+            IntGauge::new("events_established", "Open events that reached their minimum amount").unwrap();
+        let joins_per_minute = IntGauge::new("event_joins_per_minute", "Members who joined in the last minute").unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(request_duration.clone())).unwrap();
+        registry.register(Box::new(pool_connections.clone())).unwrap();
+        registry.register(Box::new(pool_wait.clone())).unwrap();
+        registry.register(Box::new(pool_timeouts.clone())).unwrap();
+        registry.register(Box::new(session_errors.clone())).unwrap();
+        registry.register(Box::new(open_events.clone())).unwrap();
+        registry.register(Box::new(established_events.clone())).unwrap();
+        registry.register(Box::new(joins_per_minute.clone())).unwrap();
+
+        Metrics {
+            registry,
+            requests,
+            request_duration,
+            pool_connections,
+            pool_wait,
+            pool_timeouts,
+            session_errors,
+            open_events,
+            established_events,
+            joins_per_minute,
+        }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.requests.with_label_values(&labels).inc();
+        self.request_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_pool_state(&self, connections: u32, idle: u32) {
+        self.pool_connections
+            .with_label_values(&["idle"])
+            .set(idle as i64);
+        self.pool_connections
+            .with_label_values(&["in_use"])
+            .set(connections.saturating_sub(idle) as i64);
+    }
+
+    pub fn set_event_gauges(&self, gauges: &EventGauges) {
+        self.open_events.set(gauges.open);
+        self.established_events.set(gauges.established);
+        self.joins_per_minute.set(gauges.joined_last_minute);
+    }
+
+    pub fn session_error(&self, operation: &str) {
+        self.session_errors.with_label_values(&[operation]).inc();
+    }
+
+    /// Hooks for `Pool::builder().event_handler(...)`.
+    pub fn pool_events(&self) -> PoolEvents {
+        PoolEvents {
+            wait: self.pool_wait.clone(),
+            timeouts: self.pool_timeouts.clone(),
+        }
+    }
+
+    /// Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+pub struct PoolEvents {
+    wait: Histogram,
+    timeouts: IntCounter,
+}
+
+impl fmt::Debug for PoolEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PoolEvents")
+    }
+}
+
+impl HandleEvent for PoolEvents {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        self.wait.observe(event.duration().as_secs_f64());
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.timeouts.inc();
+    }
+}
+
+/// Middleware counting and timing every request by its route pattern,
+/// e.g. `/api/v1/events/{event_id}`, rather than the raw path.
+pub struct RequestMetrics {
+    metrics: Arc<Metrics>,
+}
+
+impl RequestMetrics {
+    pub fn new(metrics: Arc<Metrics>) -> RequestMetrics {
+        RequestMetrics { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware {
+            service: Rc::new(service),
+            metrics: self.metrics.clone(),
+        }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+    metrics: Arc<Metrics>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let route = req.match_pattern().unwrap_or(UNMATCHED_ROUTE.to_string());
+        let service = self.service.clone();
+        let metrics = self.metrics.clone();
+
+        Box::pin(async move {
+            let result = service.call(req).await;
+            let status = match &result {
+                Ok(res) => res.status().as_u16(),
+                Err(e) => e.as_response_error().status_code().as_u16(),
+            };
+            metrics.observe_request(&method, &route, status, start.elapsed());
+            result
+        })
+    }
+}
+
+/// Session store wrapper counting failed operations, which otherwise only
+/// show up as 500s.
+#[derive(Clone)]
+pub struct MeteredSessionStore<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S> MeteredSessionStore<S> {
+    pub fn new(inner: S, metrics: Arc<Metrics>) -> MeteredSessionStore<S> {
+        MeteredSessionStore { inner, metrics }
+    }
+
+    fn span(operation: &str) -> tracing::Span {
+        tracing::info_span!("session", otel.kind = "client", db.system = "redis", db.operation = operation)
+    }
+
+    fn track<T, E>(&self, operation: &str, result: Result<T, E>) -> Result<T, E> {
+        if result.is_err() {
+            self.metrics.session_error(operation);
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<S: SessionStore> SessionStore for MeteredSessionStore<S> {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<HashMap<String, String>>, LoadError> {
+        let result = self.inner.load(session_key).instrument(Self::span("load")).await;
+        self.track("load", result)
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &CookieDuration,
+    ) -> Result<SessionKey, SaveError> {
+        let result = self.inner.save(session_state, ttl).instrument(Self::span("save")).await;
+        self.track("save", result)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: HashMap<String, String>,
+        ttl: &CookieDuration,
+    ) -> Result<SessionKey, UpdateError> {
+        let result = self
+            .inner
+            .update(session_key, session_state, ttl)
+            .instrument(Self::span("update"))
+            .await;
+        self.track("update", result)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &CookieDuration) -> Result<(), anyhow::Error> {
+        let result = self.inner.update_ttl(session_key, ttl).instrument(Self::span("update_ttl")).await;
+        self.track("update_ttl", result)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        let result = self.inner.delete(session_key).instrument(Self::span("delete")).await;
+        self.track("delete", result)
+    }
+}
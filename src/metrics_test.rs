@@ -0,0 +1,115 @@
+#[cfg(test)]
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+#[cfg(test)]
+use actix_web::cookie::time::Duration as CookieDuration;
+#[cfg(test)]
+use std::collections::HashMap;
+
+/// Stands in for Redis being down.
+#[cfg(test)]
+#[derive(Clone)]
+struct FailingStore;
+
+#[cfg(test)]
+#[async_trait::async_trait(?Send)]
+impl SessionStore for FailingStore {
+    async fn load(&self, _session_key: &SessionKey) -> Result<Option<HashMap<String, String>>, LoadError> {
+        Err(LoadError::Other(anyhow::anyhow!("connection refused")))
+    }
+
+    async fn save(
+        &self,
+        _session_state: HashMap<String, String>,
+        _ttl: &CookieDuration,
+    ) -> Result<SessionKey, SaveError> {
+        Err(SaveError::Other(anyhow::anyhow!("connection refused")))
+    }
+
+    async fn update(
+        &self,
+        _session_key: SessionKey,
+        _session_state: HashMap<String, String>,
+        _ttl: &CookieDuration,
+    ) -> Result<SessionKey, UpdateError> {
+        Err(UpdateError::Other(anyhow::anyhow!("connection refused")))
+    }
+
+    async fn update_ttl(&self, _session_key: &SessionKey, _ttl: &CookieDuration) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!("connection refused"))
+    }
+
+    async fn delete(&self, _session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_render() {
+    use crate::metrics::Metrics;
+    use crate::models::EventGauges;
+    use std::time::Duration;
+
+    let metrics = Metrics::new();
+    metrics.observe_request("GET", "/api/v1/events/{event_id}", 200, Duration::from_millis(12));
+    metrics.observe_request("GET", "/api/v1/events/{event_id}", 200, Duration::from_millis(30));
+    metrics.set_pool_state(5, 2);
+    metrics.set_event_gauges(&EventGauges {
+        open: 7,
+        established: 3,
+        joined_last_minute: 4,
+    });
+
+    let text = metrics.render().unwrap();
+    assert!(text.contains(
+        "http_requests_total{method=\"GET\",route=\"/api/v1/events/{event_id}\",status=\"200\"} 2"
+    ));
+    assert!(text.contains("http_request_duration_seconds_bucket{method=\"GET\""));
+    assert!(text.contains("db_pool_connections{state=\"idle\"} 2"));
+    assert!(text.contains("db_pool_connections{state=\"in_use\"} 3"));
+    assert!(text.contains("events_open 7"));
+    assert!(text.contains("events_established 3"));
+    assert!(text.contains("event_joins_per_minute 4"));
+}
+
+#[actix_web::test]
+async fn test_request_metrics() {
+    use crate::metrics::{Metrics, RequestMetrics};
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::Arc;
+
+    let metrics = Arc::new(Metrics::new());
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestMetrics::new(metrics.clone()))
+            .route("/events/{event_id}", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    for uri in ["/events/1", "/events/2", "/nope"] {
+        test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    }
+
+    let text = metrics.render().unwrap();
+    assert!(text.contains("http_requests_total{method=\"GET\",route=\"/events/{event_id}\",status=\"200\"} 2"));
+    assert!(text.contains("http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1"));
+}
+
+#[actix_web::test]
+async fn test_session_store_errors() {
+    use crate::metrics::{MeteredSessionStore, Metrics};
+    use std::sync::Arc;
+
+    let metrics = Arc::new(Metrics::new());
+    let store = MeteredSessionStore::new(FailingStore, metrics.clone());
+    let key = SessionKey::try_from("a".repeat(64)).unwrap();
+
+    assert!(store.load(&key).await.is_err());
+    assert!(store.load(&key).await.is_err());
+    assert!(store.save(HashMap::new(), &CookieDuration::minutes(5)).await.is_err());
+    assert!(store.delete(&key).await.is_ok());
+
+    let text = metrics.render().unwrap();
+    assert!(text.contains("session_store_errors_total{operation=\"load\"} 2"));
+    assert!(text.contains("session_store_errors_total{operation=\"save\"} 1"));
+    assert!(!text.contains("operation=\"delete\""));
+}
@@ -1,11 +1,18 @@
-use crate::schema::{events, users, event_members, event_comments};
+use crate::schema::{
+    attachments, categories, email_outbox, event_templates, events, users, event_members, event_comments, notification_preferences,
+    notifications, payments, webhook_deliveries, webhook_endpoints, jobs, job_schedules,
+};
 use chrono::naive::serde::{ts_seconds, ts_seconds_option};
 use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Queryable, Serialize, Selectable)]
+use crate::money::{Money, DEFAULT_CURRENCY};
+
+#[derive(Queryable, Serialize, Selectable, ToSchema)]
 #[diesel(primary_key(id))]
 #[diesel(table_name = users)]
 pub struct User {
@@ -25,7 +32,7 @@ pub struct NewUser {
     pub avatar: String,
 }
 
-#[derive(AsChangeset, Queryable, Deserialize)]
+#[derive(AsChangeset, Queryable, Deserialize, ToSchema)]
 #[diesel(table_name = users)]
 pub struct UpdateUser {
     pub avatar: Option<String>,
@@ -34,40 +41,100 @@ pub struct UpdateUser {
     pub phone: Option<String>,
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Deserialize, Insertable, ToSchema)]
 #[diesel(table_name = events)]
 pub struct NewEvent {
     pub name: String,
     pub description: String,
     pub category: String,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub start_time: NaiveDateTime,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub end_time: NaiveDateTime,
+    /// Minor units; the API takes decimals on `NewEventForm` instead.
+    #[serde(skip)]
     pub min_amount: i64,
+    #[serde(skip)]
     pub max_amount: i64,
     #[serde(skip)]
     pub user_id: Uuid,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub location: EventLocation,
+}
+
+/// Body of `POST /events`; tags live in their own table.
+#[derive(Deserialize, ToSchema)]
+pub struct NewEventForm {
+    #[serde(flatten)]
+    pub event: NewEvent,
+    /// Decimal in major units of `currency`, e.g. "149.50".
+    pub min_amount: String,
+    pub max_amount: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
 }
 
-#[derive(AsChangeset, Queryable, Deserialize)]
+#[derive(AsChangeset, Queryable, Deserialize, ToSchema)]
 #[diesel(table_name = events)]
 pub struct UpdateEvent {
     pub name: Option<String>,
     pub description: Option<String>,
     pub category: Option<String>,
     #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
     #[serde(default)]
     pub start_time: Option<NaiveDateTime>,
     #[serde(default)]
     #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
     pub end_time: Option<NaiveDateTime>,
+    #[serde(skip)]
     pub min_amount: Option<i64>,
+    #[serde(skip)]
     pub max_amount: Option<i64>,
     pub established: Option<bool>,
+    pub address: Option<String>,
+    pub venue: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
 
-#[derive(Queryable, Selectable, Serialize)]
+/// Body of `PATCH /events/{id}`; `tags` replaces the whole set when given.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateEventForm {
+    #[serde(flatten)]
+    pub event: UpdateEvent,
+    /// Decimal in major units of the event's currency, e.g. "149.50".
+    pub min_amount: Option<String>,
+    pub max_amount: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Where an event takes place. Every part is optional; coordinates come
+/// as a pair, see `geo::coordinates_check`.
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Default, ToSchema)]
+#[diesel(table_name = events)]
+pub struct EventLocation {
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub venue: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
 #[diesel(table_name = events)]
 #[diesel(primary_key(id))]
 pub struct Event {
@@ -77,23 +144,38 @@ pub struct Event {
     pub description: String,
     pub category: String,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub start_time: NaiveDateTime,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub end_time: NaiveDateTime,
     pub min_amount: i64,
     pub max_amount: i64,
     pub established: bool,
+    pub cancelled: bool,
+    pub currency: String,
+    pub expired: bool,
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub location: EventLocation,
 }
 
-#[derive(Queryable, Serialize)]
+#[derive(Queryable, Serialize, ToSchema)]
 pub struct EventMember {
     pub name: String,
     pub email: String,
     pub phone: String,
-    pub amount: i64,
+    pub amount: Money,
+    pub payment_status: String,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub checked_in_at: Option<NaiveDateTime>,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub joined_at: Option<NaiveDateTime>,
 }
 
-#[derive(Queryable, Serialize, Selectable)]
+#[derive(Queryable, Serialize, Selectable, ToSchema)]
 #[diesel(table_name = users)]
 pub struct EventOwner {
     pub id: Uuid,
@@ -102,7 +184,7 @@ pub struct EventOwner {
     pub email: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct EventWithMembers {
     pub id: Uuid,
     #[serde(skip_serializing)]
@@ -112,19 +194,37 @@ pub struct EventWithMembers {
     pub description: String,
     pub category: String,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub start_time: NaiveDateTime,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub end_time: NaiveDateTime,
-    pub min_amount: i64,
-    pub max_amount: i64,
-    pub amount: i64,
+    pub currency: String,
+    pub min_amount: Money,
+    pub max_amount: Money,
+    pub amount: Money,
     pub established: bool,
+    pub cancelled: bool,
+    pub expired: bool,
+    #[serde(flatten)]
+    pub location: EventLocation,
+    pub tags: Vec<String>,
+    /// Only when listing events `near` a point.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_km: Option<f64>,
+    /// Only shown to the event owner.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub members: Option<Vec<EventMember>>,
     pub members_count: i64,
+    /// Only shown to the event owner.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_summary: Option<PaymentSummary>,
+    /// Only shown to the event owner, once the event is established.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attendance: Option<AttendanceSummary>,
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Deserialize, Insertable, ToSchema)]
 #[diesel(table_name = event_members)]
 pub struct NewEventMember {
     #[serde(skip)]
@@ -134,7 +234,7 @@ pub struct NewEventMember {
     pub amount: i64,
 }
 
-#[derive(Deserialize, Insertable)]
+#[derive(Deserialize, Insertable, ToSchema)]
 #[diesel(table_name = event_comments)]
 pub struct NewEventMsg {
     #[serde(skip)]
@@ -144,17 +244,816 @@ pub struct NewEventMsg {
     pub content: String,
 }
 
-#[derive(Serialize, Queryable)]
+#[derive(Serialize, Queryable, ToSchema)]
 #[diesel(table_name = users)]
 pub struct EventMsgUser {
     pub name: String,
     pub avatar: String,
 }
-#[derive(Serialize, Queryable)]
+#[derive(Serialize, Queryable, ToSchema)]
 #[diesel(table_name = event_comments)]
 pub struct EventMsg {
+    pub id: Uuid,
     pub user: EventMsgUser,
     pub content: String,
     #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+}
+
+
+pub const PAYMENT_KIND_PAYMENT: &str = "payment";
+pub const PAYMENT_KIND_REFUND: &str = "refund";
+pub const PAYMENT_STATUS_PENDING: &str = "pending";
+pub const PAYMENT_STATUS_CONFIRMED: &str = "confirmed";
+pub const PAYMENT_STATUS_REJECTED: &str = "rejected";
+pub const PLEDGE_UNPAID: &str = "unpaid";
+pub const PLEDGE_PENDING: &str = "pending";
+pub const PLEDGE_PARTIAL: &str = "partial";
+pub const PLEDGE_PAID: &str = "paid";
+pub const PLEDGE_REFUNDED: &str = "refunded";
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = payments)]
+#[diesel(primary_key(id))]
+pub struct Payment {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub user_id: Uuid,
+    /// Minor units; see `PaymentResponse` for the API shape.
+    #[serde(skip_serializing)]
+    pub amount: i64,
+    pub kind: String,
+    pub method: String,
+    pub reference: String,
+    pub status: String,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub paid_at: NaiveDateTime,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub confirmed_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing)]
+    pub provider_ref: Option<String>,
+    /// For refunds, the payment that was refunded.
+    pub refund_of: Option<Uuid>,
+}
+
+/// A payment as returned by the API, with the amount in the event's currency.
+#[derive(Serialize, ToSchema)]
+pub struct PaymentResponse {
+    #[serde(flatten)]
+    pub payment: Payment,
+    pub amount: Money,
+}
+
+impl PaymentResponse {
+    pub fn new(payment: Payment, currency: &str) -> PaymentResponse {
+        PaymentResponse {
+            amount: Money::from_db(payment.amount, currency),
+            payment,
+        }
+    }
+}
+
+#[derive(Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = payments)]
+pub struct NewPayment {
+    #[serde(skip)]
+    pub event_id: Uuid,
+    #[serde(default)]
+    pub user_id: Uuid,
+    pub amount: i64,
+    #[serde(default = "default_payment_kind")]
+    pub kind: String,
+    pub method: String,
+    #[serde(default)]
+    pub reference: String,
+    #[serde(skip)]
+    pub status: String,
+    #[serde(default)]
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub paid_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    pub confirmed_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    pub provider_ref: Option<String>,
+    #[serde(skip)]
+    pub refund_of: Option<Uuid>,
+}
+
+fn default_payment_kind() -> String {
+    PAYMENT_KIND_PAYMENT.to_string()
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PaymentSummary {
+    pub expected_amount: Money,
+    pub paid_amount: Money,
+    pub refunded_amount: Money,
+    pub pending_amount: Money,
+    pub outstanding_amount: Money,
+    pub members_outstanding: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckoutResponse {
+    pub payment_id: Uuid,
+    pub url: String,
+}
+
+pub const NOTIFY_MEMBER_JOINED: &str = "member_joined";
+pub const NOTIFY_MEMBER_LEFT: &str = "member_left";
+pub const NOTIFY_EVENT_UPDATED: &str = "event_updated";
+pub const NOTIFY_EVENT_ESTABLISHED: &str = "event_established";
+pub const NOTIFY_EVENT_CANCELLED: &str = "event_cancelled";
+pub const NOTIFY_COMMENT_CREATED: &str = "comment_created";
+pub const EMAIL_EVENT_ESTABLISHED: &str = "email_event_established";
+pub const EMAIL_EVENT_CANCELLED: &str = "email_event_cancelled";
+pub const EMAIL_EVENT_REMINDER: &str = "email_event_reminder";
+pub const EMAIL_COMMENT_DIGEST: &str = "email_comment_digest";
+pub const NOTIFICATION_KINDS: &[&str] = &[
+    NOTIFY_MEMBER_JOINED,
+    NOTIFY_MEMBER_LEFT,
+    NOTIFY_EVENT_UPDATED,
+    NOTIFY_EVENT_ESTABLISHED,
+    NOTIFY_EVENT_CANCELLED,
+    NOTIFY_COMMENT_CREATED,
+    EMAIL_EVENT_ESTABLISHED,
+    EMAIL_EVENT_CANCELLED,
+    EMAIL_EVENT_REMINDER,
+    EMAIL_COMMENT_DIGEST,
+];
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = notifications)]
+#[diesel(primary_key(id))]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub kind: String,
+    pub message: String,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub read_at: Option<NaiveDateTime>,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification {
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub kind: String,
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NotificationList {
+    pub unread_count: i64,
+    pub notifications: Vec<Notification>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread_only: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = notification_preferences)]
+pub struct NotificationPreference {
+    #[serde(skip)]
+    pub user_id: Uuid,
+    /// Nil for the default that applies to every event.
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub kind: String,
+    pub enabled: bool,
+}
+
+pub const EMAIL_STATUS_PENDING: &str = "pending";
+pub const EMAIL_STATUS_SENT: &str = "sent";
+pub const EMAIL_STATUS_FAILED: &str = "failed";
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = email_outbox)]
+#[diesel(primary_key(id))]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub event_id: Option<Uuid>,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = email_outbox)]
+pub struct NewOutboxEmail {
+    pub user_id: Uuid,
+    pub kind: String,
+    pub event_id: Option<Uuid>,
+    /// Kind specific data, e.g. the comment lines of a digest as JSON.
+    pub payload: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UnsubscribeQuery {
+    pub user_id: Uuid,
+    pub kind: String,
+    pub token: String,
+}
+
+pub const WEBHOOK_EVENT_CREATED: &str = "event.created";
+pub const WEBHOOK_MEMBER_JOINED: &str = "member.joined";
+pub const WEBHOOK_MEMBER_LEFT: &str = "member.left";
+pub const WEBHOOK_EVENT_ESTABLISHED: &str = "event.established";
+pub const WEBHOOK_COMMENT_CREATED: &str = "comment.created";
+pub const WEBHOOK_EVENT_TYPES: &[&str] = &[
+    WEBHOOK_EVENT_CREATED,
+    WEBHOOK_MEMBER_JOINED,
+    WEBHOOK_MEMBER_LEFT,
+    WEBHOOK_EVENT_ESTABLISHED,
+    WEBHOOK_COMMENT_CREATED,
+];
+pub const DELIVERY_STATUS_PENDING: &str = "pending";
+pub const DELIVERY_STATUS_DELIVERED: &str = "delivered";
+pub const DELIVERY_STATUS_FAILED: &str = "failed";
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = webhook_endpoints)]
+#[diesel(primary_key(id))]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_id: Option<Uuid>,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub active: bool,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = webhook_endpoints)]
+pub struct NewWebhookEndpoint {
+    #[serde(skip)]
+    pub user_id: Uuid,
+    pub event_id: Option<Uuid>,
+    pub url: String,
+    #[serde(skip)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+}
+
+/// Returned once on registration; the secret can't be read back later.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhookEndpoint {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(primary_key(id))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub run_at: NaiveDateTime,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct NewWebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+}
+
+pub const JOB_SEND_EMAILS: &str = "send_emails";
+pub const JOB_SEND_WEBHOOKS: &str = "send_webhooks";
+pub const JOB_EVENT_REMINDERS: &str = "event_reminders";
+pub const JOB_COMMENT_DIGESTS: &str = "comment_digests";
+pub const JOB_EXPIRE_EVENTS: &str = "expire_events";
+pub const JOB_PURGE_DELETED: &str = "purge_deleted";
+/// One-off; the payload is the id of the payment to refund.
+pub const JOB_REFUND_PAYMENT: &str = "refund_payment";
+pub const JOB_STATUS_QUEUED: &str = "queued";
+pub const JOB_STATUS_RUNNING: &str = "running";
+pub const JOB_STATUS_SUCCEEDED: &str = "succeeded";
+pub const JOB_STATUS_DEAD: &str = "dead";
+pub const JOB_STATUSES: &[&str] = &[
+    JOB_STATUS_QUEUED,
+    JOB_STATUS_RUNNING,
+    JOB_STATUS_SUCCEEDED,
+    JOB_STATUS_DEAD,
+];
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = jobs)]
+#[diesel(primary_key(id))]
+pub struct Job {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub run_at: NaiveDateTime,
+    pub locked_by: Option<String>,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub locked_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = jobs)]
+pub struct NewJob {
+    pub kind: String,
+    pub payload: String,
+    pub run_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = job_schedules)]
+#[diesel(primary_key(name))]
+pub struct JobSchedule {
+    pub name: String,
+    pub cron: String,
+    pub next_run_at: NaiveDateTime,
+    pub last_run_at: Option<NaiveDateTime>,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct JobCount {
+    pub status: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobList {
+    pub counts: Vec<JobCount>,
+    pub jobs: Vec<Job>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobQuery {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: String,
+    pub category: Option<String>,
+    #[serde(default, with = "ts_seconds_option")]
+    #[param(value_type = Option<i64>)]
+    pub from: Option<NaiveDateTime>,
+    #[serde(default, with = "ts_seconds_option")]
+    #[param(value_type = Option<i64>)]
+    pub to: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub comments: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+pub struct EventSearchRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub description: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub category: String,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    pub start_time: NaiveDateTime,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    pub end_time: NaiveDateTime,
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub established: bool,
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub cancelled: bool,
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub expired: bool,
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub rank: f32,
+}
+
+#[derive(QueryableByName)]
+pub struct CommentSearchRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub event_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub event_name: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub author: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub content: String,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    pub created_at: NaiveDateTime,
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub rank: f32,
+}
+
+/// Matched text with the hits wrapped in `<mark>` and everything else
+/// HTML-escaped. A field is left out when the query didn't match it.
+#[derive(Serialize, ToSchema)]
+pub struct SearchHighlights {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EventSearchHit {
+    pub id: Uuid,
+    pub name: String,
+    pub category: String,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub start_time: NaiveDateTime,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub end_time: NaiveDateTime,
+    pub established: bool,
+    pub cancelled: bool,
+    pub expired: bool,
+    pub rank: f32,
+    pub highlights: SearchHighlights,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CommentSearchHit {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub event_name: String,
+    pub author: String,
+    pub snippet: String,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchResults {
+    pub events: Vec<EventSearchHit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<Vec<CommentSearchHit>>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    pub near: Option<String>,
+    pub radius_km: Option<f64>,
+    /// Comma separated, e.g. `tags=vegan,weekend`.
+    pub tags: Option<String>,
+    /// `any` (default) or `all` of `tags`.
+    pub tags_mode: Option<String>,
+}
+
+pub const ATTACHMENT_EVENT_COVER: &str = "event_cover";
+pub const ATTACHMENT_EVENT_GALLERY: &str = "event_gallery";
+pub const ATTACHMENT_COMMENT: &str = "comment";
+pub const ATTACHMENT_AVATAR: &str = "avatar";
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = attachments)]
+#[diesel(primary_key(id))]
+pub struct Attachment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub event_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_key: String,
+    pub thumbnail_key: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub original_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = attachments)]
+pub struct NewAttachment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub event_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_key: String,
+    pub thumbnail_key: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub original_name: String,
+}
+
+/// An attachment as handed to clients, with short-lived download links
+/// instead of storage keys.
+#[derive(Serialize, ToSchema)]
+pub struct AttachmentView {
+    pub id: Uuid,
+    pub kind: String,
+    pub uploaded_by: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_id: Option<Uuid>,
+    pub content_type: String,
+    pub size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub original_name: String,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub expires_at: NaiveDateTime,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FileQuery {
+    pub variant: String,
+    pub expires: i64,
+    pub signature: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = categories)]
+#[diesel(primary_key(slug))]
+pub struct Category {
+    pub slug: String,
+    pub name: String,
+    pub icon: String,
+    pub sort_order: i32,
+    pub parent: Option<String>,
+}
+
+#[derive(Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = categories)]
+pub struct NewCategory {
+    pub slug: String,
+    pub name: String,
+    #[serde(default)]
+    pub icon: String,
+    #[serde(default)]
+    pub sort_order: i32,
+    pub parent: Option<String>,
+}
+
+/// Tells a field sent as `null` (`Some(None)`) apart from one left out.
+fn double_option<'de, D, T>(de: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Deserialize::deserialize(de).map(Some)
+}
+
+#[derive(Deserialize, AsChangeset, ToSchema)]
+#[diesel(table_name = categories)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    pub icon: Option<String>,
+    pub sort_order: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub parent: Option<Option<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryWithCount {
+    #[serde(flatten)]
+    pub category: Category,
+    pub open_events: i64,
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TagsQuery {
+    pub prefix: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CloneEventForm {
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub start_time: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = event_templates)]
+#[diesel(primary_key(id))]
+pub struct EventTemplate {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    pub user_id: Uuid,
+    pub title: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub min_amount: i64,
+    pub max_amount: i64,
+    pub currency: String,
+    pub duration_secs: i64,
+    pub address: Option<String>,
+    pub venue: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub tags: Vec<String>,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
     pub created_at: NaiveDateTime,
 }
+
+#[derive(Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = event_templates)]
+pub struct NewEventTemplate {
+    #[serde(skip)]
+    pub user_id: Uuid,
+    pub title: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub min_amount: i64,
+    pub max_amount: i64,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub duration_secs: i64,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub venue: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Check-in progress of an established event, for its owner.
+#[derive(Serialize, ToSchema)]
+pub struct AttendanceSummary {
+    pub checked_in: i64,
+    pub not_checked_in: i64,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CheckinForm {
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckinResult {
+    pub user_id: Uuid,
+    pub name: String,
+    #[serde(with = "ts_seconds")]
+    #[schema(value_type = i64)]
+    pub checked_in_at: NaiveDateTime,
+    pub already_checked_in: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QrQuery {
+    pub format: Option<String>,
+}
+
+/// Attendance record shown on a user's profile. No-shows only count
+/// events that used check-in, so events nobody scanned at don't count
+/// against anyone.
+#[derive(QueryableByName, Serialize, ToSchema)]
+pub struct AttendanceCounts {
+    #[diesel(sql_type = diesel::sql_types::Int8)]
+    pub attended: i64,
+    #[diesel(sql_type = diesel::sql_types::Int8)]
+    pub no_shows: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(flatten)]
+    pub attendance: AttendanceCounts,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    pub commit: Option<bool>,
+    pub category: Option<String>,
+    pub currency: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportRowReport {
+    pub row: usize,
+    pub name: String,
+    pub error: Option<String>,
+}
+
+/// Result of `POST /events/import`. `created` is only filled in when the
+/// import was committed.
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+    pub committed: bool,
+    pub valid: usize,
+    pub invalid: usize,
+    pub rows: Vec<ImportRowReport>,
+    pub created: Vec<Uuid>,
+}
+
+/// Domain numbers sampled on every `/metrics` scrape.
+pub struct EventGauges {
+    pub open: i64,
+    pub established: i64,
+    pub joined_last_minute: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentHealth {
+    /// `ok` or `fail`.
+    pub status: String,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthReport {
+    /// `ok` when every component is.
+    pub status: String,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
@@ -0,0 +1,202 @@
+use diesel::deserialize::{self, Queryable};
+use diesel::pg::Pg;
+use diesel::sql_types::{BigInt, Text};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, Schema, SchemaFormat, SchemaType};
+use utoipa::openapi::RefOr;
+use utoipa::ToSchema;
+
+pub const DEFAULT_CURRENCY: &str = "TWD";
+
+/// ISO-4217 currency with the number of minor-unit digits it uses.
+#[derive(Debug, PartialEq)]
+pub struct Currency {
+    pub code: &'static str,
+    pub scale: u32,
+    pub symbol: &'static str,
+}
+
+const CURRENCIES: &[Currency] = &[
+    Currency { code: "TWD", scale: 2, symbol: "NT$" },
+    Currency { code: "USD", scale: 2, symbol: "US$" },
+    Currency { code: "EUR", scale: 2, symbol: "€" },
+    Currency { code: "GBP", scale: 2, symbol: "£" },
+    Currency { code: "HKD", scale: 2, symbol: "HK$" },
+    Currency { code: "CNY", scale: 2, symbol: "CN¥" },
+    Currency { code: "SGD", scale: 2, symbol: "S$" },
+    Currency { code: "JPY", scale: 0, symbol: "¥" },
+    Currency { code: "KRW", scale: 0, symbol: "₩" },
+];
+
+pub fn find_currency(code: &str) -> Option<&'static Currency> {
+    let code = code.trim().to_uppercase();
+    CURRENCIES.iter().find(|c| c.code == code)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MoneyError {
+    UnknownCurrency(String),
+    InvalidAmount(String),
+    TooPrecise(String),
+    Negative,
+    CurrencyMismatch,
+    MinExceedsMax,
+}
+
+impl std::fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoneyError::UnknownCurrency(code) => write!(f, "Unknown currency {}", code),
+            MoneyError::InvalidAmount(amount) => write!(f, "Invalid amount {}", amount),
+            MoneyError::TooPrecise(code) => write!(f, "Too many decimal places for {}", code),
+            MoneyError::Negative => write!(f, "Amount should not be negative"),
+            MoneyError::CurrencyMismatch => write!(f, "Amounts should use the same currency"),
+            MoneyError::MinExceedsMax => write!(f, "Min amount should be smaller than max amount"),
+        }
+    }
+}
+
+/// An amount in minor units (e.g. cents) of a currency. Stored as a bare
+/// BIGINT next to the event's `currency` column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Money {
+    pub minor: i64,
+    pub currency: &'static Currency,
+}
+
+impl Money {
+    pub fn new(minor: i64, code: &str) -> Result<Money, MoneyError> {
+        match find_currency(code) {
+            Some(currency) => Ok(Money { minor, currency }),
+            None => Err(MoneyError::UnknownCurrency(code.to_string())),
+        }
+    }
+
+    /// Builds a value read back from the database, where the currency has
+    /// already been validated on insert.
+    pub fn from_db(minor: i64, code: &str) -> Money {
+        Money::new(minor, code).expect("Invalid currency in database")
+    }
+
+    /// Parses a decimal string in major units, e.g. "149.5" TWD -> 14950.
+    pub fn parse(amount: &str, code: &str) -> Result<Money, MoneyError> {
+        let currency = match find_currency(code) {
+            Some(c) => c,
+            None => return Err(MoneyError::UnknownCurrency(code.to_string())),
+        };
+        let amount = amount.trim();
+        let (negative, digits) = match amount.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, amount),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+            return Err(MoneyError::InvalidAmount(amount.to_string()));
+        }
+        if fraction.len() > currency.scale as usize {
+            return Err(MoneyError::TooPrecise(currency.code.to_string()));
+        }
+
+        let padded = format!("{:0<width$}", fraction, width = currency.scale as usize);
+        let minor = format!("{}{}", whole, padded)
+            .parse::<i64>()
+            .map_err(|_| MoneyError::InvalidAmount(amount.to_string()))?;
+
+        Ok(Money {
+            minor: if negative { -minor } else { minor },
+            currency,
+        })
+    }
+
+    /// Decimal representation in major units without a symbol, e.g. "149.50".
+    pub fn to_decimal(self) -> String {
+        let scale = self.currency.scale;
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let abs = self.minor.unsigned_abs();
+        if scale == 0 {
+            return format!("{}{}", sign, abs);
+        }
+        let factor = 10u64.pow(scale);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            abs / factor,
+            abs % factor,
+            width = scale as usize
+        )
+    }
+
+    /// Human readable form, e.g. "NT$149.50".
+    pub fn format(&self) -> String {
+        let decimal = self.to_decimal();
+        match decimal.strip_prefix('-') {
+            Some(rest) => format!("-{}{}", self.currency.symbol, rest),
+            None => format!("{}{}", self.currency.symbol, decimal),
+        }
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Money", 5)?;
+        state.serialize_field("minor", &self.minor)?;
+        state.serialize_field("currency", self.currency.code)?;
+        state.serialize_field("scale", &self.currency.scale)?;
+        state.serialize_field("amount", &self.to_decimal())?;
+        state.serialize_field("display", &self.format())?;
+        state.end()
+    }
+}
+
+/// Mirrors the `Serialize` impl above for the API docs.
+impl<'s> ToSchema<'s> for Money {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let field = |schema_type: SchemaType, description: &str| {
+            ObjectBuilder::new()
+                .schema_type(schema_type)
+                .description(Some(description))
+        };
+        (
+            "Money",
+            ObjectBuilder::new()
+                .property(
+                    "minor",
+                    field(SchemaType::Integer, "Amount in minor units, e.g. 14950")
+                        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64))),
+                )
+                .property("currency", field(SchemaType::String, "ISO-4217 code, e.g. TWD"))
+                .property("scale", field(SchemaType::Integer, "Number of minor-unit digits"))
+                .property("amount", field(SchemaType::String, "Decimal in major units, e.g. 149.50"))
+                .property("display", field(SchemaType::String, "Human readable, e.g. NT$149.50"))
+                .required("minor")
+                .required("currency")
+                .required("scale")
+                .required("amount")
+                .required("display")
+                .into(),
+        )
+    }
+}
+
+impl Queryable<(BigInt, Text), Pg> for Money {
+    type Row = (i64, String);
+
+    fn build(row: Self::Row) -> deserialize::Result<Self> {
+        Money::new(row.0, &row.1).map_err(|e| e.to_string().into())
+    }
+}
+
+pub fn amount_check(min_amount: Money, max_amount: Money) -> Result<(), MoneyError> {
+    if min_amount.currency != max_amount.currency {
+        return Err(MoneyError::CurrencyMismatch);
+    }
+    if min_amount.minor < 0 || max_amount.minor < 0 {
+        return Err(MoneyError::Negative);
+    }
+    if min_amount.minor > max_amount.minor {
+        return Err(MoneyError::MinExceedsMax);
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+#[test]
+fn test_money_parse_and_format() {
+    use crate::money::{Money, MoneyError};
+
+    let price = Money::parse("149.5", "twd").unwrap();
+    assert_eq!(price.minor, 14950);
+    assert_eq!(price.currency.code, "TWD");
+    assert_eq!(price.to_decimal(), "149.50");
+    assert_eq!(price.format(), "NT$149.50");
+
+    let yen = Money::parse("1200", "JPY").unwrap();
+    assert_eq!(yen.minor, 1200);
+    assert_eq!(yen.format(), "¥1200");
+
+    assert_eq!(Money::new(-5, "USD").unwrap().format(), "-US$0.05");
+    assert_eq!(Money::parse("1.005", "USD"), Err(MoneyError::TooPrecise("USD".to_string())));
+    assert_eq!(Money::parse("1.5", "JPY"), Err(MoneyError::TooPrecise("JPY".to_string())));
+    assert_eq!(Money::parse("abc", "USD"), Err(MoneyError::InvalidAmount("abc".to_string())));
+    assert_eq!(Money::parse("1", "XYZ"), Err(MoneyError::UnknownCurrency("XYZ".to_string())));
+}
+
+#[test]
+fn test_money_serialize() {
+    use crate::money::Money;
+
+    let price = Money::new(14950, "TWD").unwrap();
+    let json = serde_json::to_value(price).unwrap();
+    assert_eq!(json["minor"], 14950);
+    assert_eq!(json["currency"], "TWD");
+    assert_eq!(json["scale"], 2);
+    assert_eq!(json["amount"], "149.50");
+}
+
+#[test]
+fn test_amount_check() {
+    use crate::money::{amount_check, Money, MoneyError};
+
+    let min = Money::new(100, "TWD").unwrap();
+    let max = Money::new(1000, "TWD").unwrap();
+    assert_eq!(amount_check(min, max), Ok(()));
+    assert_eq!(amount_check(max, min), Err(MoneyError::MinExceedsMax));
+    assert_eq!(
+        amount_check(min, Money::new(1000, "USD").unwrap()),
+        Err(MoneyError::CurrencyMismatch)
+    );
+    assert_eq!(
+        amount_check(Money::new(-1, "TWD").unwrap(), max),
+        Err(MoneyError::Negative)
+    );
+}
@@ -0,0 +1,44 @@
+use diesel::pg::PgConnection;
+use uuid::Uuid;
+
+use crate::db;
+use crate::email::worker::{email_kind_for, enqueue_event_emails};
+
+/// Owner and members of an event, minus `exclude` (usually whoever
+/// triggered the notification).
+pub fn event_audience(
+    conn: &mut PgConnection,
+    event_id: Uuid,
+    owner_id: Uuid,
+    exclude: Uuid,
+) -> Vec<Uuid> {
+    let mut audience = db::get_event_members(conn, event_id);
+    audience.push(owner_id);
+    audience.sort();
+    audience.dedup();
+    audience.retain(|user_id| *user_id != exclude);
+    audience
+}
+
+/// Stores an in-app notification for every recipient that has not turned
+/// `kind` off, and queues the matching email if the kind has one. Failures
+/// are logged rather than failing the request that triggered them.
+pub fn notify(
+    conn: &mut PgConnection,
+    recipients: Vec<Uuid>,
+    event_id: Uuid,
+    kind: &str,
+    message: String,
+) {
+    if let Some(email_kind) = email_kind_for(kind) {
+        if let Err(e) = enqueue_event_emails(conn, recipients.clone(), event_id, email_kind) {
+            log::error!("failed to queue {} emails for event {}: {}", email_kind, event_id, e);
+        }
+    }
+
+    let result = db::filter_notification_recipients(conn, recipients, event_id, kind)
+        .and_then(|recipients| db::create_notifications(conn, &recipients, event_id, kind, &message));
+    if let Err(e) = result {
+        log::error!("failed to create {} notifications for event {}: {}", kind, event_id, e);
+    }
+}
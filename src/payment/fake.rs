@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::payment::{
+    Checkout, CheckoutRequest, PaymentProvider, ProviderError, ProviderEvent, ProviderEventKind,
+};
+#[cfg(test)]
+use crate::signing::hmac_hex;
+use crate::signing::hmac_verify;
+
+/// Provider for local development and tests. Checkouts never leave the
+/// process; webhooks are plain JSON signed with a hex HMAC-SHA256.
+pub struct FakeProvider {
+    webhook_secret: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FakeWebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub checkout_id: String,
+}
+
+impl FakeProvider {
+    pub fn new(webhook_secret: String) -> Self {
+        FakeProvider { webhook_secret }
+    }
+
+    #[cfg(test)]
+    pub fn sign(&self, payload: &[u8]) -> String {
+        hmac_hex(self.webhook_secret.as_bytes(), payload)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for FakeProvider {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "X-Fake-Signature"
+    }
+
+    async fn create_checkout(&self, req: &CheckoutRequest) -> Result<Checkout, ProviderError> {
+        let provider_ref = format!("fake_cs_{}", req.payment_id.simple());
+
+        Ok(Checkout {
+            url: format!("{}?checkout_id={}", req.success_url, provider_ref),
+            provider_ref,
+        })
+    }
+
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> bool {
+        hmac_verify(self.webhook_secret.as_bytes(), payload, signature)
+    }
+
+    fn parse_event(&self, payload: &[u8]) -> Result<ProviderEvent, ProviderError> {
+        let event: FakeWebhookEvent = serde_json::from_slice(payload)
+            .map_err(|e| ProviderError::InvalidPayload(e.to_string()))?;
+        let kind = match event.kind.as_str() {
+            "payment.succeeded" => ProviderEventKind::Succeeded,
+            "payment.failed" => ProviderEventKind::Failed,
+            _ => ProviderEventKind::Ignored,
+        };
+        let payment_ref = format!("fake_pi_{}", event.checkout_id);
+
+        Ok(ProviderEvent {
+            id: event.id,
+            checkout_ref: event.checkout_id,
+            payment_ref: Some(payment_ref),
+            kind,
+        })
+    }
+
+    async fn refund(
+        &self,
+        _payment_id: Uuid,
+        _payment_ref: &str,
+        _amount: i64,
+    ) -> Result<String, ProviderError> {
+        Ok(format!("fake_re_{}", Uuid::new_v4().simple()))
+    }
+}
@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use diesel::pg::PgConnection;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db;
+use crate::models::{NewPayment, Payment, PAYMENT_KIND_REFUND, PAYMENT_STATUS_CONFIRMED};
+
+pub mod fake;
+pub mod stripe;
+
+mod payment_test;
+
+pub struct CheckoutRequest {
+    pub payment_id: Uuid,
+    pub event_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: i64,
+    pub currency: String,
+    pub description: String,
+    pub success_url: String,
+    pub cancel_url: String,
+}
+
+pub struct Checkout {
+    pub provider_ref: String,
+    pub url: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProviderEventKind {
+    Succeeded,
+    Failed,
+    Ignored,
+}
+
+#[derive(Debug)]
+pub struct ProviderEvent {
+    /// Provider side id of the notification, used to drop redelivered webhooks.
+    pub id: String,
+    /// The `provider_ref` returned by `create_checkout`.
+    pub checkout_ref: String,
+    /// Provider side id of the captured payment, needed to refund it later.
+    pub payment_ref: Option<String>,
+    pub kind: ProviderEventKind,
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+    Request(String),
+    InvalidPayload(String),
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::Request(msg) => write!(f, "provider request failed: {}", msg),
+            ProviderError::InvalidPayload(msg) => write!(f, "invalid provider payload: {}", msg),
+        }
+    }
+}
+
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn signature_header(&self) -> &'static str;
+    async fn create_checkout(&self, req: &CheckoutRequest) -> Result<Checkout, ProviderError>;
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> bool;
+    fn parse_event(&self, payload: &[u8]) -> Result<ProviderEvent, ProviderError>;
+    async fn refund(
+        &self,
+        payment_id: Uuid,
+        payment_ref: &str,
+        amount: i64,
+    ) -> Result<String, ProviderError>;
+}
+
+#[derive(Debug)]
+pub enum RefundError {
+    /// Nothing was refunded, so it is safe to try again.
+    Provider(ProviderError),
+    /// The provider refunded but the ledger row wasn't written; retrying
+    /// would refund twice.
+    NotRecorded(diesel::result::Error),
+}
+
+impl std::fmt::Display for RefundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefundError::Provider(e) => write!(f, "{}", e),
+            RefundError::NotRecorded(e) => write!(f, "refund issued but not recorded: {}", e),
+        }
+    }
+}
+
+/// Refunds `payment` in full and records the refund next to it.
+pub async fn refund_payment(
+    conn: &mut PgConnection,
+    provider: &dyn PaymentProvider,
+    payment: &Payment,
+) -> Result<Payment, RefundError> {
+    let refund_ref = provider
+        .refund(payment.id, &payment.reference, payment.amount)
+        .await
+        .map_err(RefundError::Provider)?;
+    let refund = NewPayment {
+        event_id: payment.event_id,
+        user_id: payment.user_id,
+        amount: payment.amount,
+        kind: PAYMENT_KIND_REFUND.to_string(),
+        method: provider.name().to_string(),
+        reference: refund_ref.clone(),
+        status: PAYMENT_STATUS_CONFIRMED.to_string(),
+        paid_at: None,
+        confirmed_at: Some(chrono::Utc::now().naive_utc()),
+        provider_ref: Some(refund_ref),
+        refund_of: Some(payment.id),
+    };
+    let refund = db::create_payment(conn, refund).map_err(RefundError::NotRecorded)?;
+    db::refresh_pledge_status(conn, payment.event_id, payment.user_id).ok();
+    Ok(refund)
+}
+
+/// Only call on a validated config.
+pub fn provider_from_config(config: &Config) -> Arc<dyn PaymentProvider> {
+    let webhook_secret = config.payment_webhook_secret.clone();
+    match config.payment_provider.as_str() {
+        "stripe" => Arc::new(stripe::StripeProvider::new(
+            config.stripe_secret_key.clone(),
+            webhook_secret,
+        )),
+        _ => Arc::new(fake::FakeProvider::new(webhook_secret)),
+    }
+}
@@ -0,0 +1,43 @@
+#[test]
+fn test_fake_webhook_signature() {
+    use crate::payment::fake::FakeProvider;
+    use crate::payment::{PaymentProvider, ProviderEventKind};
+
+    let provider = FakeProvider::new("test_secret".to_string());
+    let payload = br#"{"id":"evt_1","type":"payment.succeeded","checkout_id":"fake_cs_1"}"#;
+    let signature = provider.sign(payload);
+
+    assert!(provider.verify_webhook(payload, &signature));
+    assert!(!provider.verify_webhook(payload, "00"));
+    assert!(!provider.verify_webhook(b"{}", &signature));
+
+    let event = provider.parse_event(payload).unwrap();
+    assert_eq!(event.id, "evt_1");
+    assert_eq!(event.checkout_ref, "fake_cs_1");
+    assert_eq!(event.kind, ProviderEventKind::Succeeded);
+}
+
+#[test]
+fn test_stripe_webhook_signature() {
+    use crate::payment::stripe::StripeProvider;
+    use crate::payment::{PaymentProvider, ProviderEventKind};
+    use crate::signing::hmac_hex;
+
+    let provider = StripeProvider::new("sk_test".to_string(), "whsec_test".to_string());
+    let payload = br#"{"id":"evt_1","type":"checkout.session.completed","data":{"object":{"id":"cs_1","url":null,"payment_intent":"pi_1","payment_status":"paid"}}}"#;
+    let timestamp = chrono::Utc::now().timestamp();
+    let mut signed_payload = format!("{}.", timestamp).into_bytes();
+    signed_payload.extend_from_slice(payload);
+    let signature = format!("t={},v1={}", timestamp, hmac_hex(b"whsec_test", &signed_payload));
+    let mut stale_payload = format!("{}.", timestamp - 3600).into_bytes();
+    stale_payload.extend_from_slice(payload);
+    let stale = format!("t={},v1={}", timestamp - 3600, hmac_hex(b"whsec_test", &stale_payload));
+
+    assert!(provider.verify_webhook(payload, &signature));
+    assert!(!provider.verify_webhook(payload, &stale));
+
+    let event = provider.parse_event(payload).unwrap();
+    assert_eq!(event.checkout_ref, "cs_1");
+    assert_eq!(event.payment_ref, Some("pi_1".to_string()));
+    assert_eq!(event.kind, ProviderEventKind::Succeeded);
+}
@@ -0,0 +1,194 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::payment::{
+    Checkout, CheckoutRequest, PaymentProvider, ProviderError, ProviderEvent, ProviderEventKind,
+};
+use crate::signing::hmac_verify;
+use crate::telemetry;
+
+const API_BASE: &str = "https://api.stripe.com/v1";
+/// Signatures older than this are rejected to limit replays.
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+pub struct StripeProvider {
+    api_key: String,
+    webhook_secret: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct CheckoutSession {
+    id: String,
+    url: Option<String>,
+    payment_intent: Option<String>,
+    payment_status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Refund {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct WebhookData {
+    object: CheckoutSession,
+}
+
+#[derive(Deserialize)]
+struct WebhookEvent {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: WebhookData,
+}
+
+impl StripeProvider {
+    pub fn new(api_key: String, webhook_secret: String) -> Self {
+        StripeProvider {
+            api_key,
+            webhook_secret,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post_form<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        form: &[(&str, String)],
+        idempotency_key: Option<&str>,
+    ) -> Result<T, ProviderError> {
+        let mut req = self
+            .client
+            .post(format!("{}{}", API_BASE, path))
+            .headers(telemetry::trace_headers())
+            .basic_auth(&self.api_key, Some(""))
+            .form(form);
+        if let Some(key) = idempotency_key {
+            req = req.header("Idempotency-Key", key);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ProviderError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ProviderError::Request(format!("{}: {}", status, body)));
+        }
+        resp.json::<T>()
+            .await
+            .map_err(|e| ProviderError::InvalidPayload(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "Stripe-Signature"
+    }
+
+    async fn create_checkout(&self, req: &CheckoutRequest) -> Result<Checkout, ProviderError> {
+        let form = [
+            ("mode", "payment".to_string()),
+            ("success_url", req.success_url.clone()),
+            ("cancel_url", req.cancel_url.clone()),
+            ("client_reference_id", req.payment_id.to_string()),
+            ("metadata[payment_id]", req.payment_id.to_string()),
+            ("metadata[event_id]", req.event_id.to_string()),
+            ("metadata[user_id]", req.user_id.to_string()),
+            ("line_items[0][quantity]", "1".to_string()),
+            ("line_items[0][price_data][currency]", req.currency.to_lowercase()),
+            ("line_items[0][price_data][unit_amount]", req.amount.to_string()),
+            ("line_items[0][price_data][product_data][name]", req.description.clone()),
+        ];
+        let session: CheckoutSession = self.post_form("/checkout/sessions", &form, None).await?;
+        let url = session
+            .url
+            .ok_or_else(|| ProviderError::InvalidPayload("checkout session has no url".to_string()))?;
+
+        Ok(Checkout {
+            provider_ref: session.id,
+            url,
+        })
+    }
+
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> bool {
+        let mut timestamp: Option<&str> = None;
+        let mut candidates: Vec<&str> = Vec::new();
+        for part in signature.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", t)) => timestamp = Some(t),
+                Some(("v1", v)) => candidates.push(v),
+                _ => {}
+            }
+        }
+        let timestamp = match timestamp {
+            Some(t) => t,
+            None => return false,
+        };
+        match timestamp.parse::<i64>() {
+            Ok(t) => {
+                if (chrono::Utc::now().timestamp() - t).abs() > SIGNATURE_TOLERANCE_SECS {
+                    return false;
+                }
+            }
+            Err(_) => return false,
+        }
+
+        let mut signed_payload = format!("{}.", timestamp).into_bytes();
+        signed_payload.extend_from_slice(payload);
+        candidates
+            .iter()
+            .any(|v| hmac_verify(self.webhook_secret.as_bytes(), &signed_payload, v))
+    }
+
+    fn parse_event(&self, payload: &[u8]) -> Result<ProviderEvent, ProviderError> {
+        let event: WebhookEvent = serde_json::from_slice(payload)
+            .map_err(|e| ProviderError::InvalidPayload(e.to_string()))?;
+        let kind = match event.kind.as_str() {
+            "checkout.session.completed" => {
+                if event.data.object.payment_status.as_deref() == Some("paid") {
+                    ProviderEventKind::Succeeded
+                } else {
+                    ProviderEventKind::Ignored
+                }
+            }
+            "checkout.session.async_payment_succeeded" => ProviderEventKind::Succeeded,
+            "checkout.session.async_payment_failed" | "checkout.session.expired" => {
+                ProviderEventKind::Failed
+            }
+            _ => ProviderEventKind::Ignored,
+        };
+
+        Ok(ProviderEvent {
+            id: event.id,
+            checkout_ref: event.data.object.id,
+            payment_ref: event.data.object.payment_intent,
+            kind,
+        })
+    }
+
+    async fn refund(
+        &self,
+        payment_id: Uuid,
+        payment_ref: &str,
+        amount: i64,
+    ) -> Result<String, ProviderError> {
+        let form = [
+            ("payment_intent", payment_ref.to_string()),
+            ("amount", amount.to_string()),
+        ];
+        // Stripe replays the first response for a repeated key, so a retry
+        // after a lost response can't refund the payment twice.
+        let key = format!("refund-{}", payment_id);
+        let refund: Refund = self.post_form("/refunds", &form, Some(&key)).await?;
+
+        Ok(refund.id)
+    }
+}
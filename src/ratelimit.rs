@@ -0,0 +1,246 @@
+use actix_session::SessionExt;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::Method;
+use actix_web::{Error, HttpResponse};
+use async_trait::async_trait;
+use futures_util::future::LocalBoxFuture;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::types::DefaultError;
+
+const KEY_PREFIX: &str = "ratelimit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RouteGroup {
+    Comments,
+    EventCreation,
+    Auth,
+}
+
+impl RouteGroup {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteGroup::Comments => "comments",
+            RouteGroup::EventCreation => "events",
+            RouteGroup::Auth => "auth",
+        }
+    }
+}
+
+/// Limited routes, by method and route pattern. Everything else passes.
+pub fn route_group(method: &Method, pattern: &str) -> Option<RouteGroup> {
+    if *method != Method::POST {
+        return None;
+    }
+    match pattern {
+        "/api/v1/events/{event_id}/msgs" | "/api/v1/events/{event_id}/msgs/{comment_id}/attachments" => {
+            Some(RouteGroup::Comments)
+        }
+        "/api/v1/events"
+        | "/api/v1/events/import"
+        | "/api/v1/events/{event_id}/clone"
+        | "/api/v1/users/{user_id}/templates/{template_id}/events" => Some(RouteGroup::EventCreation),
+        "/api/v1/login" => Some(RouteGroup::Auth),
+        _ => None,
+    }
+}
+
+/// Requests allowed per window for each group; 0 turns a group off.
+#[derive(Clone, Debug)]
+pub struct RateLimits {
+    pub window_secs: u64,
+    pub comments: u64,
+    pub events: u64,
+    pub auth: u64,
+}
+
+impl RateLimits {
+    pub fn limit(&self, group: RouteGroup) -> u64 {
+        match group {
+            RouteGroup::Comments => self.comments,
+            RouteGroup::EventCreation => self.events,
+            RouteGroup::Auth => self.auth,
+        }
+    }
+}
+
+/// Fixed window counters. `hit` counts a request against `key` in the
+/// window starting at `window_start` and returns the count so far.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn hit(&self, key: &str, window_start: u64, window_secs: u64) -> Result<u64, String>;
+}
+
+/// Shared between instances, so limits hold behind a load balancer.
+pub struct RedisStore {
+    conn: redis::aio::ConnectionManager,
+}
+
+impl RedisStore {
+    pub async fn new(client: redis::Client) -> Result<RedisStore, redis::RedisError> {
+        Ok(RedisStore {
+            conn: redis::aio::ConnectionManager::new(client).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn hit(&self, key: &str, window_start: u64, window_secs: u64) -> Result<u64, String> {
+        let key = format!("{}:{}:{}", KEY_PREFIX, key, window_start);
+        let mut conn = self.conn.clone();
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, window_secs as usize)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(count)
+    }
+}
+
+/// Per process; for tests and single-instance setups.
+#[derive(Default)]
+pub struct MemoryStore {
+    windows: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn hit(&self, key: &str, window_start: u64, _window_secs: u64) -> Result<u64, String> {
+        let mut windows = self.windows.lock().map_err(|e| e.to_string())?;
+        windows.retain(|_, (start, _)| *start >= window_start);
+        let (start, count) = windows.entry(key.to_string()).or_insert((window_start, 0));
+        if *start != window_start {
+            *start = window_start;
+            *count = 0;
+        }
+        *count += 1;
+        Ok(*count)
+    }
+}
+
+/// Middleware counting requests to the limited route groups, per user when
+/// logged in and per client IP otherwise. Over the limit it answers 429
+/// with `Retry-After`. If the store fails, requests are let through. Needs
+/// to run inside `SessionMiddleware`.
+pub struct RateLimit {
+    store: Arc<dyn RateLimitStore>,
+    limits: RateLimits,
+    /// Take the client IP from `Forwarded`/`X-Forwarded-For`, which only a
+    /// proxy in front of us should be trusted to set.
+    trust_proxy: bool,
+}
+
+impl RateLimit {
+    pub fn new(store: Arc<dyn RateLimitStore>, limits: RateLimits, trust_proxy: bool) -> RateLimit {
+        RateLimit {
+            store,
+            limits,
+            trust_proxy,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            limits: self.limits.clone(),
+            trust_proxy: self.trust_proxy,
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    store: Arc<dyn RateLimitStore>,
+    limits: RateLimits,
+    trust_proxy: bool,
+}
+
+impl<S> RateLimitMiddleware<S> {
+    fn client(&self, req: &ServiceRequest) -> String {
+        if let Some(user_id) = req.get_session().entries().get("user_id") {
+            return format!("user:{}", user_id.trim_matches('"'));
+        }
+        let ip = if self.trust_proxy {
+            req.connection_info().realip_remote_addr().map(|ip| ip.to_string())
+        } else {
+            req.peer_addr().map(|addr| addr.ip().to_string())
+        };
+        format!("ip:{}", ip.unwrap_or("unknown".to_string()))
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limited = req
+            .match_pattern()
+            .and_then(|pattern| route_group(req.method(), &pattern))
+            .map(|group| (group, self.limits.limit(group)))
+            .filter(|(_, limit)| *limit > 0 && self.limits.window_secs > 0);
+        let (group, limit) = match limited {
+            Some(limited) => limited,
+            None => {
+                return Box::pin(async move { service.call(req).await.map(|res| res.map_into_left_body()) });
+            }
+        };
+
+        let key = format!("{}:{}", group.name(), self.client(&req));
+        let store = self.store.clone();
+        let window_secs = self.limits.window_secs;
+        Box::pin(async move {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let window_start = now - now % window_secs;
+            match store.hit(&key, window_start, window_secs).await {
+                Ok(count) if count > limit => {
+                    let retry_after = window_start + window_secs - now;
+                    let res = HttpResponse::TooManyRequests()
+                        .insert_header((RETRY_AFTER, retry_after.to_string()))
+                        .json(DefaultError {
+                            message: format!("Too many requests, retry in {} seconds", retry_after),
+                            error_code: "429".to_string(),
+                        });
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("rate limit store failed, letting {} through: {}", group.name(), e),
+            }
+            service.call(req).await.map(|res| res.map_into_left_body())
+        })
+    }
+}
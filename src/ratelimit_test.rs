@@ -0,0 +1,131 @@
+#[test]
+fn test_route_group() {
+    use crate::ratelimit::{route_group, RouteGroup};
+    use actix_web::http::Method;
+
+    assert_eq!(
+        route_group(&Method::POST, "/api/v1/events/{event_id}/msgs"),
+        Some(RouteGroup::Comments)
+    );
+    assert_eq!(route_group(&Method::POST, "/api/v1/events"), Some(RouteGroup::EventCreation));
+    assert_eq!(
+        route_group(&Method::POST, "/api/v1/events/{event_id}/clone"),
+        Some(RouteGroup::EventCreation)
+    );
+    assert_eq!(route_group(&Method::POST, "/api/v1/login"), Some(RouteGroup::Auth));
+    assert_eq!(route_group(&Method::GET, "/api/v1/events"), None);
+    assert_eq!(route_group(&Method::POST, "/api/v1/events/{event_id}/leave"), None);
+}
+
+#[actix_web::test]
+async fn test_memory_store_windows() {
+    use crate::ratelimit::{MemoryStore, RateLimitStore};
+
+    let store = MemoryStore::default();
+    assert_eq!(store.hit("auth:ip:1", 60, 60).await.unwrap(), 1);
+    assert_eq!(store.hit("auth:ip:1", 60, 60).await.unwrap(), 2);
+    assert_eq!(store.hit("auth:ip:2", 60, 60).await.unwrap(), 1);
+    // A new window starts over.
+    assert_eq!(store.hit("auth:ip:1", 120, 60).await.unwrap(), 1);
+}
+
+#[actix_web::test]
+async fn test_rate_limit() {
+    use crate::ratelimit::{MemoryStore, RateLimit, RateLimits};
+    use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+    use actix_web::cookie::{Cookie, Key};
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::Arc;
+
+    let limits = RateLimits {
+        window_secs: 3600,
+        comments: 2,
+        events: 1,
+        auth: 0,
+    };
+    let app = test::init_service(
+        App::new()
+            .wrap(RateLimit::new(Arc::new(MemoryStore::default()), limits, false))
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                    .cookie_name("session".to_string())
+                    .build(),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .route(
+                        "/login",
+                        web::post().to(|session: Session| async move {
+                            session.insert("user_id", uuid::Uuid::new_v4()).unwrap();
+                            HttpResponse::Ok().finish()
+                        }),
+                    )
+                    .route("/events", web::post().to(|| async { HttpResponse::Ok().finish() }))
+                    .route(
+                        "/events/{event_id}/msgs",
+                        web::post().to(|| async { HttpResponse::Ok().finish() }),
+                    )
+                    .route(
+                        "/events/{event_id}/msgs",
+                        web::get().to(|| async { HttpResponse::Ok().finish() }),
+                    ),
+            ),
+    )
+    .await;
+    let peer = "203.0.113.7:4000".parse().unwrap();
+
+    // Per IP when logged out; the limit is per route group, not per path.
+    for event_id in 1..=2 {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/events/{}/msgs", event_id))
+            .peer_addr(peer)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    let req = test::TestRequest::post()
+        .uri("/api/v1/events/3/msgs")
+        .peer_addr(peer)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status().as_u16(), 429);
+    let retry_after: u64 = res.headers().get(RETRY_AFTER).unwrap().to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 3600);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["error_code"], "429");
+
+    // Reads, other groups and other clients are unaffected.
+    let req = test::TestRequest::get().uri("/api/v1/events/3/msgs").peer_addr(peer).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post().uri("/api/v1/events").peer_addr(peer).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/events/3/msgs")
+        .peer_addr("203.0.113.8:4000".parse().unwrap())
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    // A limit of 0 turns the group off.
+    for _ in 0..3 {
+        let req = test::TestRequest::post().uri("/api/v1/login").peer_addr(peer).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    // Per user when logged in, even from the same IP.
+    let req = test::TestRequest::post().uri("/api/v1/login").peer_addr(peer).to_request();
+    let res = test::call_service(&app, req).await;
+    let header = res.headers().get("set-cookie").unwrap().to_str().unwrap().to_string();
+    let cookie = Cookie::parse_encoded(header).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/events")
+        .peer_addr(peer)
+        .cookie(cookie.clone())
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/events")
+        .peer_addr(peer)
+        .cookie(cookie)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 429);
+}
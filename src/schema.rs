@@ -1,12 +1,67 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+}
+
+diesel::table! {
+    attachments (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Text,
+        event_id -> Nullable<Uuid>,
+        comment_id -> Nullable<Uuid>,
+        content_type -> Text,
+        size -> Int8,
+        storage_key -> Text,
+        thumbnail_key -> Nullable<Text>,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        original_name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
+    categories (slug) {
+        slug -> Text,
+        name -> Text,
+        icon -> Text,
+        sort_order -> Int4,
+        parent -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    email_outbox (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Text,
+        event_id -> Nullable<Uuid>,
+        payload -> Text,
+        status -> Text,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        run_at -> Timestamp,
+        created_at -> Timestamp,
+        sent_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     event_comments (id) {
         id -> Uuid,
         event_id -> Uuid,
         user_id -> Uuid,
         content -> Text,
         created_at -> Timestamp,
+        search_vector -> Nullable<Tsvector>,
     }
 }
 
@@ -15,10 +70,46 @@ diesel::table! {
         event_id -> Uuid,
         user_id -> Uuid,
         amount -> Int8,
+        payment_status -> Text,
+        checkin_token -> Nullable<Text>,
+        checked_in_at -> Nullable<Timestamp>,
+        checked_in_by -> Nullable<Uuid>,
+        joined_at -> Nullable<Timestamp>,
     }
 }
 
 diesel::table! {
+    event_templates (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        title -> Text,
+        name -> Text,
+        description -> Text,
+        category -> Text,
+        min_amount -> Int8,
+        max_amount -> Int8,
+        currency -> Text,
+        duration_secs -> Int8,
+        address -> Nullable<Text>,
+        venue -> Nullable<Text>,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        tags -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    event_tags (event_id, tag) {
+        event_id -> Uuid,
+        tag -> Text,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     events (id) {
         id -> Uuid,
         name -> Text,
@@ -30,6 +121,90 @@ diesel::table! {
         max_amount -> Int8,
         user_id -> Uuid,
         established -> Bool,
+        cancelled -> Bool,
+        currency -> Text,
+        reminder_sent_at -> Nullable<Timestamp>,
+        expired -> Bool,
+        deleted_at -> Nullable<Timestamp>,
+        search_vector -> Nullable<Tsvector>,
+        address -> Nullable<Text>,
+        venue -> Nullable<Text>,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    job_schedules (name) {
+        name -> Text,
+        cron -> Text,
+        next_run_at -> Timestamp,
+        last_run_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Uuid,
+        kind -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        locked_by -> Nullable<Text>,
+        locked_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    notification_preferences (user_id, event_id, kind) {
+        user_id -> Uuid,
+        event_id -> Uuid,
+        kind -> Text,
+        enabled -> Bool,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        event_id -> Uuid,
+        kind -> Text,
+        message -> Text,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        emailed -> Bool,
+    }
+}
+
+diesel::table! {
+    payment_webhook_events (provider, event_ref) {
+        provider -> Text,
+        event_ref -> Text,
+        received_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    payments (id) {
+        id -> Uuid,
+        event_id -> Uuid,
+        user_id -> Uuid,
+        amount -> Int8,
+        kind -> Text,
+        method -> Text,
+        reference -> Text,
+        status -> Text,
+        paid_at -> Timestamp,
+        created_at -> Timestamp,
+        confirmed_at -> Nullable<Timestamp>,
+        provider_ref -> Nullable<Text>,
+        refund_of -> Nullable<Uuid>,
     }
 }
 
@@ -44,18 +219,78 @@ diesel::table! {
         phone -> Varchar,
         avatar -> Text,
         guid -> Text,
+        is_admin -> Bool,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        endpoint_id -> Uuid,
+        event_type -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Int4,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        run_at -> Timestamp,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhook_endpoints (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        event_id -> Nullable<Uuid>,
+        url -> Text,
+        secret -> Text,
+        event_types -> Array<Text>,
+        active -> Bool,
+        created_at -> Timestamp,
     }
 }
 
+diesel::joinable!(attachments -> event_comments (comment_id));
+diesel::joinable!(attachments -> events (event_id));
+diesel::joinable!(attachments -> users (user_id));
+diesel::joinable!(email_outbox -> events (event_id));
+diesel::joinable!(email_outbox -> users (user_id));
 diesel::joinable!(event_comments -> events (event_id));
 diesel::joinable!(event_comments -> users (user_id));
 diesel::joinable!(event_members -> events (event_id));
 diesel::joinable!(event_members -> users (user_id));
+diesel::joinable!(event_tags -> events (event_id));
+diesel::joinable!(event_templates -> categories (category));
+diesel::joinable!(event_templates -> users (user_id));
+diesel::joinable!(events -> categories (category));
 diesel::joinable!(events -> users (user_id));
+diesel::joinable!(notification_preferences -> users (user_id));
+diesel::joinable!(notifications -> events (event_id));
+diesel::joinable!(notifications -> users (user_id));
+diesel::joinable!(payments -> events (event_id));
+diesel::joinable!(payments -> users (user_id));
+diesel::joinable!(webhook_deliveries -> webhook_endpoints (endpoint_id));
+diesel::joinable!(webhook_endpoints -> events (event_id));
+diesel::joinable!(webhook_endpoints -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    attachments,
+    categories,
+    email_outbox,
     event_comments,
     event_members,
+    event_tags,
+    event_templates,
     events,
+    job_schedules,
+    jobs,
+    notification_preferences,
+    notifications,
+    payment_webhook_events,
+    payments,
     users,
+    webhook_deliveries,
+    webhook_endpoints,
 );
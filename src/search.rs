@@ -0,0 +1,190 @@
+use chrono::NaiveDateTime;
+
+use crate::email::templates::escape_html;
+use crate::models::{
+    CommentSearchHit, CommentSearchRow, EventSearchHit, EventSearchRow, SearchHighlights,
+    SearchQuery,
+};
+
+const SNIPPET_CHARS: usize = 160;
+/// How much text to keep before the first hit in a snippet.
+const SNIPPET_LEAD_CHARS: usize = 40;
+
+pub struct SearchFilter {
+    pub query: String,
+    pub pattern: String,
+    pub substring: bool,
+    pub category: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub limit: i64,
+}
+
+impl SearchFilter {
+    pub fn new(query: &SearchQuery, limit: i64) -> SearchFilter {
+        let q = query.q.trim();
+        SearchFilter {
+            query: q.to_string(),
+            pattern: like_pattern(q),
+            substring: has_unsegmented_script(q),
+            category: query.category.clone().filter(|c| !c.is_empty()),
+            from: query.from,
+            to: query.to,
+            limit,
+        }
+    }
+}
+
+/// The words of a query, lowercased and deduplicated. Matches what the
+/// 'simple' text search configuration splits on closely enough for
+/// highlighting.
+pub fn terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if !word.is_empty() && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Chinese, Japanese and Korean text has no spaces between words, so the
+/// tokenizer sees a whole sentence as one word and would never match a
+/// search for part of it.
+pub fn has_unsegmented_script(query: &str) -> bool {
+    query.chars().any(|c| {
+        matches!(c,
+            '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}'
+            | '\u{f900}'..='\u{faff}')
+    })
+}
+
+/// `%query%` for ILIKE with the wildcard characters in the query escaped.
+pub fn like_pattern(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 2);
+    pattern.push('%');
+    for c in query.chars() {
+        if c == '\\' || c == '%' || c == '_' {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Byte ranges in `text` where any of `terms` occurs, compared case
+/// insensitively, without overlaps.
+fn find_matches(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let hit = terms.iter().filter_map(|t| match_len(rest, t)).max();
+        match hit {
+            Some(len) => {
+                matches.push((pos, pos + len));
+                pos += len;
+            }
+            None => {
+                pos += rest.chars().next().map_or(1, |c| c.len_utf8());
+            }
+        }
+    }
+    matches
+}
+
+/// Length in bytes of `text`'s prefix equal to `term` ignoring case.
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut len = 0;
+    let mut chars = text.chars();
+    for t in term.chars() {
+        let c = chars.next()?;
+        if !c.to_lowercase().eq(t.to_lowercase()) {
+            return None;
+        }
+        len += c.len_utf8();
+    }
+    if len == 0 { None } else { Some(len) }
+}
+
+/// An excerpt of `text` around the first hit with every hit wrapped in
+/// `<mark>`, or `None` if nothing matched. At most `max_chars` characters
+/// of the original text are kept.
+pub fn highlight(text: &str, terms: &[String], max_chars: usize) -> Option<String> {
+    let matches = find_matches(text, terms);
+    let first = matches.first()?.0;
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let first_char = chars.iter().position(|(i, _)| *i == first).unwrap_or(0);
+    let start_char = if chars.len() <= max_chars {
+        0
+    } else {
+        first_char
+            .saturating_sub(SNIPPET_LEAD_CHARS)
+            .min(chars.len() - max_chars)
+    };
+    let end_char = start_char.saturating_add(max_chars).min(chars.len());
+    let start = chars[start_char].0;
+    let end = chars.get(end_char).map_or(text.len(), |(i, _)| *i);
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut pos = start;
+    for (m_start, m_end) in matches {
+        if m_end <= start || m_start >= end {
+            continue;
+        }
+        let (m_start, m_end) = (m_start.max(start), m_end.min(end));
+        out.push_str(&escape_html(&text[pos..m_start]));
+        out.push_str("<mark>");
+        out.push_str(&escape_html(&text[m_start..m_end]));
+        out.push_str("</mark>");
+        pos = m_end;
+    }
+    out.push_str(&escape_html(&text[pos..end]));
+    if end < text.len() {
+        out.push('…');
+    }
+    Some(out)
+}
+
+pub fn event_hit(row: EventSearchRow, terms: &[String]) -> EventSearchHit {
+    let highlights = SearchHighlights {
+        name: highlight(&row.name, terms, usize::MAX),
+        category: highlight(&row.category, terms, usize::MAX),
+        description: highlight(&row.description, terms, SNIPPET_CHARS),
+    };
+    EventSearchHit {
+        id: row.id,
+        name: row.name,
+        category: row.category,
+        start_time: row.start_time,
+        end_time: row.end_time,
+        established: row.established,
+        cancelled: row.cancelled,
+        expired: row.expired,
+        rank: row.rank,
+        highlights,
+    }
+}
+
+pub fn comment_hit(row: CommentSearchRow, terms: &[String]) -> CommentSearchHit {
+    let snippet = highlight(&row.content, terms, SNIPPET_CHARS)
+        .unwrap_or_else(|| escape_html(&row.content.chars().take(SNIPPET_CHARS).collect::<String>()));
+    CommentSearchHit {
+        id: row.id,
+        event_id: row.event_id,
+        event_name: row.event_name,
+        author: row.author,
+        snippet,
+        created_at: row.created_at,
+        rank: row.rank,
+    }
+}
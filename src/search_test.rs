@@ -0,0 +1,37 @@
+#[test]
+fn test_search_terms_and_patterns() {
+    use crate::search::{has_unsegmented_script, like_pattern, terms};
+
+    assert_eq!(terms("  Ramen, ramen & Tonkotsu! "), vec!["ramen", "tonkotsu"]);
+    assert!(terms("--- ").is_empty());
+    assert_eq!(like_pattern("50%_off\\"), "%50\\%\\_off\\\\%");
+    assert!(has_unsegmented_script("台北拉麵"));
+    assert!(has_unsegmented_script("ラーメン"));
+    assert!(!has_unsegmented_script("ramen night"));
+}
+
+#[test]
+fn test_highlight() {
+    use crate::search::{highlight, terms};
+
+    let t = terms("ramen");
+    assert_eq!(
+        highlight("Ramen & <b>gyoza</b> night", &t, 100),
+        Some("<mark>Ramen</mark> &amp; &lt;b&gt;gyoza&lt;/b&gt; night".to_string())
+    );
+    assert_eq!(highlight("Sushi night", &t, 100), None);
+
+    let t = terms("拉麵");
+    assert_eq!(
+        highlight("週末去吃拉麵吧", &t, 100),
+        Some("週末去吃<mark>拉麵</mark>吧".to_string())
+    );
+
+    // Long text is cut around the first hit.
+    let long = format!("{} ramen {}", "a ".repeat(100), "b ".repeat(100));
+    let snippet = highlight(&long, &terms("ramen"), 60).unwrap();
+    assert!(snippet.starts_with('…'));
+    assert!(snippet.ends_with('…'));
+    assert!(snippet.contains("<mark>ramen</mark>"));
+    assert!(snippet.chars().count() <= 60 + 2 + "<mark></mark>".len());
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::config::Config;
+
+pub mod local;
+pub mod s3;
+
+mod storage_test;
+
+#[derive(Debug, PartialEq)]
+pub enum StorageError {
+    NotFound,
+    InvalidKey(String),
+    Backend(String),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "object not found"),
+            StorageError::InvalidKey(key) => write!(f, "invalid object key {}", key),
+            StorageError::Backend(msg) => write!(f, "storage backend failed: {}", msg),
+        }
+    }
+}
+
+/// Blob store for uploaded files. Keys are generated by us (see
+/// `uploads::storage_key`) and only use `[a-z0-9-/_.]`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('/')
+        && !key.split('/').any(|part| part.is_empty() || part == "." || part == "..")
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-/_.".contains(c))
+}
+
+/// Only call on a validated config.
+pub fn storage_from_config(config: &Config) -> Arc<dyn Storage> {
+    match config.storage_backend.as_str() {
+        "s3" => Arc::new(s3::S3Storage::new(
+            config.s3_endpoint.clone(),
+            config.s3_bucket.clone(),
+            config.s3_region.clone(),
+            config.s3_access_key.clone(),
+            config.s3_secret_key.clone(),
+        )),
+        _ => Arc::new(local::LocalStorage::new(config.storage_dir.clone())),
+    }
+}
@@ -0,0 +1,82 @@
+pub const MAX_TAGS: usize = 10;
+const MAX_TAG_CHARS: usize = 32;
+
+/// Lowercases a tag and joins its words with `-`, so "#Weekend Trip" and
+/// "weekend-trip" end up the same. Letters and digits of any script are
+/// kept; anything else is rejected.
+pub fn normalize_tag(raw: &str) -> Result<String, String> {
+    let trimmed = raw.trim().trim_start_matches('#');
+    let mut tag = String::with_capacity(trimmed.len());
+    for c in trimmed.chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            tag.push(c);
+        } else if c.is_whitespace() || c == '-' || c == '_' {
+            if !tag.is_empty() && !tag.ends_with('-') {
+                tag.push('-');
+            }
+        } else {
+            return Err(format!("Tag {} should only use letters, digits and dashes", raw.trim()));
+        }
+    }
+    let tag = tag.trim_end_matches('-').to_string();
+    if tag.is_empty() {
+        return Err("Tags can't be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_CHARS {
+        return Err(format!("Tag {} is longer than {} characters", tag, MAX_TAG_CHARS));
+    }
+    Ok(tag)
+}
+
+/// Normalizes and dedupes tags from a create or patch, keeping their order.
+pub fn normalize_tags(raw: &[String]) -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = Vec::with_capacity(raw.len());
+    for r in raw {
+        let tag = normalize_tag(r)?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.len() > MAX_TAGS {
+        return Err(format!("An event can have at most {} tags", MAX_TAGS));
+    }
+    Ok(tags)
+}
+
+/// `tags=` filter of the events listing.
+#[derive(Debug, PartialEq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    /// Require every tag instead of any of them.
+    pub all: bool,
+}
+
+impl TagFilter {
+    pub fn parse(tags: &str, mode: Option<&str>) -> Result<Option<TagFilter>, String> {
+        let all = match mode.unwrap_or("any") {
+            "any" => false,
+            "all" => true,
+            other => return Err(format!("tags_mode should be any or all, not {}", other)),
+        };
+        let raw: Vec<String> = tags
+            .split(',')
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| t.to_string())
+            .collect();
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TagFilter {
+            tags: normalize_tags(&raw)?,
+            all,
+        }))
+    }
+
+    pub fn matches(&self, event_tags: &[String]) -> bool {
+        if self.all {
+            self.tags.iter().all(|t| event_tags.contains(t))
+        } else {
+            self.tags.iter().any(|t| event_tags.contains(t))
+        }
+    }
+}
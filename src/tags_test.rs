@@ -0,0 +1,52 @@
+#[test]
+fn test_normalize_tag() {
+    use crate::tags::normalize_tag;
+
+    assert_eq!(normalize_tag("vegan"), Ok("vegan".to_string()));
+    assert_eq!(normalize_tag("  #Weekend Trip "), Ok("weekend-trip".to_string()));
+    assert_eq!(normalize_tag("bulk__buy -- deals"), Ok("bulk-buy-deals".to_string()));
+    assert_eq!(normalize_tag("素食"), Ok("素食".to_string()));
+    assert_eq!(normalize_tag("Café"), Ok("café".to_string()));
+    assert!(normalize_tag("").is_err());
+    assert!(normalize_tag(" # ").is_err());
+    assert!(normalize_tag("100%").is_err());
+    assert!(normalize_tag("a_b%").is_err());
+    assert!(normalize_tag(&"a".repeat(33)).is_err());
+}
+
+#[test]
+fn test_normalize_tags() {
+    use crate::tags::{normalize_tags, MAX_TAGS};
+
+    let tags = normalize_tags(&[
+        "Vegan".to_string(),
+        "weekend".to_string(),
+        "#vegan".to_string(),
+    ]);
+    assert_eq!(tags, Ok(vec!["vegan".to_string(), "weekend".to_string()]));
+
+    let too_many: Vec<String> = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+    assert!(normalize_tags(&too_many).is_err());
+    assert_eq!(normalize_tags(&[]), Ok(vec![]));
+}
+
+#[test]
+fn test_tag_filter() {
+    use crate::tags::TagFilter;
+
+    assert_eq!(TagFilter::parse("", None), Ok(None));
+    assert_eq!(TagFilter::parse(" , ", Some("all")), Ok(None));
+    assert!(TagFilter::parse("vegan", Some("some")).is_err());
+    assert!(TagFilter::parse("vegan,100%", None).is_err());
+
+    let event_tags = vec!["bulk".to_string(), "vegan".to_string()];
+    let any = TagFilter::parse("Vegan, weekend", None).unwrap().unwrap();
+    assert_eq!(any.tags, vec!["vegan".to_string(), "weekend".to_string()]);
+    assert!(any.matches(&event_tags));
+    assert!(!any.matches(&[]));
+
+    let all = TagFilter::parse("vegan,weekend", Some("all")).unwrap().unwrap();
+    assert!(!all.matches(&event_tags));
+    let all = TagFilter::parse("vegan,bulk", Some("all")).unwrap().unwrap();
+    assert!(all.matches(&event_tags));
+}
@@ -0,0 +1,142 @@
+use diesel::connection::{Instrumentation, InstrumentationEvent};
+use diesel::pg::PgConnection;
+use diesel::r2d2::CustomizeConnection;
+use diesel::Connection;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TraceError;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, Sampler, Tracer};
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const SERVICE_NAME: &str = "o2gather-backend";
+pub const DEFAULT_SAMPLE_RATIO: f64 = 1.0;
+
+pub struct TraceSettings {
+    /// OTLP/gRPC collector, e.g. `http://localhost:4317`.
+    pub endpoint: String,
+    /// Share of new traces to keep, 0.0 to 1.0. Incoming `traceparent`
+    /// headers decide for their own trace.
+    pub sample_ratio: f64,
+}
+
+pub fn sampler(ratio: f64) -> Sampler {
+    Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio)))
+}
+
+/// W3C `traceparent`/`tracestate`, for both directions.
+pub fn init_propagator() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+}
+
+/// Batches spans on the actix runtime and sends them to the collector.
+/// Also installs the global tracer provider, flushed by
+/// `global::shutdown_tracer_provider`.
+pub fn otlp_tracer(settings: &TraceSettings) -> Result<Tracer, TraceError> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(&settings.endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config()
+                .with_sampler(sampler(settings.sample_ratio))
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", SERVICE_NAME)])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+}
+
+struct HeaderExtractor<'a>(&'a actix_web::http::header::HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+/// Trace context sent by the caller, if any.
+pub fn remote_context(headers: &actix_web::http::header::HeaderMap) -> opentelemetry::Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl<'a> Injector for HeaderInjector<'a> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Headers continuing the current trace, for outgoing requests.
+pub fn trace_headers() -> reqwest::header::HeaderMap {
+    let context = tracing::Span::current().context();
+    let mut headers = reqwest::header::HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+/// The SQL of a query without its bind values, which may hold personal
+/// data.
+pub fn statement(debug_query: &str) -> &str {
+    match debug_query.find(" -- binds: ") {
+        Some(end) => &debug_query[..end],
+        None => debug_query,
+    }
+}
+
+/// One span per query, under whatever `db.rs` span is current.
+#[derive(Default)]
+pub struct QueryTracing {
+    span: Option<tracing::Span>,
+}
+
+impl Instrumentation for QueryTracing {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                let query = query.to_string();
+                self.span = Some(tracing::info_span!(
+                    "db.query",
+                    otel.kind = "client",
+                    db.system = "postgresql",
+                    db.statement = statement(&query),
+                    otel.status_code = tracing::field::Empty,
+                    error = tracing::field::Empty,
+                ));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let (Some(span), Some(error)) = (self.span.take(), error) {
+                    span.record("otel.status_code", "ERROR");
+                    span.record("error", error.to_string().as_str());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Pool hook installing `QueryTracing` on every new connection.
+#[derive(Debug)]
+pub struct TracedConnections;
+
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for TracedConnections {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.set_instrumentation(QueryTracing::default());
+        Ok(())
+    }
+}
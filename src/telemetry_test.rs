@@ -0,0 +1,95 @@
+#[cfg(test)]
+use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+#[cfg(test)]
+use opentelemetry_sdk::trace::TracerProvider;
+
+#[cfg(test)]
+fn test_provider() -> (TracerProvider, InMemorySpanExporter) {
+    use crate::telemetry::{init_propagator, sampler};
+    use opentelemetry_sdk::trace;
+
+    init_propagator();
+    let exporter = InMemorySpanExporter::default();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .with_config(trace::config().with_sampler(sampler(1.0)))
+        .build();
+    (provider, exporter)
+}
+
+#[test]
+fn test_statement() {
+    use crate::telemetry::statement;
+
+    assert_eq!(
+        statement("SELECT * FROM users WHERE guid = $1 -- binds: [\"secret\"]"),
+        "SELECT * FROM users WHERE guid = $1"
+    );
+    assert_eq!(statement("SELECT 1"), "SELECT 1");
+}
+
+#[actix_web::test]
+async fn test_request_span_continues_remote_trace() {
+    use crate::logging::RequestLogging;
+    use actix_web::{test, web, App, HttpResponse};
+    use opentelemetry::trace::TracerProvider as _;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let (provider, exporter) = test_provider();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestLogging)
+            .route("/events/{event_id}", web::get().to(|| async { HttpResponse::Ok().finish() })),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/events/1")
+        .insert_header((
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    provider.force_flush();
+    let spans = exporter.get_finished_spans().unwrap();
+    let span = spans
+        .iter()
+        .find(|span| span.name == "GET /events/{event_id}")
+        .unwrap();
+    assert_eq!(
+        span.span_context.trace_id().to_string(),
+        "4bf92f3577b34da6a3ce929d0e0e4736"
+    );
+    assert_eq!(span.parent_span_id.to_string(), "00f067aa0ba902b7");
+}
+
+#[test]
+fn test_trace_headers() {
+    use crate::telemetry::trace_headers;
+    use opentelemetry::trace::TracerProvider as _;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let (provider, exporter) = test_provider();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+    let headers = tracing::subscriber::with_default(subscriber, || {
+        let _span = tracing::info_span!("webhook").entered();
+        trace_headers()
+    });
+    provider.force_flush();
+
+    let traceparent = headers.get("traceparent").unwrap().to_str().unwrap().to_string();
+    let spans = exporter.get_finished_spans().unwrap();
+    let span = spans.iter().find(|span| span.name == "webhook").unwrap();
+    assert_eq!(
+        traceparent,
+        format!("00-{}-{}-01", span.span_context.trace_id(), span.span_context.span_id())
+    );
+}
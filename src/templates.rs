@@ -0,0 +1,82 @@
+use chrono::{Duration, NaiveDateTime};
+
+use crate::models::{EventLocation, EventTemplate, EventWithMembers, NewEvent, NewEventTemplate};
+
+const MAX_TITLE_CHARS: usize = 100;
+/// Longest event a template or clone may describe.
+const MAX_DURATION_SECS: i64 = 366 * 24 * 60 * 60;
+
+pub fn duration_check(duration_secs: i64) -> Result<(), String> {
+    if !(0..=MAX_DURATION_SECS).contains(&duration_secs) {
+        return Err(format!("Duration should be between 0 and {} seconds", MAX_DURATION_SECS));
+    }
+    Ok(())
+}
+
+pub fn title_check(title: &str) -> Result<(), String> {
+    let length = title.trim().chars().count();
+    if length == 0 || length > MAX_TITLE_CHARS {
+        return Err(format!("Template title should be 1 to {} characters", MAX_TITLE_CHARS));
+    }
+    Ok(())
+}
+
+/// A new event like `event` starting at `start_time`: same name,
+/// description, category, amounts and duration.
+pub fn clone_event(event: &EventWithMembers, start_time: NaiveDateTime) -> NewEvent {
+    NewEvent {
+        name: event.name.clone(),
+        description: event.description.clone(),
+        category: event.category.clone(),
+        start_time,
+        end_time: start_time + (event.end_time - event.start_time),
+        min_amount: event.min_amount.minor,
+        max_amount: event.max_amount.minor,
+        user_id: event.user_id,
+        currency: event.currency.clone(),
+        location: Default::default(),
+    }
+}
+
+/// The event a saved template describes, starting at `start_time`.
+pub fn event_from_template(template: &EventTemplate, start_time: NaiveDateTime) -> NewEvent {
+    NewEvent {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        category: template.category.clone(),
+        start_time,
+        end_time: start_time + Duration::seconds(template.duration_secs),
+        min_amount: template.min_amount,
+        max_amount: template.max_amount,
+        user_id: template.user_id,
+        currency: template.currency.clone(),
+        location: EventLocation {
+            address: template.address.clone(),
+            venue: template.venue.clone(),
+            latitude: template.latitude,
+            longitude: template.longitude,
+        },
+    }
+}
+
+/// Same as `event_from_template` for a template that isn't saved yet, so
+/// it can go through the checks `create_event` runs.
+pub fn event_from_new_template(template: &NewEventTemplate, start_time: NaiveDateTime) -> NewEvent {
+    NewEvent {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        category: template.category.clone(),
+        start_time,
+        end_time: start_time + Duration::seconds(template.duration_secs),
+        min_amount: template.min_amount,
+        max_amount: template.max_amount,
+        user_id: template.user_id,
+        currency: template.currency.clone(),
+        location: EventLocation {
+            address: template.address.clone(),
+            venue: template.venue.clone(),
+            latitude: template.latitude,
+            longitude: template.longitude,
+        },
+    }
+}
@@ -0,0 +1,52 @@
+#[test]
+fn test_duration_and_title_check() {
+    use crate::templates::{duration_check, title_check};
+
+    assert!(duration_check(0).is_ok());
+    assert!(duration_check(2 * 60 * 60).is_ok());
+    assert!(duration_check(-1).is_err());
+    assert!(duration_check(400 * 24 * 60 * 60).is_err());
+
+    assert!(title_check("Weekly ramen run").is_ok());
+    assert!(title_check("  ").is_err());
+    assert!(title_check(&"t".repeat(101)).is_err());
+}
+
+#[test]
+fn test_event_from_template() {
+    use crate::models::EventTemplate;
+    use crate::templates::event_from_template;
+    use chrono::{Duration, NaiveDate};
+    use uuid::Uuid;
+
+    let template = EventTemplate {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        title: "Weekly ramen run".to_string(),
+        name: "Ramen".to_string(),
+        description: "Group order".to_string(),
+        category: "food".to_string(),
+        min_amount: 100,
+        max_amount: 1000,
+        currency: "TWD".to_string(),
+        duration_secs: 90 * 60,
+        address: Some("Taipei".to_string()),
+        venue: None,
+        latitude: Some(25.03),
+        longitude: Some(121.56),
+        tags: vec!["weekly".to_string()],
+        created_at: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+    };
+    let start = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap().and_hms_opt(18, 0, 0).unwrap();
+
+    let event = event_from_template(&template, start);
+    assert_eq!(event.name, "Ramen");
+    assert_eq!(event.category, "food");
+    assert_eq!(event.user_id, template.user_id);
+    assert_eq!(event.start_time, start);
+    assert_eq!(event.end_time, start + Duration::minutes(90));
+    assert_eq!((event.min_amount, event.max_amount), (100, 1000));
+    assert_eq!(event.currency, "TWD");
+    assert_eq!(event.location.address.as_deref(), Some("Taipei"));
+    assert_eq!(event.location.latitude, Some(25.03));
+}
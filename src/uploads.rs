@@ -0,0 +1,213 @@
+use chrono::NaiveDateTime;
+use image::io::Reader as ImageReader;
+use image::{io::Limits, ImageOutputFormat};
+use std::io::Cursor;
+use uuid::Uuid;
+
+use crate::models::{
+    Attachment, AttachmentView, ATTACHMENT_AVATAR, ATTACHMENT_COMMENT, ATTACHMENT_EVENT_COVER, ATTACHMENT_EVENT_GALLERY,
+};
+use crate::signing::{hmac_hex, hmac_verify};
+
+pub const VARIANT_ORIGINAL: &str = "original";
+pub const VARIANT_THUMBNAIL: &str = "thumbnail";
+
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+const MAX_IMAGE_DIMENSION: u32 = 8000;
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 80;
+/// How long a download link handed out by the API stays valid.
+pub const DOWNLOAD_URL_TTL_SECS: i64 = 60 * 60;
+
+const IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
+const DOCUMENT_TYPES: &[&str] = &["application/pdf"];
+
+pub struct UploadSettings {
+    pub public_url: String,
+    pub secret: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UploadError {
+    Empty,
+    TooLarge(usize),
+    UnsupportedType(String),
+    TypeMismatch(String, String),
+    InvalidImage(String),
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::Empty => write!(f, "File is empty"),
+            UploadError::TooLarge(max) => write!(f, "File should be at most {} bytes", max),
+            UploadError::UnsupportedType(t) => write!(f, "Unsupported file type {}", t),
+            UploadError::TypeMismatch(declared, actual) => {
+                write!(f, "File was sent as {} but is {}", declared, actual)
+            }
+            UploadError::InvalidImage(e) => write!(f, "Invalid image: {}", e),
+        }
+    }
+}
+
+/// Largest upload accepted for an attachment kind.
+pub fn max_bytes(kind: &str) -> usize {
+    if kind == ATTACHMENT_AVATAR {
+        MAX_AVATAR_BYTES
+    } else {
+        MAX_IMAGE_BYTES
+    }
+}
+
+fn allowed_types(kind: &str) -> Vec<&'static str> {
+    match kind {
+        ATTACHMENT_COMMENT => IMAGE_TYPES.iter().chain(DOCUMENT_TYPES).copied().collect(),
+        ATTACHMENT_EVENT_COVER | ATTACHMENT_EVENT_GALLERY | ATTACHMENT_AVATAR => IMAGE_TYPES.to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Content type from the file's magic bytes. The client's declared type is
+/// never trusted on its own.
+pub fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("image/png")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    }
+}
+
+pub struct ProcessedUpload {
+    pub content_type: &'static str,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// JPEG thumbnail, for images only.
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+/// Validates an upload for `kind` and renders a thumbnail for images.
+/// Decoding is bounded so a small file can't expand into a huge bitmap.
+pub fn process(kind: &str, declared: Option<&str>, data: &[u8]) -> Result<ProcessedUpload, UploadError> {
+    if data.is_empty() {
+        return Err(UploadError::Empty);
+    }
+    if data.len() > max_bytes(kind) {
+        return Err(UploadError::TooLarge(max_bytes(kind)));
+    }
+    let content_type = match sniff_content_type(data) {
+        Some(t) => t,
+        None => {
+            return Err(UploadError::UnsupportedType(
+                declared.unwrap_or("unknown").to_string(),
+            ))
+        }
+    };
+    if !allowed_types(kind).contains(&content_type) {
+        return Err(UploadError::UnsupportedType(content_type.to_string()));
+    }
+    if let Some(declared) = declared {
+        let declared = declared.split(';').next().unwrap_or("").trim().to_lowercase();
+        if declared != "application/octet-stream" && declared != content_type {
+            return Err(UploadError::TypeMismatch(declared, content_type.to_string()));
+        }
+    }
+    if !content_type.starts_with("image/") {
+        return Ok(ProcessedUpload {
+            content_type,
+            width: None,
+            height: None,
+            thumbnail: None,
+        });
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| UploadError::InvalidImage(e.to_string()))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|e| UploadError::InvalidImage(e.to_string()))?;
+
+    let mut thumbnail = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgb8()
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageOutputFormat::Jpeg(THUMBNAIL_QUALITY))
+        .map_err(|e| UploadError::InvalidImage(e.to_string()))?;
+
+    Ok(ProcessedUpload {
+        content_type,
+        width: Some(image.width() as i32),
+        height: Some(image.height() as i32),
+        thumbnail: Some(thumbnail),
+    })
+}
+
+pub fn storage_key(attachment_id: Uuid, variant: &str) -> String {
+    format!("attachments/{}/{}", attachment_id, variant)
+}
+
+fn download_payload(attachment_id: Uuid, variant: &str, expires: i64) -> String {
+    format!("{}:{}:{}", attachment_id, variant, expires)
+}
+
+pub fn signed_url(settings: &UploadSettings, attachment_id: Uuid, variant: &str, expires: i64) -> String {
+    let signature = hmac_hex(
+        &settings.secret,
+        download_payload(attachment_id, variant, expires).as_bytes(),
+    );
+    format!(
+        "{}/api/v1/files/{}?variant={}&expires={}&signature={}",
+        settings.public_url, attachment_id, variant, expires, signature
+    )
+}
+
+pub fn verify_download(
+    settings: &UploadSettings,
+    attachment_id: Uuid,
+    variant: &str,
+    expires: i64,
+    signature: &str,
+    now: i64,
+) -> bool {
+    expires >= now
+        && hmac_verify(
+            &settings.secret,
+            download_payload(attachment_id, variant, expires).as_bytes(),
+            signature,
+        )
+}
+
+/// Client view of an attachment with download links valid until `expires`.
+pub fn view(settings: &UploadSettings, attachment: Attachment, expires: NaiveDateTime) -> AttachmentView {
+    let expires_ts = expires.and_utc().timestamp();
+    AttachmentView {
+        id: attachment.id,
+        kind: attachment.kind,
+        uploaded_by: attachment.user_id,
+        comment_id: attachment.comment_id,
+        content_type: attachment.content_type,
+        size: attachment.size,
+        width: attachment.width,
+        height: attachment.height,
+        original_name: attachment.original_name,
+        url: signed_url(settings, attachment.id, VARIANT_ORIGINAL, expires_ts),
+        thumbnail_url: attachment
+            .thumbnail_key
+            .map(|_| signed_url(settings, attachment.id, VARIANT_THUMBNAIL, expires_ts)),
+        expires_at: expires,
+        created_at: attachment.created_at,
+    }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+fn png(width: u32, height: u32) -> Vec<u8> {
+    use image::{ImageOutputFormat, RgbImage};
+    use std::io::Cursor;
+
+    let mut data = Vec::new();
+    RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]))
+        .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+        .unwrap();
+    data
+}
+
+#[cfg(test)]
+fn settings() -> crate::uploads::UploadSettings {
+    crate::uploads::UploadSettings {
+        public_url: "https://api.o2gather.app".to_string(),
+        secret: b"test_secret".to_vec(),
+    }
+}
+
+#[test]
+fn test_sniff_content_type() {
+    use crate::uploads::sniff_content_type;
+
+    assert_eq!(sniff_content_type(&png(1, 1)), Some("image/png"));
+    assert_eq!(sniff_content_type(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), Some("image/jpeg"));
+    assert_eq!(sniff_content_type(b"GIF89a...."), Some("image/gif"));
+    assert_eq!(sniff_content_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "), Some("image/webp"));
+    assert_eq!(sniff_content_type(b"%PDF-1.7\n"), Some("application/pdf"));
+    assert_eq!(sniff_content_type(b"<svg xmlns="), None);
+    assert_eq!(sniff_content_type(b""), None);
+}
+
+#[test]
+fn test_process_image_makes_thumbnail() {
+    use crate::models::ATTACHMENT_EVENT_GALLERY;
+    use crate::uploads::{process, sniff_content_type};
+
+    let processed = process(ATTACHMENT_EVENT_GALLERY, Some("image/png"), &png(1280, 640)).unwrap();
+    assert_eq!(processed.content_type, "image/png");
+    assert_eq!(processed.width, Some(1280));
+    assert_eq!(processed.height, Some(640));
+
+    let thumbnail = processed.thumbnail.unwrap();
+    assert_eq!(sniff_content_type(&thumbnail), Some("image/jpeg"));
+    let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (320, 160));
+}
+
+#[test]
+fn test_process_rejects_bad_uploads() {
+    use crate::models::{ATTACHMENT_AVATAR, ATTACHMENT_COMMENT, ATTACHMENT_EVENT_COVER};
+    use crate::uploads::{max_bytes, process, UploadError};
+
+    assert_eq!(
+        process(ATTACHMENT_EVENT_COVER, Some("image/png"), b"").err(),
+        Some(UploadError::Empty)
+    );
+    // The declared type has to agree with the file contents.
+    assert_eq!(
+        process(ATTACHMENT_EVENT_COVER, Some("image/jpeg"), &png(4, 4)).err(),
+        Some(UploadError::TypeMismatch("image/jpeg".to_string(), "image/png".to_string()))
+    );
+    assert!(process(ATTACHMENT_EVENT_COVER, Some("application/octet-stream"), &png(4, 4)).is_ok());
+    assert!(process(ATTACHMENT_EVENT_COVER, None, &png(4, 4)).is_ok());
+
+    assert_eq!(
+        process(ATTACHMENT_EVENT_COVER, Some("text/html"), b"<html></html>").err(),
+        Some(UploadError::UnsupportedType("text/html".to_string()))
+    );
+    // PDFs are only allowed on comments.
+    assert_eq!(
+        process(ATTACHMENT_EVENT_COVER, Some("application/pdf"), b"%PDF-1.7\n").err(),
+        Some(UploadError::UnsupportedType("application/pdf".to_string()))
+    );
+    let pdf = process(ATTACHMENT_COMMENT, Some("application/pdf"), b"%PDF-1.7\n").unwrap();
+    assert_eq!(pdf.content_type, "application/pdf");
+    assert!(pdf.thumbnail.is_none());
+
+    // Right magic bytes but not a decodable image.
+    let mut broken = png(4, 4);
+    broken.truncate(20);
+    assert!(matches!(
+        process(ATTACHMENT_EVENT_COVER, Some("image/png"), &broken),
+        Err(UploadError::InvalidImage(_))
+    ));
+
+    let mut too_large = png(1, 1);
+    too_large.resize(max_bytes(ATTACHMENT_AVATAR) + 1, 0);
+    assert_eq!(
+        process(ATTACHMENT_AVATAR, Some("image/png"), &too_large).err(),
+        Some(UploadError::TooLarge(max_bytes(ATTACHMENT_AVATAR)))
+    );
+}
+
+#[test]
+fn test_signed_download_url() {
+    use crate::uploads::{signed_url, storage_key, verify_download, VARIANT_ORIGINAL, VARIANT_THUMBNAIL};
+    use uuid::Uuid;
+
+    let settings = settings();
+    let id = Uuid::new_v4();
+    assert_eq!(storage_key(id, VARIANT_ORIGINAL), format!("attachments/{}/original", id));
+
+    let url = signed_url(&settings, id, VARIANT_ORIGINAL, 2_000);
+    let prefix = format!("https://api.o2gather.app/api/v1/files/{}?variant=original&expires=2000&signature=", id);
+    assert!(url.starts_with(&prefix));
+    let signature = &url[prefix.len()..];
+
+    assert!(verify_download(&settings, id, VARIANT_ORIGINAL, 2_000, signature, 1_000));
+    assert!(verify_download(&settings, id, VARIANT_ORIGINAL, 2_000, signature, 2_000));
+    // Expired.
+    assert!(!verify_download(&settings, id, VARIANT_ORIGINAL, 2_000, signature, 2_001));
+    // Tampered expiry, variant or attachment.
+    assert!(!verify_download(&settings, id, VARIANT_ORIGINAL, 9_000, signature, 1_000));
+    assert!(!verify_download(&settings, id, VARIANT_THUMBNAIL, 2_000, signature, 1_000));
+    assert!(!verify_download(&settings, Uuid::new_v4(), VARIANT_ORIGINAL, 2_000, signature, 1_000));
+    assert!(!verify_download(&settings, id, VARIANT_ORIGINAL, 2_000, "not-hex", 1_000));
+}
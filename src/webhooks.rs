@@ -0,0 +1,295 @@
+use chrono::{Duration as ChronoDuration, NaiveDateTime};
+use diesel::pg::PgConnection;
+use diesel::result::Error;
+#[cfg(test)]
+use std::cell::Cell;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db;
+use crate::models::{EventWithMembers, NewWebhookDelivery, WebhookDelivery, WebhookEndpoint};
+use crate::signing::hmac_hex;
+#[cfg(test)]
+use crate::signing::hmac_verify;
+use crate::telemetry;
+
+pub const SIGNATURE_HEADER: &str = "X-O2Gather-Signature";
+pub const EVENT_HEADER: &str = "X-O2Gather-Event";
+pub const DELIVERY_HEADER: &str = "X-O2Gather-Delivery";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 50;
+const MAX_ATTEMPTS: i32 = 8;
+#[cfg(test)]
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+pub fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Only absolute http(s) URLs can be registered as endpoints.
+pub fn valid_endpoint_url(url: &str) -> bool {
+    match reqwest::Url::parse(url) {
+        Ok(u) => (u.scheme() == "http" || u.scheme() == "https") && u.host().is_some(),
+        Err(_) => false,
+    }
+}
+
+/// False for addresses a webhook must never reach: this host, private
+/// networks and link-local ones, which include cloud metadata services.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || octets[0] == 0
+                // 100.64.0.0/10, carrier-grade NAT
+                || (octets[0] == 100 && octets[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // fc00::/7 unique local, fe80::/10 link-local
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static ALLOW_PRIVATE_ENDPOINTS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Lets the calling test thread deliver to its local receiver.
+#[cfg(test)]
+pub fn allow_private_endpoints() {
+    ALLOW_PRIVATE_ENDPOINTS.with(|allow| allow.set(true));
+}
+
+#[cfg(test)]
+fn private_endpoints_allowed() -> bool {
+    ALLOW_PRIVATE_ENDPOINTS.with(|allow| allow.get())
+}
+
+#[cfg(not(test))]
+fn private_endpoints_allowed() -> bool {
+    false
+}
+
+/// `valid_endpoint_url`, plus every address the host resolves to has to be
+/// public. Checked on registration and again before each delivery, as DNS
+/// may have changed in between. Returns the checked addresses so the
+/// delivery can connect to exactly those. Blocks on the lookup.
+pub fn check_endpoint_url(url: &str) -> Result<Vec<SocketAddr>, String> {
+    if !valid_endpoint_url(url) {
+        return Err("Webhook url should be an http or https URL".to_string());
+    }
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    let addrs = parsed
+        .socket_addrs(|| None)
+        .map_err(|e| format!("Couldn't resolve webhook host: {}", e))?;
+    if addrs.is_empty() {
+        return Err("Webhook host has no addresses".to_string());
+    }
+    if !private_endpoints_allowed() && addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err("Webhook url should point to a public address".to_string());
+    }
+    Ok(addrs)
+}
+
+/// Signature header value in the form `t=<unix ts>,v1=<hex hmac>`, where
+/// the HMAC covers `"{t}.{body}"` so a captured request can't be replayed
+/// later with a fresh timestamp.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let signed = format!("{}.{}", timestamp, body);
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hmac_hex(secret.as_bytes(), signed.as_bytes())
+    )
+}
+
+/// Receiver side of `sign`, as integrators are expected to implement it.
+#[cfg(test)]
+pub fn verify(secret: &str, header: &str, body: &str, now: i64) -> bool {
+    let mut timestamp: Option<i64> = None;
+    let mut signatures: Vec<&str> = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", v)) => timestamp = v.parse::<i64>().ok(),
+            Some(("v1", v)) => signatures.push(v),
+            _ => {}
+        }
+    }
+    let timestamp = match timestamp {
+        Some(t) => t,
+        None => return false,
+    };
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return false;
+    }
+
+    let signed = format!("{}.{}", timestamp, body);
+    signatures
+        .iter()
+        .any(|s| hmac_verify(secret.as_bytes(), signed.as_bytes(), s))
+}
+
+/// Exponential backoff after a failed attempt, or `None` once the delivery
+/// has used up its attempts.
+pub fn retry_at(attempts: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if attempts + 1 >= MAX_ATTEMPTS {
+        return None;
+    }
+    Some(now + ChronoDuration::minutes(1i64 << attempts))
+}
+
+/// Public summary of an event for payloads. Members are left out since
+/// their contact details are only meant for the owner.
+pub fn event_data(event: &EventWithMembers) -> serde_json::Value {
+    serde_json::json!({
+        "id": event.id,
+        "name": event.name,
+        "category": event.category,
+        "tags": event.tags,
+        "start_time": event.start_time.and_utc().timestamp(),
+        "end_time": event.end_time.and_utc().timestamp(),
+        "min_amount": event.min_amount,
+        "max_amount": event.max_amount,
+        "amount": event.amount,
+        "established": event.established,
+        "members_count": event.members_count,
+    })
+}
+
+pub fn build_payload(
+    delivery_id: Uuid,
+    event_type: &str,
+    event_id: Uuid,
+    data: &serde_json::Value,
+) -> String {
+    serde_json::json!({
+        "id": delivery_id,
+        "type": event_type,
+        "event_id": event_id,
+        "created_at": chrono::Utc::now().timestamp(),
+        "data": data,
+    })
+    .to_string()
+}
+
+/// Queues a delivery for every endpoint subscribed to `event_type` on this
+/// event. Nothing is sent inline; the worker picks the rows up.
+pub fn enqueue(
+    conn: &mut PgConnection,
+    event_type: &str,
+    event_id: Uuid,
+    data: serde_json::Value,
+) -> Result<usize, Error> {
+    let endpoints = db::get_subscribed_webhook_endpoints(conn, event_id, event_type)?;
+    let deliveries: Vec<NewWebhookDelivery> = endpoints
+        .into_iter()
+        .map(|endpoint| {
+            let id = Uuid::new_v4();
+            NewWebhookDelivery {
+                id,
+                endpoint_id: endpoint.id,
+                event_type: event_type.to_string(),
+                payload: build_payload(id, event_type, event_id, &data),
+            }
+        })
+        .collect();
+
+    db::enqueue_webhook_deliveries(conn, &deliveries)
+}
+
+/// Like `enqueue`, but only logs failures so handlers can fire and forget.
+pub fn dispatch(conn: &mut PgConnection, event_type: &str, event_id: Uuid, data: serde_json::Value) {
+    if let Err(e) = enqueue(conn, event_type, event_id, data) {
+        log::error!("queueing {} webhooks for {} failed: {}", event_type, event_id, e);
+    }
+}
+
+/// Posts one delivery. Any 2xx counts as delivered; the status code is
+/// returned either way when the receiver answered.
+pub fn deliver(
+    delivery: &WebhookDelivery,
+    endpoint: &WebhookEndpoint,
+) -> Result<i32, (Option<i32>, String)> {
+    let addrs = check_endpoint_url(&endpoint.url).map_err(|e| (None, e))?;
+    let host = reqwest::Url::parse(&endpoint.url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default();
+    let client = client(&host, &addrs).map_err(|e| (None, e.to_string()))?;
+    let signature = sign(&endpoint.secret, chrono::Utc::now().timestamp(), &delivery.payload);
+    let response = client
+        .post(&endpoint.url)
+        .headers(telemetry::trace_headers())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, signature)
+        .header(EVENT_HEADER, &delivery.event_type)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(delivery.payload.clone())
+        .send()
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i32)
+    } else {
+        Err((Some(status.as_u16() as i32), format!("Receiver responded {}", status)))
+    }
+}
+
+pub fn send_due_deliveries(conn: &mut PgConnection) -> Result<usize, Error> {
+    let due = db::get_due_webhook_deliveries(conn, BATCH_SIZE)?;
+    let mut delivered = 0;
+    for (delivery, endpoint) in due {
+        match deliver(&delivery, &endpoint) {
+            Ok(status) => {
+                db::mark_webhook_delivered(conn, delivery.id, status)?;
+                delivered += 1;
+            }
+            Err((status, error)) => {
+                let retry = retry_at(delivery.attempts, chrono::Utc::now().naive_utc());
+                if retry.is_none() {
+                    log::warn!("giving up on webhook delivery {}: {}", delivery.id, error);
+                }
+                db::mark_webhook_failed(conn, delivery.id, status, &error, retry)?;
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+/// Client that connects `host` to `addrs` only. Letting it resolve the
+/// host again would allow DNS to hand out a private address after
+/// `check_endpoint_url` approved a public one.
+fn client(host: &str, addrs: &[SocketAddr]) -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("O2Gather-Webhooks/1.0")
+        // A redirect could point anywhere, including past `check_endpoint_url`.
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, addrs)
+        .build()
+}
@@ -0,0 +1,238 @@
+#[cfg(test)]
+struct Received {
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[cfg(test)]
+impl Received {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(test)]
+/// Accepts a single request on a random local port and answers with
+/// `status`. Returns the endpoint URL and a channel with what arrived.
+fn receiver(status: u16) -> (String, std::sync::mpsc::Receiver<Received>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (k, v) = line.split_once(':').unwrap();
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+        let length: usize = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+            .map(|(_, v)| v.parse().unwrap())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = reader.into_inner();
+        write!(stream, "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status).unwrap();
+        tx.send(Received {
+            headers,
+            body: String::from_utf8(body).unwrap(),
+        })
+        .unwrap();
+    });
+    (url, rx)
+}
+
+#[cfg(test)]
+fn fixtures(url: String) -> (crate::models::WebhookDelivery, crate::models::WebhookEndpoint) {
+    use crate::models::{WebhookDelivery, WebhookEndpoint, DELIVERY_STATUS_PENDING, WEBHOOK_MEMBER_JOINED};
+    use uuid::Uuid;
+
+    let now = chrono::Utc::now().naive_utc();
+    let event_id = Uuid::new_v4();
+    let delivery_id = Uuid::new_v4();
+    let endpoint = WebhookEndpoint {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        event_id: Some(event_id),
+        url,
+        secret: crate::webhooks::generate_secret(),
+        event_types: vec![WEBHOOK_MEMBER_JOINED.to_string()],
+        active: true,
+        created_at: now,
+    };
+    let delivery = WebhookDelivery {
+        id: delivery_id,
+        endpoint_id: endpoint.id,
+        event_type: WEBHOOK_MEMBER_JOINED.to_string(),
+        payload: crate::webhooks::build_payload(
+            delivery_id,
+            WEBHOOK_MEMBER_JOINED,
+            event_id,
+            &serde_json::json!({ "user": { "name": "test_user" } }),
+        ),
+        status: DELIVERY_STATUS_PENDING.to_string(),
+        attempts: 0,
+        response_status: None,
+        last_error: None,
+        run_at: now,
+        created_at: now,
+        delivered_at: None,
+    };
+    (delivery, endpoint)
+}
+
+#[test]
+fn test_signature_round_trip() {
+    use crate::webhooks::{sign, verify};
+
+    let now = chrono::Utc::now().timestamp();
+    let header = sign("whsec_test", now, "{\"a\":1}");
+    assert!(verify("whsec_test", &header, "{\"a\":1}", now));
+    assert!(!verify("whsec_test", &header, "{\"a\":2}", now));
+    assert!(!verify("whsec_other", &header, "{\"a\":1}", now));
+
+    let stale = sign("whsec_test", now - 3600, "{\"a\":1}");
+    assert!(!verify("whsec_test", &stale, "{\"a\":1}", now));
+    assert!(!verify("whsec_test", "v1=deadbeef", "{\"a\":1}", now));
+}
+
+#[test]
+fn test_endpoint_url_validation() {
+    use crate::webhooks::valid_endpoint_url;
+
+    assert!(valid_endpoint_url("https://hooks.example.com/o2gather"));
+    assert!(valid_endpoint_url("http://127.0.0.1:9000/hook"));
+    assert!(!valid_endpoint_url("ftp://example.com/hook"));
+    assert!(!valid_endpoint_url("not a url"));
+    assert!(!valid_endpoint_url("/relative/path"));
+}
+
+#[test]
+fn test_private_endpoints_rejected() {
+    use crate::webhooks::{check_endpoint_url, is_public_ip};
+    use std::net::IpAddr;
+
+    for ip in [
+        "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0",
+        "100.64.0.1", "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public_ip(ip.parse::<IpAddr>().unwrap()), "{} should not be public", ip);
+    }
+    for ip in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+        assert!(is_public_ip(ip.parse::<IpAddr>().unwrap()), "{} should be public", ip);
+    }
+
+    assert!(check_endpoint_url("http://127.0.0.1:9000/hook").is_err());
+    assert!(check_endpoint_url("http://localhost/hook").is_err());
+    assert!(check_endpoint_url("http://169.254.169.254/latest/meta-data").is_err());
+    assert!(check_endpoint_url("http://[::1]/hook").is_err());
+    assert!(check_endpoint_url("http://10.0.0.1/hook").is_err());
+    assert!(check_endpoint_url("https://93.184.216.34/hook").is_ok());
+    assert!(check_endpoint_url("ftp://example.com/hook").is_err());
+}
+
+#[test]
+fn test_retry_backoff() {
+    use crate::webhooks::retry_at;
+    use chrono::Duration;
+
+    let now = chrono::Utc::now().naive_utc();
+    assert_eq!(retry_at(0, now), Some(now + Duration::minutes(1)));
+    assert_eq!(retry_at(3, now), Some(now + Duration::minutes(8)));
+    assert_eq!(retry_at(7, now), None);
+}
+
+#[test]
+fn test_deliver_signed_request() {
+    use crate::webhooks::{
+        allow_private_endpoints, deliver, verify, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+    };
+
+    allow_private_endpoints();
+    let (url, rx) = receiver(200);
+    let (delivery, endpoint) = fixtures(url);
+
+    assert_eq!(deliver(&delivery, &endpoint), Ok(200));
+
+    let received = rx.recv().unwrap();
+    assert_eq!(received.body, delivery.payload);
+    assert_eq!(received.header(EVENT_HEADER), Some("member.joined"));
+    assert_eq!(received.header(DELIVERY_HEADER), Some(delivery.id.to_string().as_str()));
+    assert_eq!(received.header("content-type"), Some("application/json"));
+    let signature = received.header(SIGNATURE_HEADER).unwrap();
+    assert!(verify(
+        &endpoint.secret,
+        signature,
+        &received.body,
+        chrono::Utc::now().timestamp()
+    ));
+
+    let payload: serde_json::Value = serde_json::from_str(&received.body).unwrap();
+    assert_eq!(payload["type"], "member.joined");
+    assert_eq!(payload["data"]["user"]["name"], "test_user");
+}
+
+#[test]
+fn test_deliver_reports_receiver_errors() {
+    use crate::webhooks::{allow_private_endpoints, deliver};
+
+    allow_private_endpoints();
+    let (url, rx) = receiver(500);
+    let (delivery, endpoint) = fixtures(url);
+
+    match deliver(&delivery, &endpoint) {
+        Err((status, _)) => assert_eq!(status, Some(500)),
+        Ok(_) => panic!("a 500 response should not count as delivered"),
+    }
+    rx.recv().unwrap();
+
+    let (_, closed) = fixtures("http://127.0.0.1:1/hook".to_string());
+    match deliver(&delivery, &closed) {
+        Err((status, _)) => assert_eq!(status, None),
+        Ok(_) => panic!("an unreachable receiver should not count as delivered"),
+    }
+}
+
+#[test]
+fn test_deliver_refuses_private_endpoints() {
+    use crate::webhooks::deliver;
+
+    let (delivery, endpoint) = fixtures("http://169.254.169.254/latest/meta-data".to_string());
+    match deliver(&delivery, &endpoint) {
+        Err((status, _)) => assert_eq!(status, None),
+        Ok(_) => panic!("a link-local endpoint should not be called"),
+    }
+}
+
+#[test]
+fn test_deliver_does_not_follow_redirects() {
+    use crate::webhooks::{allow_private_endpoints, deliver};
+
+    allow_private_endpoints();
+    let (url, rx) = receiver(302);
+    let (delivery, endpoint) = fixtures(url);
+    match deliver(&delivery, &endpoint) {
+        Err((status, _)) => assert_eq!(status, Some(302)),
+        Ok(_) => panic!("a redirect should not count as delivered"),
+    }
+    rx.recv().unwrap();
+}